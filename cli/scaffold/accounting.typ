@@ -0,0 +1,104 @@
+// {{title}} - Accounting Layout
+// Generated by `docgen template new`. Labels for all languages are in
+// locale.json, the data fields in schema.json.
+
+#import "../common/footers.typ": accounting-footer
+#import "../common/formatting.typ": format_german_date
+#import "../common/din5008-address.typ": din5008-address-block
+#import "../common/accounting-header.typ": accounting-header
+
+// Load data from JSON input
+#let data = json(sys.inputs.data)
+
+// Load company data
+#let company = json(if "company" in sys.inputs { sys.inputs.company } else { "/data/company.json" })
+
+// Labels in the company's language
+#let labels = json("locale.json")
+#let lang = if "language" in company and company.language in labels { company.language } else { "de" }
+#let l = labels.at(lang)
+
+#let show-footer = if "show_footer" in data.metadata { data.metadata.show_footer } else { true }
+
+#set page(
+  paper: "a4",
+  margin: (left: 50pt, right: 45pt, top: 50pt, bottom: 80pt),
+
+  footer: if show-footer { accounting-footer(company: company) }
+)
+
+#set text(
+  font: "Helvetica",
+  size: 10pt,
+  lang: lang
+)
+
+// ============================================================================
+// HEADER SECTION
+// ============================================================================
+
+#accounting-header(
+  company: company,
+  metadata_content: [
+    *#l.number:* #data.metadata.document_number\
+    *#l.date:* #format_german_date(data.metadata.date)\
+    #if "customer_number" in data.metadata [
+      *#l.customer_number:* #data.metadata.customer_number\
+    ]
+  ]
+)
+
+// ============================================================================
+// RECIPIENT ADDRESS (DIN 5008 position at y=160)
+// ============================================================================
+
+#din5008-address-block(
+  company: company,
+  recipient: data.recipient,
+)
+
+#text(size: 14pt, weight: "bold")[#l.title]
+
+#v(10pt)
+
+#if "intro" in data [
+  #data.intro
+
+  #v(10pt)
+]
+
+// ============================================================================
+// ITEMS TABLE SECTION
+// ============================================================================
+
+#if "items" in data and data.items.len() > 0 [
+  #set text(size: 9pt)
+
+  #table(
+    columns: (35pt, 1fr, 150pt),
+    align: (center, left, left),
+    stroke: none,
+    row-gutter: 4pt,
+
+    table.header(
+      text(weight: "bold")[#l.position],
+      text(weight: "bold")[#l.description],
+      text(weight: "bold")[#l.notes],
+    ),
+    table.hline(stroke: 0.5pt),
+
+    ..data.items.enumerate().map(((i, item)) => (
+      [#if "position" in item { item.position } else { i + 1 }],
+      [#item.description],
+      [#if "notes" in item { item.notes }],
+    )).flatten(),
+
+    table.hline(stroke: 0.5pt),
+  )
+]
+
+#if "closing" in data [
+  #v(12pt)
+
+  #data.closing
+]
@@ -0,0 +1,179 @@
+// {{title}} - Document Layout
+// Generated by `docgen template new`. Labels for all languages are in
+// locale.json, the data fields in schema.json.
+
+#import "../common/styles.typ": *
+#import "../common/footers.typ": document-footer
+#import "../common/title-page.typ": document-title-page
+
+// Labels by language
+#let labels = json("locale.json")
+
+// Document function
+#let {{function}}(
+  title: none,
+  document_number: none,
+  client_name: none,
+  project_name: none,
+  version: "1.0",
+  status: none,
+  created_at: none,
+  show_toc: true,
+  company: none,
+  locale: none,
+  logo: none,
+  show-title-page: true,
+  show-footer: true,
+  lang: "de",
+  body
+) = {
+  let company = if company != none { company } else { (:) }
+  let l = labels.at(lang, default: labels.de)
+  let title = if title != none { title } else { l.title }
+  let status = if status != none { status } else { l.draft }
+
+  let accent-color = get-accent-color(company)
+  let fonts = get-font-preset(company)
+
+  set page(
+    paper: "a4",
+    margin: (left: 50pt, right: 45pt, top: 50pt, bottom: 80pt),
+
+    footer: if show-footer { document-footer(
+      company: company,
+      locale: locale,
+      document_number: document_number,
+      created_at: created_at,
+      status: status,
+    ) } else { none }
+  )
+
+  set text(font: fonts.body, size: size-medium, lang: lang)
+  set par(justify: true, leading: 0.65em)
+  set heading(numbering: "1.")
+
+  show heading.where(level: 1): it => {
+    v(18pt)
+    text(size: size-xlarge, weight: "bold", fill: accent-color)[#it]
+    v(8pt)
+  }
+
+  show heading.where(level: 2): it => {
+    v(12pt)
+    text(size: size-large, weight: "bold")[#it]
+    v(6pt)
+  }
+
+  // ============================================================================
+  // TITLE PAGE
+  // ============================================================================
+
+  if show-title-page {
+    let title-metadata = (:)
+    if client_name != none {
+      title-metadata.insert(l.client, client_name)
+    }
+    if project_name != none {
+      title-metadata.insert(l.project, project_name)
+    }
+    title-metadata.insert(l.version, version)
+    if created_at != none {
+      title-metadata.insert(l.date, created_at)
+    }
+
+    document-title-page(
+      company: company,
+      logo: logo,
+      title: title,
+      document-type: upper(l.title),
+      document-number: document_number,
+      accent-color: accent-color,
+      metadata: title-metadata,
+      tags: none,
+    )
+
+    pagebreak()
+  }
+
+  // ============================================================================
+  // TABLE OF CONTENTS (optional)
+  // ============================================================================
+
+  if show_toc {
+    outline(
+      title: [
+        #set text(size: size-large, weight: "bold")
+        #l.contents
+      ],
+      indent: 1em,
+      depth: 2,
+    )
+
+    pagebreak()
+  }
+
+  // ============================================================================
+  // MAIN CONTENT
+  // ============================================================================
+
+  body
+}
+
+// JSON workflow
+#let data = if "data" in sys.inputs {
+  json(sys.inputs.data)
+} else {
+  none
+}
+
+#let _company = if data != none and "company" in sys.inputs {
+  let c = json(sys.inputs.company)
+  if "logo" in c and c.logo != none {
+    let logo-width = if "logo_width" in c { eval(c.logo_width) } else { 150pt }
+    c.insert("_logo_image", image("/" + c.logo, width: logo-width))
+  }
+  c
+} else {
+  none
+}
+
+#let _locale = if data != none and "locale" in sys.inputs {
+  json(sys.inputs.locale)
+} else {
+  none
+}
+
+#if data != none {
+  let created-date = if "created_at" in data.metadata {
+    if type(data.metadata.created_at) == dictionary and "date" in data.metadata.created_at {
+      data.metadata.created_at.date
+    } else {
+      data.metadata.created_at
+    }
+  } else {
+    none
+  }
+
+  let content-body = if "content_file" in data {
+    include(data.content_file)
+  } else if "content" in data {
+    eval(data.content, mode: "markup")
+  } else {
+    []
+  }
+
+  {{function}}(
+    title: if "title" in data.metadata { data.metadata.title } else { none },
+    document_number: data.metadata.document_number,
+    client_name: if "client_name" in data.metadata { data.metadata.client_name } else { none },
+    project_name: if "project_name" in data.metadata { data.metadata.project_name } else { none },
+    version: if "version" in data.metadata { data.metadata.version } else { "1.0" },
+    status: if "status" in data.metadata { data.metadata.status } else { none },
+    created_at: created-date,
+    show_toc: if "show_toc" in data.metadata { data.metadata.show_toc } else { true },
+    company: _company,
+    locale: _locale,
+    lang: if _company != none and "language" in _company and _company.language in labels { _company.language } else { "de" },
+    content-body
+  )
+}
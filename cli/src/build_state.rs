@@ -0,0 +1,242 @@
+// Dependency manifest for incremental builds (.docgen/build-state.json)
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::data::journal::sha256_file;
+use crate::data::write_atomic;
+use crate::render::page_files;
+
+pub const STATE_PATH: &str = ".docgen/build-state.json";
+
+/// Entry recording the output format options next to the file hashes
+pub const OPTIONS_KEY: &str = "(options)";
+
+/// Recorded in place of a hash for dependencies that did not exist
+const MISSING: &str = "missing";
+
+/// Calls in .typ sources whose string argument names a file
+const REFERENCES: [&str; 9] = [
+    "import \"",
+    "include \"",
+    "json(\"",
+    "yaml(\"",
+    "toml(\"",
+    "csv(\"",
+    "xml(\"",
+    "read(\"",
+    "image(\"",
+];
+
+/// Hashes of everything a PDF was built from, keyed by output path
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BuildState {
+    #[serde(default)]
+    outputs: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl BuildState {
+    /// Previous state; a missing or unreadable manifest means a full build
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Whether `output` exists and was built from exactly these inputs
+    pub fn is_fresh(&self, output: &Path, hashes: &BTreeMap<String, String>) -> bool {
+        !page_files(output).is_empty() && self.outputs.get(&key(output)) == Some(hashes)
+    }
+
+    pub fn record(&mut self, output: &Path, hashes: BTreeMap<String, String>) {
+        self.outputs.insert(key(output), hashes);
+    }
+
+    pub fn forget(&mut self, output: &Path) {
+        self.outputs.remove(&key(output));
+    }
+}
+
+/// File hashes shared by all documents of one build
+#[derive(Default)]
+pub struct Hasher {
+    cache: HashMap<PathBuf, String>,
+}
+
+impl Hasher {
+    pub fn hash(&mut self, path: &Path) -> String {
+        self.cache
+            .entry(path.to_path_buf())
+            .or_insert_with(|| sha256_file(path).unwrap_or_else(|_| MISSING.to_string()))
+            .clone()
+    }
+
+    /// Hashes of all dependencies of a document
+    pub fn hashes(&mut self, dependencies: &[PathBuf]) -> BTreeMap<String, String> {
+        dependencies
+            .iter()
+            .map(|path| (key(path), self.hash(path)))
+            .collect()
+    }
+}
+
+/// Files a document is built from
+///
+/// The input, company.json with its logo, the locale file, all fonts, and
+/// every file referenced from the entry point (the template for JSON
+/// documents, the input itself for .typ files) and the files it imports.
+pub fn dependencies(input: &Path, entry: &Path, locale: &Path) -> Vec<PathBuf> {
+    let company = PathBuf::from("data/company.json");
+    let mut deps = vec![input.to_path_buf(), company.clone(), locale.to_path_buf()];
+    if let Some(logo) = company_logo(&company) {
+        deps.push(logo);
+    }
+    deps.extend(
+        WalkDir::new("fonts")
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path()),
+    );
+
+    let mut seen = HashSet::new();
+    let mut queue = vec![normalize(entry)];
+    while let Some(file) = queue.pop() {
+        if !seen.insert(file.clone()) {
+            continue;
+        }
+        if file.extension().is_some_and(|ext| ext == "typ") {
+            if let Ok(source) = fs::read_to_string(&file) {
+                queue.extend(references(&source).iter().map(|r| resolve(&file, r)));
+            }
+        }
+        if !file.is_dir() && !deps.contains(&file) {
+            deps.push(file);
+        }
+    }
+    deps
+}
+
+/// Root-relative logo path from company.json, if configured
+fn company_logo(company: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(company).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let logo = json.get("logo")?.as_str()?;
+    Some(normalize(Path::new(logo.trim_start_matches('/'))))
+}
+
+/// String paths passed to file-reading calls, skipping packages
+fn references(source: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    for pattern in REFERENCES {
+        for (start, _) in source.match_indices(pattern) {
+            let rest = &source[start + pattern.len()..];
+            if let Some(end) = rest.find('"') {
+                let path = &rest[..end];
+                if !path.is_empty() && !path.starts_with('@') && !path.contains('\n') {
+                    found.push(path);
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Resolve a reference like typst does: "/..." from the project root,
+/// anything else relative to the referencing file
+fn resolve(from: &Path, reference: &str) -> PathBuf {
+    match reference.strip_prefix('/') {
+        Some(rooted) => normalize(Path::new(rooted)),
+        None => normalize(&from.parent().unwrap_or(Path::new("")).join(reference)),
+    }
+}
+
+/// Remove "." and ".." components without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn key(path: &Path) -> String {
+    normalize(path).to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references_and_resolve() {
+        let source = r#"
+#import "../common/footers.typ": accounting-footer
+#import "@preview/tablex:0.0.8": tablex
+#let company = json("/data/company.json")
+#image("/data/logo.png", width: 3cm)
+#let dynamic = image("/" + company.logo)
+"#;
+        let refs = references(source);
+        assert_eq!(
+            refs,
+            vec![
+                "../common/footers.typ",
+                "/data/company.json",
+                "/data/logo.png",
+                "/"
+            ]
+        );
+
+        let template = Path::new(".docgen/templates/invoice/default.typ");
+        assert_eq!(
+            resolve(template, refs[0]),
+            PathBuf::from(".docgen/templates/common/footers.typ")
+        );
+        assert_eq!(
+            resolve(template, refs[1]),
+            PathBuf::from("data/company.json")
+        );
+    }
+
+    #[test]
+    fn test_changed_dependency_makes_output_stale() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dep = tmp.path().join("company.json");
+        let output = tmp.path().join("out.pdf");
+        fs::write(&dep, "{}").unwrap();
+        fs::write(&output, "pdf").unwrap();
+
+        let mut state = BuildState::default();
+        let hashes = Hasher::default().hashes(std::slice::from_ref(&dep));
+        state.record(&output, hashes.clone());
+        assert!(state.is_fresh(&output, &hashes));
+
+        fs::write(&dep, r#"{"bank": "new"}"#).unwrap();
+        let changed = Hasher::default().hashes(&[dep]);
+        assert!(!state.is_fresh(&output, &changed));
+
+        fs::remove_file(&output).unwrap();
+        assert!(!state.is_fresh(&output, &hashes));
+    }
+}
@@ -0,0 +1,308 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::data::documents::document_number;
+use crate::data::journal::{already_issued, sha256_file, Issue, Journal, JournalEntry};
+
+/// Compile a document and record it in the journal as issued
+pub fn issue(input: &Path, pdf: Option<PathBuf>, template: Option<String>) -> Result<()> {
+    if input.extension().is_none_or(|ext| ext != "json") {
+        anyhow::bail!("Only JSON documents can be issued: {}", input.display());
+    }
+    let content =
+        fs::read_to_string(input).with_context(|| format!("Failed to read {}", input.display()))?;
+    let json: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", input.display()))?;
+
+    let number = document_number(&json)
+        .with_context(|| format!("{} has no document number", input.display()))?;
+    let doc_type = template
+        .clone()
+        .or_else(|| crate::document_type(input))
+        .context("Cannot detect document type, use --template")?;
+
+    // Fail early without compiling; `append` checks again under the lock
+    let journal = Journal::default();
+    if let Some(entry) = journal.find(&doc_type, &number)? {
+        return Err(already_issued(&entry));
+    }
+
+    let pdf = match pdf {
+        Some(pdf) => {
+            if !pdf.exists() {
+                anyhow::bail!("PDF not found: {}", pdf.display());
+            }
+            pdf
+        }
+        None => {
+            let pdf = input.with_extension("pdf");
+            crate::compile_document(
+                input,
+                Some(pdf.clone()),
+                template,
+                None,
+                false,
+                &Default::default(),
+            )?;
+            pdf
+        }
+    };
+
+    let entry = journal.append(Issue {
+        doc_type,
+        number,
+        source: input.to_path_buf(),
+        pdf: Some(pdf),
+    })?;
+
+    println!(
+        "{} Issued {} (journal entry #{})",
+        "✓".green(),
+        entry.number.cyan(),
+        entry.seq
+    );
+    println!("  JSON {}  {}", &entry.source_sha256[..16], entry.source);
+    if let (Some(pdf), Some(hash)) = (&entry.pdf, &entry.pdf_sha256) {
+        println!("  PDF  {}  {}", &hash[..16], pdf);
+    }
+    Ok(())
+}
+
+/// Verify the journal, the issued files and the number sequences
+pub fn audit(path: &Path) -> Result<()> {
+    let journal = Journal::default();
+    let mut problems = 0;
+    let mut report = |message: String| {
+        println!("{} {}", "✗".red(), message);
+        problems += 1;
+    };
+
+    println!("{}", "Journal".bold());
+    for error in journal.verify()? {
+        report(format!(
+            "{} line {}: {}",
+            journal.path().display(),
+            error.line,
+            error.message
+        ));
+    }
+
+    let entries = journal.entries()?;
+    for entry in &entries {
+        for problem in check_files(entry) {
+            report(problem);
+        }
+    }
+
+    println!();
+    println!("{}", "Numbering".bold());
+    for problem in check_sequences(&entries) {
+        report(problem);
+    }
+    for problem in check_duplicates_on_disk(path)? {
+        report(problem);
+    }
+
+    println!();
+    if problems > 0 {
+        anyhow::bail!("Audit found {} problem(s)", problems);
+    }
+    println!(
+        "{} {} issued document(s) verified, journal intact",
+        "✓".green(),
+        entries.len()
+    );
+    Ok(())
+}
+
+/// Compare the source JSON and PDF of an issued document with the journal
+fn check_files(entry: &JournalEntry) -> Vec<String> {
+    let mut problems = Vec::new();
+    let source = Path::new(&entry.source);
+    match sha256_file(source) {
+        Err(_) if !source.exists() => problems.push(format!(
+            "{}: issued document {} was deleted",
+            entry.number, entry.source
+        )),
+        Err(e) => problems.push(format!("{}: {}", entry.number, e)),
+        Ok(hash) if hash != entry.source_sha256 => problems.push(format!(
+            "{}: {} was edited after it was issued",
+            entry.number, entry.source
+        )),
+        Ok(_) => {}
+    }
+
+    if let (Some(pdf), Some(recorded)) = (&entry.pdf, &entry.pdf_sha256) {
+        match sha256_file(Path::new(pdf)) {
+            Err(_) => problems.push(format!("{}: PDF {} is missing", entry.number, pdf)),
+            Ok(hash) if &hash != recorded => problems.push(format!(
+                "{}: PDF {} no longer matches its recorded hash",
+                entry.number, pdf
+            )),
+            Ok(_) => {}
+        }
+    }
+    problems
+}
+
+/// Split "RE-2025-042" into ("RE-2025-", 42, 3)
+fn split_number(number: &str) -> Option<(&str, u64, usize)> {
+    let digits = number.len() - number.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let (prefix, seq) = number.split_at(number.len() - digits);
+    Some((prefix, seq.parse().ok()?, digits))
+}
+
+/// Gaps and duplicates in the issued numbers of each document type
+///
+/// Numbers are checked per prefix (e.g. per year), from the first journaled
+/// number on: documents issued before the journal was started don't count
+/// as missing.
+fn check_sequences(entries: &[JournalEntry]) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    // (doc_type, prefix) → (digit width, sequence numbers)
+    let mut groups: BTreeMap<(&str, &str), (usize, Vec<u64>)> = BTreeMap::new();
+    let mut seen: HashMap<(&str, &str), usize> = HashMap::new();
+    for entry in entries {
+        *seen
+            .entry((entry.doc_type.as_str(), entry.number.as_str()))
+            .or_default() += 1;
+        if let Some((prefix, seq, width)) = split_number(&entry.number) {
+            let group = groups.entry((&entry.doc_type, prefix)).or_default();
+            group.0 = width;
+            group.1.push(seq);
+        }
+    }
+
+    let mut duplicates: Vec<_> = seen.into_iter().filter(|(_, n)| *n > 1).collect();
+    duplicates.sort();
+    for ((doc_type, number), count) in duplicates {
+        problems.push(format!(
+            "{} {} was issued {} times",
+            doc_type, number, count
+        ));
+    }
+
+    for ((doc_type, prefix), (width, mut seqs)) in groups {
+        seqs.sort_unstable();
+        seqs.dedup();
+        let missing: Vec<String> = (seqs[0]..=*seqs.last().unwrap())
+            .filter(|n| seqs.binary_search(n).is_err())
+            .map(|n| format!("{}{:0width$}", prefix, n, width = width))
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!(
+                "{}: missing number(s) {}",
+                doc_type,
+                missing.join(", ")
+            ));
+        }
+    }
+    problems
+}
+
+/// Documents in the tree that share a number with another document of the same type
+fn check_duplicates_on_disk(path: &Path) -> Result<Vec<String>> {
+    let mut numbers: BTreeMap<(String, String), Vec<PathBuf>> = BTreeMap::new();
+    for entry in WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+    {
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) else {
+            continue;
+        };
+        if let (Some(number), Some(doc_type)) =
+            (document_number(&json), crate::document_type(entry.path()))
+        {
+            numbers
+                .entry((doc_type, number))
+                .or_default()
+                .push(entry.path().to_path_buf());
+        }
+    }
+
+    Ok(numbers
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|((_, number), paths)| {
+            let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+            format!(
+                "{} is used by {} documents: {}",
+                number,
+                paths.len(),
+                paths.join(", ")
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(doc_type: &str, number: &str) -> JournalEntry {
+        JournalEntry {
+            seq: 0,
+            issued_at: String::new(),
+            doc_type: doc_type.to_string(),
+            number: number.to_string(),
+            source: String::new(),
+            source_sha256: String::new(),
+            pdf: None,
+            pdf_sha256: None,
+            prev: String::new(),
+            hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_split_number() {
+        assert_eq!(split_number("RE-2025-042"), Some(("RE-2025-", 42, 3)));
+        assert_eq!(split_number("M1-2025-001"), Some(("M1-2025-", 1, 3)));
+        assert_eq!(split_number("DRAFT"), None);
+    }
+
+    #[test]
+    fn test_sequences_per_prefix() {
+        // Reset per year: complete
+        let entries = vec![
+            entry("credit-note", "GS-2024-001"),
+            entry("credit-note", "GS-2024-002"),
+            entry("credit-note", "GS-2025-001"),
+        ];
+        assert!(check_sequences(&entries).is_empty());
+
+        // Numbers before the first journaled one are not missing
+        let entries = vec![
+            entry("invoice", "RE-2024-041"),
+            entry("invoice", "RE-2024-042"),
+            entry("invoice", "RE-2025-004"),
+            entry("invoice", "RE-2025-006"),
+        ];
+        let problems = check_sequences(&entries);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].ends_with("missing number(s) RE-2025-005"));
+    }
+
+    #[test]
+    fn test_sequences_duplicates() {
+        let entries = vec![
+            entry("invoice", "RE-2025-001"),
+            entry("invoice", "RE-2025-002"),
+            entry("invoice", "RE-2025-002"),
+        ];
+        let problems = check_sequences(&entries);
+        assert_eq!(problems, ["invoice RE-2025-002 was issued 2 times"]);
+    }
+}
@@ -0,0 +1,298 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::thread;
+
+use crate::build_state::{self, BuildState, Hasher};
+use crate::discovery::Discovery;
+use crate::layout::{self, Layout};
+use crate::local_templates;
+use crate::locale::{t, tf};
+use crate::render::{self, RenderOptions};
+
+/// A document whose PDF is missing or out of date
+struct Job {
+    input: PathBuf,
+    output: PathBuf,
+    hashes: BTreeMap<String, String>,
+}
+
+/// Command line options of `docgen build`
+pub struct BuildOptions {
+    /// Worker threads (default: number of CPUs)
+    pub jobs: Option<usize>,
+    /// Rebuild documents that are up to date
+    pub force: bool,
+    /// Output path pattern overriding company.json
+    pub layout: Option<String>,
+    /// Only build documents matching one of these globs
+    pub include: Vec<String>,
+    /// Skip documents matching one of these globs
+    pub exclude: Vec<String>,
+    /// Output format, resolution and pages
+    pub render: RenderOptions,
+}
+
+/// Result of one build pass
+#[derive(Default)]
+pub struct Outcome {
+    /// All documents found as (input, PDF), including up-to-date ones
+    pub documents: Vec<(PathBuf, PathBuf)>,
+    /// Compiled documents as (input, PDF)
+    pub built: Vec<(PathBuf, PathBuf)>,
+    /// Failed documents with their error
+    pub failed: Vec<(PathBuf, String)>,
+    pub up_to_date: usize,
+}
+
+/// Build all documents below `path` on a pool of worker threads
+///
+/// Documents whose dependencies are unchanged since the last build are
+/// skipped unless `force` is set.
+pub fn run(path: &Path, output: &Path, options: BuildOptions) -> Result<()> {
+    println!(
+        "{} {}",
+        "→".blue(),
+        tf("build", "building_all", &[&path.display().to_string()])
+    );
+
+    // Sync templates once instead of before every document
+    local_templates::ensure_local_templates_updated()?;
+
+    let outcome = build(path, output, &options)?;
+    if !outcome.failed.is_empty() {
+        anyhow::bail!(
+            "{}",
+            tf("build", "failed", &[&outcome.failed.len().to_string()])
+        );
+    }
+    Ok(())
+}
+
+/// Compile the stale documents below `path` and print a summary
+///
+/// Templates must already be synced; `watch` calls this after every change.
+pub fn build(path: &Path, output: &Path, options: &BuildOptions) -> Result<Outcome> {
+    let layout = Layout::configured(options.layout.clone())?;
+    options.render.validate()?;
+    std::fs::create_dir_all(output)?;
+
+    let inputs = Discovery::new(&options.include, &options.exclude)?
+        .prune(output)
+        .find(path);
+
+    // Refuse to build before any PDF would be overwritten by another document
+    let outputs = inputs
+        .into_iter()
+        .map(|input| {
+            let pdf = output.join(layout.output_path(path, &input)?);
+            Ok((input, options.render.output_path(&pdf)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let collisions = layout::collisions(&outputs);
+    if !collisions.is_empty() {
+        for (pdf, inputs) in &collisions {
+            let inputs: Vec<String> = inputs.iter().map(|i| i.display().to_string()).collect();
+            println!("{} {} ← {}", "✗".red(), pdf.display(), inputs.join(", "));
+        }
+        anyhow::bail!(
+            "{} output file(s) would be written by more than one document. \
+             Adjust structure.output_pattern in data/company.json or --layout.",
+            collisions.len()
+        );
+    }
+
+    let state_path = Path::new(build_state::STATE_PATH);
+    let mut state = BuildState::load(state_path);
+    let locale = PathBuf::from(crate::locale_path().trim_start_matches('/'));
+    let mut hasher = Hasher::default();
+    let mut up_to_date = 0;
+    let mut stale = Vec::new();
+    let documents = outputs.clone();
+    for (input, output) in outputs {
+        let entry = if input.extension().is_some_and(|ext| ext == "typ") {
+            input.clone()
+        } else {
+            // Unknown templates are reported when the document is compiled
+            crate::template_path(&input, None, None).unwrap_or_else(|_| input.clone())
+        };
+        let mut hashes = hasher.hashes(&build_state::dependencies(&input, &entry, &locale));
+        hashes.insert(
+            build_state::OPTIONS_KEY.to_string(),
+            options.render.fingerprint(),
+        );
+        if !options.force && state.is_fresh(&output, &hashes) {
+            up_to_date += 1;
+        } else {
+            stale.push(Job {
+                input,
+                output,
+                hashes,
+            });
+        }
+    }
+
+    let jobs = options
+        .jobs
+        .filter(|&n| n > 0)
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1);
+
+    let mut outcome = Outcome {
+        documents,
+        up_to_date,
+        ..Outcome::default()
+    };
+    run_ordered(
+        &stale,
+        jobs,
+        |job| {
+            if let Some(parent) = job.output.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            crate::render_document(&job.input, &job.output, None, None, &options.render)
+        },
+        |job, result| {
+            println!(
+                "{} {}",
+                "→".blue(),
+                tf(
+                    "compile",
+                    "compiling",
+                    &[
+                        &job.input.display().to_string(),
+                        &job.output.display().to_string()
+                    ]
+                )
+            );
+            match result {
+                Ok(()) => {
+                    println!("{} {}", "✓".green(), t("compile", "success"));
+                    state.record(&job.output, job.hashes.clone());
+                    outcome.built.push((job.input.clone(), job.output.clone()));
+                }
+                Err(e) => {
+                    println!(
+                        "{} {}: {}",
+                        "✗".red(),
+                        tf("compile", "error_at", &[&job.input.display().to_string()]),
+                        e
+                    );
+                    state.forget(&job.output);
+                    outcome.failed.push((job.input.clone(), e.to_string()));
+                }
+            }
+        },
+    );
+
+    state.save(state_path)?;
+
+    println!();
+    if outcome.up_to_date > 0 {
+        println!(
+            "{} {}",
+            "·".dimmed(),
+            tf("build", "up_to_date", &[&outcome.up_to_date.to_string()])
+        );
+    }
+    if !outcome.failed.is_empty() {
+        println!("{}", t("build", "failed_documents").bold());
+        for (input, error) in &outcome.failed {
+            // The first diagnostic is enough to find the document again
+            println!(
+                "  {} {}: {}",
+                "✗".red(),
+                input.display(),
+                render::first_diagnostic(error)
+            );
+        }
+        println!();
+    }
+    println!(
+        "{} {}",
+        if outcome.failed.is_empty() {
+            "✓".green()
+        } else {
+            "!".yellow()
+        },
+        tf(
+            "build",
+            "documents_created",
+            &[
+                &outcome.built.len().to_string(),
+                &outcome.failed.len().to_string()
+            ]
+        )
+    );
+
+    Ok(outcome)
+}
+
+/// Run `work` for every item on up to `jobs` threads
+///
+/// `report` is called on the calling thread in the order of `items`, as soon
+/// as an item and all items before it are done.
+fn run_ordered<T, R, W, F>(items: &[T], jobs: usize, work: W, mut report: F)
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&T) -> R + Sync,
+    F: FnMut(&T, R),
+{
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = channel();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            let tx = tx.clone();
+            let (next, work) = (&next, &work);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                if tx.send((index, work(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        let mut pending: Vec<Option<R>> = items.iter().map(|_| None).collect();
+        let mut reported = 0;
+        for (index, result) in rx {
+            pending[index] = Some(result);
+            while let Some(result) = pending.get_mut(reported).and_then(Option::take) {
+                report(&items[reported], result);
+                reported += 1;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_run_ordered_reports_in_input_order() {
+        let items: Vec<u64> = (0..20).collect();
+        let mut seen = Vec::new();
+        run_ordered(
+            &items,
+            4,
+            |&n| {
+                // Later items finish first
+                thread::sleep(Duration::from_millis(20 - n));
+                n * 2
+            },
+            |&n, doubled| seen.push((n, doubled)),
+        );
+        let expected: Vec<(u64, u64)> = items.iter().map(|&n| (n, n * 2)).collect();
+        assert_eq!(seen, expected);
+    }
+}
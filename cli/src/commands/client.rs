@@ -1,17 +1,41 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
 use colored::Colorize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 
-use crate::data::{ClientStore, CounterStore, NewClient, ProjectStore};
+use crate::data::exchange;
+use crate::data::{self, NewClient, Storage};
 use crate::locale::{t, tf};
 use crate::ClientAction;
 
+/// File format for client import/export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ContactFormat {
+    Csv,
+    /// vCard (.vcf)
+    Vcf,
+    Json,
+}
+
+impl ContactFormat {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "csv" | "txt" => Some(ContactFormat::Csv),
+            "vcf" | "vcard" => Some(ContactFormat::Vcf),
+            "json" => Some(ContactFormat::Json),
+            _ => None,
+        }
+    }
+}
+
 pub fn handle(action: ClientAction) -> Result<()> {
-    let client_store = ClientStore::default();
-    let mut counter_store = CounterStore::default();
+    let mut storage = data::open()?;
 
     match action {
         ClientAction::List => {
-            let clients = client_store.list()?;
+            let clients = storage.list_clients()?;
             if clients.is_empty() {
                 println!("{}", t("client", "no_clients"));
                 println!("{}", t("client", "create_with"));
@@ -39,7 +63,7 @@ pub fn handle(action: ClientAction) -> Result<()> {
                 ..Default::default()
             };
 
-            let client = client_store.add(new_client, &mut counter_store)?;
+            let client = storage.add_client(new_client)?;
             println!(
                 "{} {}",
                 "✓".green(),
@@ -47,13 +71,12 @@ pub fn handle(action: ClientAction) -> Result<()> {
             );
         }
         ClientAction::Show { id } => {
-            let client_id = parse_client_id(&client_store, &id)?;
-            let client = client_store
-                .get(client_id)?
+            let client_id = parse_client_id(storage.as_ref(), &id)?;
+            let client = storage
+                .get_client(client_id)?
                 .ok_or_else(|| anyhow::anyhow!("Client not found"))?;
 
-            let project_store = ProjectStore::default();
-            let projects = project_store.list_by_client(client_id)?;
+            let projects = storage.list_projects_by_client(client_id)?;
 
             println!();
             println!(
@@ -80,9 +103,9 @@ pub fn handle(action: ClientAction) -> Result<()> {
             }
         }
         ClientAction::Delete { id } => {
-            let client_id = parse_client_id(&client_store, &id)?;
-            let client = client_store
-                .get(client_id)?
+            let client_id = parse_client_id(storage.as_ref(), &id)?;
+            let client = storage
+                .get_client(client_id)?
                 .ok_or_else(|| anyhow::anyhow!("Client not found"))?;
 
             println!(
@@ -93,8 +116,7 @@ pub fn handle(action: ClientAction) -> Result<()> {
             );
 
             // Check if client has projects
-            let project_store = ProjectStore::default();
-            let projects = project_store.list_by_client(client_id)?;
+            let projects = storage.list_projects_by_client(client_id)?;
 
             if !projects.is_empty() {
                 println!(
@@ -105,19 +127,185 @@ pub fn handle(action: ClientAction) -> Result<()> {
                 anyhow::bail!("Cannot delete client with existing projects");
             }
 
-            client_store.delete(client_id)?;
+            storage.delete_client(client_id)?;
             println!("{} Client deleted successfully", "✓".green());
         }
+        ClientAction::Import {
+            file,
+            format,
+            map,
+            dry_run,
+            allow_duplicates,
+        } => {
+            let format = format
+                .or_else(|| ContactFormat::from_path(&file))
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Cannot detect format of {}, use --format", file.display())
+                })?;
+            let content = fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+
+            let candidates = match format {
+                ContactFormat::Csv => {
+                    let mapping = match &map {
+                        Some(map) => exchange::parse_mapping(map)?,
+                        None => HashMap::new(),
+                    };
+                    exchange::read_csv(&content, &mapping)?
+                }
+                ContactFormat::Vcf => exchange::read_vcards(&content)?,
+                ContactFormat::Json => read_json(&content)
+                    .with_context(|| format!("Failed to parse {}", file.display()))?,
+            };
+
+            import(storage.as_mut(), candidates, dry_run, allow_duplicates)?;
+        }
+        ClientAction::Export { format, output } => {
+            let clients = storage.list_clients()?;
+            let content = match format {
+                ContactFormat::Csv => exchange::write_csv(&clients)?,
+                ContactFormat::Vcf => exchange::write_vcards(&clients),
+                ContactFormat::Json => serde_json::to_string_pretty(&clients)? + "\n",
+            };
+
+            match output {
+                Some(path) => {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&path, content)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    println!(
+                        "{} Exported {} client(s) to {}",
+                        "✓".green(),
+                        clients.len(),
+                        path.display()
+                    );
+                }
+                None => print!("{}", content),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Add imported clients, skipping rows without a name and likely duplicates
+fn import(
+    storage: &mut dyn Storage,
+    candidates: Vec<NewClient>,
+    dry_run: bool,
+    allow_duplicates: bool,
+) -> Result<()> {
+    let existing = storage.list_clients()?;
+    // (label, email, company) of everything already known, including this import
+    let mut known: Vec<(String, Option<String>, Option<String>)> = existing
+        .iter()
+        .map(|c| (c.formatted_number(), c.email.clone(), c.company.clone()))
+        .collect();
+
+    let mut imported = 0;
+    let mut skipped = 0;
+
+    for (row, mut candidate) in candidates.into_iter().enumerate() {
+        if candidate.name.is_empty() {
+            candidate.name = candidate.company.clone().unwrap_or_default();
+        }
+        if candidate.name.is_empty() {
+            println!(
+                "{} Entry {}: no name or company, skipped",
+                "⚠".yellow(),
+                row + 1
+            );
+            skipped += 1;
+            continue;
+        }
+
+        let duplicate =
+            exchange::find_duplicate(&candidate, known.iter().map(|(l, e, c)| (l.as_str(), e, c)))
+                .map(str::to_string);
+        if let Some(other) = &duplicate {
+            if !allow_duplicates {
+                println!(
+                    "{} {}: looks like a duplicate of {}, skipped",
+                    "⚠".yellow(),
+                    candidate.name,
+                    other.cyan()
+                );
+                skipped += 1;
+                continue;
+            }
+        }
+
+        let label = if dry_run {
+            let label = format!("row {}", row + 1);
+            println!(
+                "{} {:30} │ {:20} │ {}",
+                "→".blue(),
+                candidate.name,
+                candidate.city.clone().unwrap_or_default(),
+                candidate.email.clone().unwrap_or_default()
+            );
+            label
+        } else {
+            let client = storage.add_client(candidate.clone())?;
+            println!(
+                "{} {} {}",
+                "✓".green(),
+                client.formatted_number().cyan(),
+                client.display_name()
+            );
+            client.formatted_number()
+        };
+        known.push((label, candidate.email, candidate.company));
+        imported += 1;
+    }
+
+    println!();
+    if dry_run {
+        println!(
+            "Dry run: {} client(s) would be imported, {} skipped",
+            imported, skipped
+        );
+    } else {
+        println!("Imported {} client(s), {} skipped", imported, skipped);
     }
     Ok(())
 }
 
+/// JSON import accepts a list of client objects, e.g. from `client export --format json`
+fn read_json(content: &str) -> Result<Vec<NewClient>> {
+    let values: Vec<serde_json::Value> = serde_json::from_str(content)?;
+    Ok(values
+        .iter()
+        .map(|v| {
+            let field = |key: &str| {
+                v.get(key)
+                    .and_then(|s| s.as_str())
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+            };
+            NewClient {
+                name: field("name").unwrap_or_default(),
+                company: field("company"),
+                street: field("street"),
+                house_number: field("house_number"),
+                postal_code: field("postal_code"),
+                city: field("city"),
+                country: field("country"),
+                email: field("email"),
+                phone: field("phone"),
+                notes: field("notes"),
+            }
+        })
+        .collect())
+}
+
 /// Parse client ID from various input formats (K-001, 1, etc.)
-pub fn parse_client_id(store: &ClientStore, input: &str) -> Result<i64> {
+pub fn parse_client_id(storage: &dyn Storage, input: &str) -> Result<i64> {
     // Try direct number
     if let Ok(num) = input.parse::<i64>() {
         // Check if it's a client number or ID
-        let clients = store.list()?;
+        let clients = storage.list_clients()?;
         if let Some(c) = clients.iter().find(|c| c.number == num || c.id == num) {
             return Ok(c.id);
         }
@@ -126,7 +314,7 @@ pub fn parse_client_id(store: &ClientStore, input: &str) -> Result<i64> {
     // Try K-XXX format
     if input.to_uppercase().starts_with("K-") {
         if let Ok(num) = input[2..].parse::<i64>() {
-            let clients = store.list()?;
+            let clients = storage.list_clients()?;
             if let Some(c) = clients.iter().find(|c| c.number == num) {
                 return Ok(c.id);
             }
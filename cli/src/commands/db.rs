@@ -0,0 +1,67 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::path::Path;
+
+use crate::data::{self, storage, Backend};
+use crate::DbAction;
+
+pub fn handle(action: DbAction) -> Result<()> {
+    let data_dir = Path::new("data");
+
+    match action {
+        DbAction::Info => {
+            let storage = data::open()?;
+            let snapshot = storage.snapshot()?;
+            println!(
+                "{} {} ({})",
+                "Storage:".bold(),
+                storage.backend().as_str().cyan(),
+                storage.location()
+            );
+            println!("  Clients:  {}", snapshot.clients.len());
+            println!("  Projects: {}", snapshot.projects.len());
+            for name in data::COUNTER_TYPES {
+                println!(
+                    "  {:14} {}",
+                    format!("{}:", name),
+                    snapshot.counters.get(name).unwrap_or(0)
+                );
+            }
+        }
+        DbAction::Migrate { to, force } => {
+            let from = Backend::configured(data_dir)?;
+            if from == to {
+                println!(
+                    "{} Storage already uses the {} backend",
+                    "✓".green(),
+                    to.as_str()
+                );
+                return Ok(());
+            }
+
+            let source = storage::open_backend(data_dir, from)?;
+            let mut target = storage::open_backend(data_dir, to)?;
+            if !target.is_empty()? && !force {
+                anyhow::bail!(
+                    "{} already contains data. Use --force to overwrite it",
+                    target.location()
+                );
+            }
+
+            let snapshot = source.snapshot()?;
+            target.restore(&snapshot)?;
+            to.save(data_dir)?;
+
+            println!(
+                "{} Migrated {} client(s) and {} project(s) from {} to {}",
+                "✓".green(),
+                snapshot.clients.len(),
+                snapshot.projects.len(),
+                from.as_str(),
+                to.as_str().cyan()
+            );
+            println!("{} Previous data kept in {}", "→".blue(), source.location());
+        }
+    }
+    Ok(())
+}
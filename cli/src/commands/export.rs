@@ -0,0 +1,528 @@
+use anyhow::{Context, Result};
+use chrono::{Datelike, Local};
+use colored::Colorize;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::report::Period;
+use crate::data::documents::{
+    format_decimal, home_country, load_issued_documents, DocumentKind, IssuedDocument, VatCategory,
+};
+use crate::ExportAction;
+
+pub fn handle(action: ExportAction) -> Result<()> {
+    match action {
+        ExportAction::Datev {
+            year,
+            month,
+            path,
+            output,
+        } => {
+            let period = export_period(year, month)?;
+            let config = AccountingConfig::load()?;
+            let documents = load_issued_documents(&path)?;
+            let bookings = bookings(&documents, &period, &config, &home_country());
+
+            let output = output.unwrap_or_else(|| {
+                PathBuf::from("output/exports")
+                    .join(format!("EXTF_Buchungsstapel_{}.csv", period.label))
+            });
+            let content = datev_batch(&bookings, &period, &config);
+            write_export(&output, &encode_windows_1252(&content), bookings.len())
+        }
+        ExportAction::Journal {
+            year,
+            month,
+            path,
+            output,
+        } => {
+            let period = export_period(year, month)?;
+            let config = AccountingConfig::load()?;
+            let documents = load_issued_documents(&path)?;
+            let bookings = bookings(&documents, &period, &config, &home_country());
+
+            let output = output.unwrap_or_else(|| {
+                PathBuf::from("output/exports").join(format!("journal-{}.csv", period.label))
+            });
+            let content = journal_csv(&bookings)?;
+            write_export(&output, content.as_bytes(), bookings.len())
+        }
+    }
+}
+
+fn export_period(year: i32, month: Option<u32>) -> Result<Period> {
+    let label = match month {
+        Some(month) => format!("{}-{:02}", year, month),
+        None => year.to_string(),
+    };
+    label.parse().map_err(|e: String| anyhow::anyhow!(e))
+}
+
+fn write_export(path: &Path, content: &[u8], count: usize) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    println!(
+        "{} {} booking(s) exported to {}",
+        "✓".green(),
+        count,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Chart of accounts used for the default revenue accounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Chart {
+    #[serde(rename = "SKR03")]
+    Skr03,
+    #[serde(rename = "SKR04")]
+    Skr04,
+}
+
+/// The `accounting` block of company.json
+///
+/// ```json
+/// "accounting": {
+///   "chart": "SKR03",
+///   "consultant_number": 1234567,
+///   "client_number": 10001,
+///   "revenue_accounts": { "19": 8400, "7": 8300 }
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountingConfig {
+    #[serde(default = "default_chart")]
+    pub chart: Chart,
+    /// DATEV Beraternummer of the tax advisor
+    #[serde(default)]
+    pub consultant_number: Option<u32>,
+    /// DATEV Mandantennummer
+    #[serde(default)]
+    pub client_number: Option<u32>,
+    /// Length of G/L account numbers (Sachkontenlänge)
+    #[serde(default = "default_account_length")]
+    pub account_length: u32,
+    /// Debtor account = offset + client number (K-007 → 10007)
+    #[serde(default = "default_debtor_offset")]
+    pub debtor_offset: u32,
+    /// Revenue account per VAT rate ("19", "7", "0") or special key
+    /// ("reverse_charge", "eu_reverse_charge", "intra_community", "oss", "export")
+    #[serde(default)]
+    pub revenue_accounts: BTreeMap<String, u32>,
+}
+
+fn default_chart() -> Chart {
+    Chart::Skr03
+}
+
+fn default_account_length() -> u32 {
+    4
+}
+
+fn default_debtor_offset() -> u32 {
+    10000
+}
+
+impl Default for AccountingConfig {
+    fn default() -> Self {
+        Self {
+            chart: default_chart(),
+            consultant_number: None,
+            client_number: None,
+            account_length: default_account_length(),
+            debtor_offset: default_debtor_offset(),
+            revenue_accounts: BTreeMap::new(),
+        }
+    }
+}
+
+impl AccountingConfig {
+    /// Read the `accounting` block from data/company.json (defaults if absent)
+    pub fn load() -> Result<Self> {
+        let path = Path::new("data/company.json");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let company: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        match company.get("accounting") {
+            Some(accounting) => serde_json::from_value(accounting.clone())
+                .context("Invalid \"accounting\" block in data/company.json"),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Revenue account for a VAT category, configured or chart default
+    pub fn revenue_account(&self, category: &VatCategory) -> u32 {
+        let key = match category {
+            VatCategory::Taxable(rate) if rate % 100 == 0 => (rate / 100).to_string(),
+            VatCategory::Taxable(rate) => format!("{}", *rate as f64 / 100.0),
+            VatCategory::TaxFree => "0".to_string(),
+            VatCategory::IntraCommunity => "intra_community".to_string(),
+            VatCategory::EuReverseCharge => "eu_reverse_charge".to_string(),
+            VatCategory::ReverseCharge => "reverse_charge".to_string(),
+            VatCategory::Oss(..) => "oss".to_string(),
+            VatCategory::Export => "export".to_string(),
+        };
+        if let Some(account) = self.revenue_accounts.get(&key) {
+            return *account;
+        }
+
+        let (skr03, skr04) = match category {
+            VatCategory::Taxable(700) => (8300, 4300),
+            VatCategory::Taxable(_) => (8400, 4400),
+            VatCategory::TaxFree => (8120, 4120),
+            VatCategory::IntraCommunity => (8125, 4125),
+            VatCategory::EuReverseCharge => (8336, 4336),
+            VatCategory::ReverseCharge => (8337, 4337),
+            VatCategory::Oss(..) => (8320, 4320),
+            VatCategory::Export => (8338, 4338),
+        };
+        match self.chart {
+            Chart::Skr03 => skr03,
+            Chart::Skr04 => skr04,
+        }
+    }
+
+    /// Debtor account derived from the client number ("K-007" → 10007)
+    pub fn debtor_account(&self, customer_number: Option<&str>) -> u32 {
+        let number = customer_number
+            .map(|c| {
+                c.trim()
+                    .trim_start_matches(['K', 'k'])
+                    .trim_start_matches('-')
+            })
+            .and_then(|n| n.parse::<u32>().ok())
+            .unwrap_or(0);
+        self.debtor_offset + number
+    }
+}
+
+/// One booking line: a document's VAT line against its revenue account
+#[derive(Debug, Clone, PartialEq)]
+pub struct Booking {
+    pub date: chrono::NaiveDate,
+    pub number: String,
+    pub kind: DocumentKind,
+    pub text: String,
+    pub customer_number: Option<String>,
+    pub debtor_account: u32,
+    pub revenue_account: u32,
+    /// VAT rate in basis points
+    pub rate: i64,
+    pub net: i64,
+    pub vat: i64,
+    pub currency: String,
+    /// File name of the compiled PDF (document reference)
+    pub document: String,
+}
+
+impl Booking {
+    pub fn gross(&self) -> i64 {
+        self.net + self.vat
+    }
+}
+
+/// Split issued documents of a period into bookings, one per VAT line
+pub fn bookings(
+    documents: &[IssuedDocument],
+    period: &Period,
+    config: &AccountingConfig,
+    home: &str,
+) -> Vec<Booking> {
+    let mut bookings = Vec::new();
+
+    for doc in documents.iter().filter(|d| period.contains(d.date)) {
+        let document = doc
+            .path
+            .with_extension("pdf")
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let debtor_account = config.debtor_account(doc.customer_number.as_deref());
+
+        // Documents without VAT breakdown (e.g. Kleinunternehmer) book net = gross
+        let lines: Vec<(i64, Option<&str>, i64, i64)> = if doc.vat.is_empty() {
+            vec![(0, None, doc.total, 0)]
+        } else {
+            doc.vat
+                .iter()
+                .map(|l| (l.rate, l.code.as_deref(), l.base, l.amount))
+                .collect()
+        };
+
+        for (rate, code, net, vat) in lines {
+            let category = VatCategory::classify(doc, rate, code, home);
+            bookings.push(Booking {
+                date: doc.date,
+                number: doc.number.clone(),
+                kind: doc.kind,
+                text: doc.recipient.clone(),
+                customer_number: doc.customer_number.clone(),
+                debtor_account,
+                revenue_account: config.revenue_account(&category),
+                rate,
+                net,
+                vat,
+                currency: doc.currency.clone(),
+                document: document.clone(),
+            });
+        }
+    }
+
+    bookings
+}
+
+/// DATEV amount: comma decimal, no thousands separator
+fn datev_amount(cents: i64) -> String {
+    format_decimal(cents).replace('.', ",")
+}
+
+/// Quote a DATEV text field, truncated to the field's maximum length
+fn datev_text(text: &str, max: usize) -> String {
+    let text: String = text.replace('"', "'").chars().take(max).collect();
+    format!("\"{}\"", text)
+}
+
+/// Column header of the Buchungsstapel (first 22 of the DATEV columns)
+const DATEV_COLUMNS: &[&str] = &[
+    "Umsatz (ohne Soll/Haben-Kz)",
+    "Soll/Haben-Kennzeichen",
+    "WKZ Umsatz",
+    "Kurs",
+    "Basis-Umsatz",
+    "WKZ Basis-Umsatz",
+    "Konto",
+    "Gegenkonto (ohne BU-Schlüssel)",
+    "BU-Schlüssel",
+    "Belegdatum",
+    "Belegfeld 1",
+    "Belegfeld 2",
+    "Skonto",
+    "Buchungstext",
+    "Postensperre",
+    "Diverse Adressnummer",
+    "Geschäftspartnerbank",
+    "Sachverhalt",
+    "Zinssperre",
+    "Beleglink",
+    "Beleginfo - Art 1",
+    "Beleginfo - Inhalt 1",
+];
+
+/// Render a DATEV Buchungsstapel (EXTF format 700, category 21)
+pub fn datev_batch(bookings: &[Booking], period: &Period, config: &AccountingConfig) -> String {
+    let now = Local::now();
+    let fiscal_year_start = format!("{}0101", period.start.year());
+    let chart = match config.chart {
+        Chart::Skr03 => "03",
+        Chart::Skr04 => "04",
+    };
+
+    let header = [
+        "\"EXTF\"".to_string(),
+        "700".to_string(),
+        "21".to_string(),
+        "\"Buchungsstapel\"".to_string(),
+        "13".to_string(),
+        now.format("%Y%m%d%H%M%S%3f").to_string(),
+        String::new(),
+        "\"RE\"".to_string(),
+        "\"docgen\"".to_string(),
+        String::new(),
+        config
+            .consultant_number
+            .map(|n| n.to_string())
+            .unwrap_or_default(),
+        config
+            .client_number
+            .map(|n| n.to_string())
+            .unwrap_or_default(),
+        fiscal_year_start,
+        config.account_length.to_string(),
+        period.start.format("%Y%m%d").to_string(),
+        period.end.format("%Y%m%d").to_string(),
+        datev_text(&format!("Ausgangsrechnungen {}", period.label), 30),
+        String::new(),
+        "1".to_string(),
+        "0".to_string(),
+        "0".to_string(),
+        "\"EUR\"".to_string(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        format!("\"{}\"", chart),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+    ];
+
+    let mut lines = vec![
+        header.join(";"),
+        DATEV_COLUMNS
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(";"),
+    ];
+
+    for booking in bookings {
+        // Invoices debit the debtor, credit notes credit it
+        let debit_credit = match booking.kind {
+            DocumentKind::Invoice => "S",
+            DocumentKind::CreditNote => "H",
+        };
+        let mut row = vec![String::new(); DATEV_COLUMNS.len()];
+        row[0] = datev_amount(booking.gross());
+        row[1] = format!("\"{}\"", debit_credit);
+        row[2] = datev_text(&booking.currency, 3);
+        row[6] = booking.debtor_account.to_string();
+        row[7] = booking.revenue_account.to_string();
+        row[9] = booking.date.format("%d%m").to_string();
+        row[10] = datev_text(&booking.number, 36);
+        row[13] = datev_text(&booking.text, 60);
+        // Beleglink only takes links to documents uploaded to DATEV, so the
+        // PDF file name goes into Beleginfo as a plain reference
+        row[20] = datev_text("PDF", 20);
+        row[21] = datev_text(&booking.document, 210);
+        lines.push(row.join(";"));
+    }
+
+    lines.join("\r\n") + "\r\n"
+}
+
+/// Generic journal CSV with one row per booking
+pub fn journal_csv(bookings: &[Booking]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "date",
+        "number",
+        "type",
+        "client",
+        "text",
+        "account",
+        "contra_account",
+        "vat_rate",
+        "net",
+        "vat",
+        "gross",
+        "currency",
+        "document",
+    ])?;
+    for booking in bookings {
+        let sign = match booking.kind {
+            DocumentKind::Invoice => 1,
+            DocumentKind::CreditNote => -1,
+        };
+        writer.write_record([
+            booking.date.to_string(),
+            booking.number.clone(),
+            booking.kind.as_str().to_string(),
+            booking.customer_number.clone().unwrap_or_default(),
+            booking.text.clone(),
+            booking.debtor_account.to_string(),
+            booking.revenue_account.to_string(),
+            format_decimal(booking.rate),
+            format_decimal(sign * booking.net),
+            format_decimal(sign * booking.vat),
+            format_decimal(sign * booking.gross()),
+            booking.currency.clone(),
+            booking.document.clone(),
+        ])?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// DATEV imports expect Windows-1252; unmappable characters become '?'
+fn encode_windows_1252(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '€' => 0x80,
+            c if (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)) => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::documents::VatLine;
+    use chrono::NaiveDate;
+
+    fn invoice(number: &str, customer: &str, rate: i64, net: i64) -> IssuedDocument {
+        IssuedDocument {
+            kind: DocumentKind::Invoice,
+            number: number.to_string(),
+            date: NaiveDate::from_ymd_opt(2025, 3, 14).unwrap(),
+            due_date: None,
+            paid_date: None,
+            customer_number: Some(customer.to_string()),
+            recipient: "Müller GmbH".to_string(),
+            recipient_country: Some("DE"),
+            recipient_vat_id: None,
+            project: None,
+            invoice_reference: None,
+            currency: "EUR".to_string(),
+            net,
+            vat: vec![VatLine {
+                rate,
+                code: None,
+                base: net,
+                amount: net * rate / 10_000,
+            }],
+            total: net + net * rate / 10_000,
+            path: PathBuf::from(format!("documents/invoices/2025/{}.json", number)),
+        }
+    }
+
+    #[test]
+    fn test_accounts_per_chart_and_config() {
+        let mut config = AccountingConfig::default();
+        assert_eq!(config.revenue_account(&VatCategory::Taxable(1900)), 8400);
+        assert_eq!(config.revenue_account(&VatCategory::Taxable(700)), 8300);
+        config.chart = Chart::Skr04;
+        assert_eq!(config.revenue_account(&VatCategory::Taxable(1900)), 4400);
+        config.revenue_accounts.insert("19".to_string(), 4401);
+        assert_eq!(config.revenue_account(&VatCategory::Taxable(1900)), 4401);
+
+        assert_eq!(config.debtor_account(Some("K-007")), 10007);
+        assert_eq!(config.debtor_account(None), 10000);
+    }
+
+    #[test]
+    fn test_datev_batch_rows() {
+        let docs = vec![
+            invoice("RE-2025-010", "K-007", 1900, 100_000),
+            invoice("RE-2025-011", "K-012", 700, 10_000),
+        ];
+        let period: Period = "2025-03".parse().unwrap();
+        let config = AccountingConfig::default();
+        let bookings = bookings(&docs, &period, &config, "DE");
+        let batch = datev_batch(&bookings, &period, &config);
+        let lines: Vec<&str> = batch.split("\r\n").collect();
+
+        assert!(lines[0].starts_with("\"EXTF\";700;21;\"Buchungsstapel\";13;"));
+        assert!(lines[0].contains(";20250301;20250331;"));
+        assert!(lines[1].starts_with("Umsatz (ohne Soll/Haben-Kz);"));
+        assert_eq!(
+            lines[2],
+            "1190,00;\"S\";\"EUR\";;;;10007;8400;;1403;\"RE-2025-010\";;;\"Müller GmbH\";;;;;;;\"PDF\";\"RE-2025-010.pdf\""
+        );
+        assert!(lines[3].starts_with("107,00;\"S\";\"EUR\";;;;10012;8300;"));
+    }
+
+    #[test]
+    fn test_windows_1252_encoding() {
+        assert_eq!(encode_windows_1252("Ü€✓"), vec![0xDC, 0x80, b'?']);
+    }
+}
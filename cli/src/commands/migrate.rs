@@ -0,0 +1,41 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::path::Path;
+
+use crate::data::{migrate, DATA_VERSION};
+use crate::local_templates;
+
+pub fn run(dry_run: bool) -> Result<()> {
+    let root = Path::new(".");
+    let changes = migrate::plan(root)?;
+
+    if changes.is_empty() {
+        println!(
+            "{} Project is up to date (data format version {})",
+            "✓".green(),
+            DATA_VERSION
+        );
+        return Ok(());
+    }
+
+    for change in &changes {
+        println!("{} {}", "→".blue(), change);
+    }
+    println!();
+
+    if dry_run {
+        println!("Dry run: {} change(s) would be made", changes.len());
+        return Ok(());
+    }
+
+    let backup_dir = migrate::apply(root, &changes)?;
+    if changes.iter().any(|c| c.touches_templates()) {
+        local_templates::init_project()?;
+    }
+
+    println!("{} Applied {} change(s)", "✓".green(), changes.len());
+    if let Some(dir) = backup_dir {
+        println!("{} Backups in {}", "→".blue(), dir.display());
+    }
+    Ok(())
+}
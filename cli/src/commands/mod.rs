@@ -1,3 +1,11 @@
+pub mod audit;
+pub mod build;
 pub mod client;
+pub mod db;
+pub mod export;
+pub mod migrate;
 pub mod project;
+pub mod report;
 pub mod template;
+pub mod test;
+pub mod watch;
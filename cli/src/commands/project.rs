@@ -2,22 +2,20 @@ use anyhow::Result;
 use colored::Colorize;
 
 use crate::commands::client::parse_client_id;
-use crate::data::{ClientStore, CounterStore, NewProject, ProjectStore};
+use crate::data::{self, NewProject};
 use crate::locale::{t, tf};
 use crate::ProjectAction;
 
 pub fn handle(action: ProjectAction) -> Result<()> {
-    let client_store = ClientStore::default();
-    let project_store = ProjectStore::default();
-    let mut counter_store = CounterStore::default();
+    let mut storage = data::open()?;
 
     match action {
         ProjectAction::List { client } => {
-            let client_id = parse_client_id(&client_store, &client)?;
-            let client = client_store
-                .get(client_id)?
+            let client_id = parse_client_id(storage.as_ref(), &client)?;
+            let client = storage
+                .get_client(client_id)?
                 .ok_or_else(|| anyhow::anyhow!("Client not found"))?;
-            let projects = project_store.list_by_client(client_id)?;
+            let projects = storage.list_projects_by_client(client_id)?;
 
             println!(
                 "{} - {}",
@@ -40,13 +38,13 @@ pub fn handle(action: ProjectAction) -> Result<()> {
             }
         }
         ProjectAction::Add { client, name } => {
-            let client_id = parse_client_id(&client_store, &client)?;
-            let client_data = client_store
-                .get(client_id)?
+            let client_id = parse_client_id(storage.as_ref(), &client)?;
+            let client_data = storage
+                .get_client(client_id)?
                 .ok_or_else(|| anyhow::anyhow!("Client not found"))?;
 
             let new_project = NewProject::new(client_id, name);
-            let project = project_store.add(new_project, &mut counter_store)?;
+            let project = storage.add_project(new_project)?;
 
             println!(
                 "{} {}",
@@ -65,7 +63,7 @@ pub fn handle(action: ProjectAction) -> Result<()> {
                 anyhow::bail!("Invalid project ID format. Expected: P-XXX-YY");
             }
 
-            let projects = project_store.list()?;
+            let projects = storage.list_projects()?;
             let project = projects
                 .iter()
                 .find(|p| {
@@ -76,8 +74,8 @@ pub fn handle(action: ProjectAction) -> Result<()> {
                 })
                 .ok_or_else(|| anyhow::anyhow!("Project not found: {}", id))?;
 
-            let client = client_store
-                .get(project.client_id)?
+            let client = storage
+                .get_client(project.client_id)?
                 .ok_or_else(|| anyhow::anyhow!("Client not found"))?;
 
             println!(
@@ -87,7 +85,7 @@ pub fn handle(action: ProjectAction) -> Result<()> {
                 project.name
             );
 
-            project_store.delete(project.id)?;
+            storage.delete_project(project.id)?;
             println!("{} Project deleted successfully", "✓".green());
         }
     }
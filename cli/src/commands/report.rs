@@ -0,0 +1,1097 @@
+use anyhow::{Context, Result};
+use chrono::{Datelike, Local, NaiveDate};
+use clap::ValueEnum;
+use colored::Colorize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::data::documents::{
+    format_decimal, format_money, format_rate, home_country, load_issued_documents, DocumentKind,
+    IssuedDocument, VatCategory,
+};
+use crate::ReportAction;
+
+/// Output format shared by all reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Table,
+    Csv,
+    Json,
+    Pdf,
+}
+
+/// Grouping for the revenue report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    Month,
+    Client,
+    Project,
+}
+
+/// When revenue counts for VAT: invoice date or payment date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Basis {
+    /// Soll-Versteuerung: by document date
+    Accrual,
+    /// Ist-Versteuerung: by payment date (payment.paid_date)
+    Cash,
+}
+
+/// Reporting period: a year ("2025"), quarter ("2025-Q1") or month ("2025-03")
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Period {
+    pub label: String,
+    pub start: NaiveDate,
+    /// Inclusive
+    pub end: NaiveDate,
+}
+
+impl Period {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+
+    fn months(label: &str, year: i32, first: u32, last: u32) -> Result<Self, String> {
+        let start = NaiveDate::from_ymd_opt(year, first, 1)
+            .ok_or_else(|| format!("Invalid period: {}", label))?;
+        let end = if last == 12 {
+            NaiveDate::from_ymd_opt(year, 12, 31)
+        } else {
+            NaiveDate::from_ymd_opt(year, last + 1, 1).and_then(|d| d.pred_opt())
+        }
+        .ok_or_else(|| format!("Invalid period: {}", label))?;
+        Ok(Self {
+            label: label.to_string(),
+            start,
+            end,
+        })
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid period '{}'. Expected YYYY, YYYY-Q1..Q4 or YYYY-MM",
+                input
+            )
+        };
+        let input = input.trim().to_uppercase();
+        let (year, rest) = match input.split_once('-') {
+            Some((year, rest)) => (year, Some(rest)),
+            None => (input.as_str(), None),
+        };
+        let year: i32 = year.parse().map_err(|_| invalid())?;
+
+        match rest {
+            None => Period::months(&input, year, 1, 12),
+            Some(q) if q.starts_with('Q') => match q[1..].parse::<u32>() {
+                Ok(quarter @ 1..=4) => Period::months(&input, year, quarter * 3 - 2, quarter * 3),
+                _ => Err(invalid()),
+            },
+            Some(m) => match m.parse::<u32>() {
+                Ok(month @ 1..=12) => Period::months(&input, year, month, month),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+pub fn handle(action: ReportAction) -> Result<()> {
+    match action {
+        ReportAction::Revenue {
+            year,
+            by,
+            path,
+            format,
+            output,
+        } => {
+            let documents = load_issued_documents(&path)?;
+            let report = revenue_report(&documents, year, by);
+            emit(&report, format, output)
+        }
+        ReportAction::Client {
+            id,
+            path,
+            format,
+            output,
+        } => {
+            let documents = load_issued_documents(&path)?;
+            let storage = crate::data::open()?;
+            // Unknown clients are still reported by their number
+            let name = crate::commands::client::parse_client_id(storage.as_ref(), &id)
+                .ok()
+                .and_then(|client_id| storage.get_client(client_id).ok().flatten())
+                .map(|c| c.display_name());
+            let report = client_report(&documents, &id, name, today());
+            emit(&report, format, output)
+        }
+        ReportAction::Outstanding {
+            path,
+            format,
+            output,
+        } => {
+            let documents = load_issued_documents(&path)?;
+            let report = outstanding_report(&documents, today());
+            emit(&report, format, output)
+        }
+        ReportAction::Vat {
+            period,
+            basis,
+            path,
+            format,
+            output,
+        } => {
+            let documents = load_issued_documents(&path)?;
+            let report = vat_report(&documents, &period, basis, &home_country());
+            emit(&report, format, output)
+        }
+    }
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// A single table cell; money is kept in cents until output
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Money(i64),
+    Count(i64),
+}
+
+impl Cell {
+    fn display(&self) -> String {
+        match self {
+            Cell::Text(s) => s.clone(),
+            Cell::Money(cents) => format_money(*cents),
+            Cell::Count(n) => n.to_string(),
+        }
+    }
+
+    fn raw(&self) -> String {
+        match self {
+            Cell::Text(s) => s.clone(),
+            Cell::Money(cents) => format_decimal(*cents),
+            Cell::Count(n) => n.to_string(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            Cell::Text(s) => json!(s),
+            Cell::Money(cents) => json!(format_decimal(*cents)),
+            Cell::Count(n) => json!(n),
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        !matches!(self, Cell::Text(_))
+    }
+}
+
+/// Column definition: machine key (CSV/JSON) and human label (table/PDF)
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub key: &'static str,
+    pub label: &'static str,
+}
+
+const fn col(key: &'static str, label: &'static str) -> Column {
+    Column { key, label }
+}
+
+/// Tabular report that can be rendered in every `ReportFormat`
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// File stem for generated files, e.g. "revenue-2025"
+    pub name: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<Cell>>,
+    pub totals: Option<Vec<Cell>>,
+}
+
+/// Revenue (net, VAT, gross) of one year, credit notes deducted
+pub fn revenue_report(documents: &[IssuedDocument], year: i32, by: GroupBy) -> Report {
+    #[derive(Default)]
+    struct Sums {
+        invoices: i64,
+        credit_notes: i64,
+        net: i64,
+        vat: i64,
+        gross: i64,
+    }
+
+    let mut groups: BTreeMap<String, Sums> = BTreeMap::new();
+    if by == GroupBy::Month {
+        for month in 1..=12 {
+            groups.insert(format!("{}-{:02}", year, month), Sums::default());
+        }
+    }
+
+    for doc in documents.iter().filter(|d| d.date.year() == year) {
+        let key = match by {
+            GroupBy::Month => format!("{}-{:02}", year, doc.date.month()),
+            GroupBy::Client => match &doc.customer_number {
+                Some(number) => format!("{} {}", number, doc.recipient),
+                None => doc.recipient.clone(),
+            },
+            GroupBy::Project => doc.project.clone().unwrap_or_else(|| "—".to_string()),
+        };
+        let sums = groups.entry(key).or_default();
+        match doc.kind {
+            DocumentKind::Invoice => sums.invoices += 1,
+            DocumentKind::CreditNote => sums.credit_notes += 1,
+        }
+        sums.net += doc.sign() * doc.net;
+        sums.vat += doc.sign() * doc.vat_total();
+        sums.gross += doc.sign() * doc.total;
+    }
+
+    let mut total = Sums::default();
+    let rows = groups
+        .into_iter()
+        .map(|(key, sums)| {
+            total.invoices += sums.invoices;
+            total.credit_notes += sums.credit_notes;
+            total.net += sums.net;
+            total.vat += sums.vat;
+            total.gross += sums.gross;
+            vec![
+                Cell::Text(key),
+                Cell::Count(sums.invoices),
+                Cell::Count(sums.credit_notes),
+                Cell::Money(sums.net),
+                Cell::Money(sums.vat),
+                Cell::Money(sums.gross),
+            ]
+        })
+        .collect();
+
+    let (group_key, group_label, suffix) = match by {
+        GroupBy::Month => ("month", "Month", "month"),
+        GroupBy::Client => ("client", "Client", "client"),
+        GroupBy::Project => ("project", "Project", "project"),
+    };
+
+    Report {
+        name: format!("revenue-{}-by-{}", year, suffix),
+        title: format!("Revenue {}", year),
+        subtitle: Some(format!("By {}, credit notes deducted", suffix)),
+        columns: vec![
+            col(group_key, group_label),
+            col("invoices", "Invoices"),
+            col("credit_notes", "Credit notes"),
+            col("net", "Net"),
+            col("vat", "VAT"),
+            col("gross", "Gross"),
+        ],
+        rows,
+        totals: Some(vec![
+            Cell::Text("Total".to_string()),
+            Cell::Count(total.invoices),
+            Cell::Count(total.credit_notes),
+            Cell::Money(total.net),
+            Cell::Money(total.vat),
+            Cell::Money(total.gross),
+        ]),
+    }
+}
+
+/// Credit note totals by the invoice number they refer to
+fn credited(documents: &[IssuedDocument]) -> BTreeMap<&str, i64> {
+    let mut credited: BTreeMap<&str, i64> = BTreeMap::new();
+    for doc in documents
+        .iter()
+        .filter(|d| d.kind == DocumentKind::CreditNote)
+    {
+        if let Some(reference) = &doc.invoice_reference {
+            *credited.entry(reference.as_str()).or_default() += doc.total;
+        }
+    }
+    credited
+}
+
+/// All invoices and credit notes of one client with payment status
+///
+/// The open amount is reduced by credit notes like in `outstanding_report`.
+pub fn client_report(
+    documents: &[IssuedDocument],
+    client: &str,
+    client_name: Option<String>,
+    today: NaiveDate,
+) -> Report {
+    let wanted = normalize_customer_number(client);
+    let docs: Vec<&IssuedDocument> = documents
+        .iter()
+        .filter(|d| {
+            d.customer_number
+                .as_deref()
+                .map(normalize_customer_number)
+                .is_some_and(|n| n == wanted)
+        })
+        .collect();
+
+    let credited = credited(documents);
+    let mut net = 0;
+    let mut gross = 0;
+    let mut open = 0;
+    let row = |doc: &IssuedDocument, status: &str| {
+        vec![
+            Cell::Text(doc.date.to_string()),
+            Cell::Text(doc.number.clone()),
+            Cell::Text(doc.kind.as_str().to_string()),
+            Cell::Text(doc.project.clone().unwrap_or_default()),
+            Cell::Money(doc.sign() * doc.net),
+            Cell::Money(doc.sign() * doc.vat_total()),
+            Cell::Money(doc.sign() * doc.total),
+            Cell::Text(status.to_string()),
+        ]
+    };
+    let rows = docs
+        .iter()
+        .map(|doc| {
+            net += doc.sign() * doc.net;
+            gross += doc.sign() * doc.total;
+            let status = match doc.kind {
+                DocumentKind::CreditNote => "credit",
+                DocumentKind::Invoice if doc.paid_date.is_some() => "paid",
+                DocumentKind::Invoice => {
+                    let credit = credited.get(doc.number.as_str()).copied().unwrap_or(0);
+                    let remaining = doc.total - credit;
+                    if remaining <= 0 {
+                        "credited"
+                    } else {
+                        open += remaining;
+                        if doc.due_date.is_some_and(|due| due < today) {
+                            "overdue"
+                        } else {
+                            "open"
+                        }
+                    }
+                }
+            };
+            row(doc, status)
+        })
+        .collect();
+
+    let subtitle = match client_name.or_else(|| docs.first().map(|d| d.recipient.clone())) {
+        Some(name) => format!("{} · {} open", name, format_money(open)),
+        None => format!("{} open", format_money(open)),
+    };
+
+    Report {
+        name: format!("client-{}", wanted.to_lowercase()),
+        title: format!("Client {}", wanted),
+        subtitle: Some(subtitle),
+        columns: vec![
+            col("date", "Date"),
+            col("number", "Number"),
+            col("type", "Type"),
+            col("project", "Project"),
+            col("net", "Net"),
+            col("vat", "VAT"),
+            col("gross", "Gross"),
+            col("status", "Status"),
+        ],
+        rows,
+        totals: Some(vec![
+            Cell::Text("Total".to_string()),
+            Cell::Text(String::new()),
+            Cell::Text(String::new()),
+            Cell::Text(String::new()),
+            Cell::Money(net),
+            Cell::Money(gross - net),
+            Cell::Money(gross),
+            Cell::Text(String::new()),
+        ]),
+    }
+}
+
+/// Unpaid invoices, reduced by credit notes referring to them
+pub fn outstanding_report(documents: &[IssuedDocument], today: NaiveDate) -> Report {
+    let credited = credited(documents);
+
+    let mut total_open = 0;
+    let mut rows = Vec::new();
+    for doc in documents
+        .iter()
+        .filter(|d| d.kind == DocumentKind::Invoice && d.paid_date.is_none())
+    {
+        let credit = credited.get(doc.number.as_str()).copied().unwrap_or(0);
+        let open = doc.total - credit;
+        if open <= 0 {
+            continue;
+        }
+        total_open += open;
+        let overdue_days = doc
+            .due_date
+            .map(|due| (today - due).num_days().max(0))
+            .unwrap_or(0);
+        rows.push(vec![
+            Cell::Text(doc.number.clone()),
+            Cell::Text(doc.date.to_string()),
+            Cell::Text(doc.due_date.map(|d| d.to_string()).unwrap_or_default()),
+            Cell::Text(doc.recipient.clone()),
+            Cell::Money(doc.total),
+            Cell::Money(credit),
+            Cell::Money(open),
+            Cell::Count(overdue_days),
+        ]);
+    }
+
+    Report {
+        name: format!("outstanding-{}", today),
+        title: "Outstanding invoices".to_string(),
+        subtitle: Some(format!("As of {}", today)),
+        columns: vec![
+            col("number", "Number"),
+            col("date", "Date"),
+            col("due_date", "Due"),
+            col("client", "Client"),
+            col("gross", "Gross"),
+            col("credited", "Credited"),
+            col("outstanding", "Outstanding"),
+            col("days_overdue", "Days overdue"),
+        ],
+        rows,
+        totals: Some(vec![
+            Cell::Text("Total".to_string()),
+            Cell::Text(String::new()),
+            Cell::Text(String::new()),
+            Cell::Text(String::new()),
+            Cell::Text(String::new()),
+            Cell::Text(String::new()),
+            Cell::Money(total_open),
+            Cell::Text(String::new()),
+        ]),
+    }
+}
+
+impl VatCategory {
+    /// Label and UStVA field (Kennzahl) for the net amount
+    fn describe(&self) -> (String, &'static str) {
+        match self {
+            VatCategory::Taxable(1900) => ("Taxable 19%".to_string(), "81"),
+            VatCategory::Taxable(700) => ("Taxable 7%".to_string(), "86"),
+            VatCategory::Taxable(rate) => (format!("Taxable {}", format_rate(*rate)), "35"),
+            VatCategory::TaxFree => ("Tax-free 0%".to_string(), ""),
+            VatCategory::IntraCommunity => ("Intra-community supplies".to_string(), "41"),
+            VatCategory::EuReverseCharge => ("EU services, reverse charge".to_string(), "21"),
+            VatCategory::ReverseCharge => ("Reverse charge §13b".to_string(), "60"),
+            VatCategory::Oss(country, rate) => {
+                (format!("OSS {} {}", country, format_rate(*rate)), "")
+            }
+            VatCategory::Export => ("Non-EU (export, services abroad)".to_string(), "45"),
+        }
+    }
+
+    /// Whether the VAT is payable in the domestic advance return
+    fn domestic_vat(&self) -> bool {
+        matches!(self, VatCategory::Taxable(_))
+    }
+}
+
+/// Figures for the advance VAT return (UStVA) and the OSS return
+///
+/// Net turnover and VAT per rate, reverse-charge and intra-community
+/// supplies separated, B2C sales to other EU countries listed per
+/// destination country and rate. On cash basis only paid invoices count.
+pub fn vat_report(
+    documents: &[IssuedDocument],
+    period: &Period,
+    basis: Basis,
+    home: &str,
+) -> Report {
+    let mut sums: BTreeMap<VatCategory, (i64, i64)> = BTreeMap::new();
+    let mut unpaid = 0;
+
+    for doc in documents {
+        let date = match (basis, doc.kind) {
+            (Basis::Accrual, _) => Some(doc.date),
+            (Basis::Cash, DocumentKind::Invoice) => doc.paid_date,
+            // Refunds without a recorded payment date count on their issue date
+            (Basis::Cash, DocumentKind::CreditNote) => doc.paid_date.or(Some(doc.date)),
+        };
+        let Some(date) = date else {
+            if period.contains(doc.date) {
+                unpaid += 1;
+            }
+            continue;
+        };
+        if !period.contains(date) {
+            continue;
+        }
+
+        if doc.vat.is_empty() {
+            let category = VatCategory::classify(doc, 0, None, home);
+            let entry = sums.entry(category).or_default();
+            entry.0 += doc.sign() * doc.net;
+        }
+        for line in &doc.vat {
+            let category = VatCategory::classify(doc, line.rate, line.code.as_deref(), home);
+            let entry = sums.entry(category).or_default();
+            entry.0 += doc.sign() * line.base;
+            entry.1 += doc.sign() * line.amount;
+        }
+    }
+
+    // The totals row covers the domestic taxable turnover and its VAT only
+    let mut taxable = 0;
+    let mut payable = 0;
+    let rows = sums
+        .into_iter()
+        .map(|(category, (net, vat))| {
+            if category.domestic_vat() {
+                taxable += net;
+                payable += vat;
+            }
+            let (label, field) = category.describe();
+            vec![
+                Cell::Text(label),
+                Cell::Text(field.to_string()),
+                Cell::Money(net),
+                Cell::Money(vat),
+            ]
+        })
+        .collect();
+
+    let basis_label = match basis {
+        Basis::Accrual => "accrual",
+        Basis::Cash => "cash",
+    };
+    let mut subtitle = format!("{} to {}, {} basis", period.start, period.end, basis_label);
+    if unpaid > 0 {
+        subtitle.push_str(&format!(", {} unpaid invoice(s) not included", unpaid));
+    }
+
+    Report {
+        name: format!("vat-{}-{}", period.label.to_lowercase(), basis_label),
+        title: format!("VAT return {}", period.label),
+        subtitle: Some(subtitle),
+        columns: vec![
+            col("category", "Category"),
+            col("field", "UStVA field"),
+            col("net", "Net"),
+            col("vat", "VAT"),
+        ],
+        rows,
+        totals: Some(vec![
+            Cell::Text("Total (domestic VAT payable)".to_string()),
+            Cell::Text(String::new()),
+            Cell::Money(taxable),
+            Cell::Money(payable),
+        ]),
+    }
+}
+
+/// "K-7", "K-007" and "7" all refer to the same client
+fn normalize_customer_number(input: &str) -> String {
+    let trimmed = input.trim();
+    let digits = trimmed
+        .strip_prefix("K-")
+        .or_else(|| trimmed.strip_prefix("k-"))
+        .unwrap_or(trimmed);
+    match digits.parse::<i64>() {
+        Ok(n) => format!("K-{:03}", n),
+        Err(_) => trimmed.to_string(),
+    }
+}
+
+/// Render a report in the requested format
+///
+/// Table output always goes to the terminal; CSV and JSON go to `output` or
+/// stdout; PDFs are compiled with the `report` template.
+pub fn emit(report: &Report, format: ReportFormat, output: Option<PathBuf>) -> Result<()> {
+    match format {
+        ReportFormat::Table => {
+            print_table(report);
+            Ok(())
+        }
+        ReportFormat::Csv => write_output(&to_csv(report)?, output),
+        ReportFormat::Json => {
+            write_output(&serde_json::to_string_pretty(&to_json(report))?, output)
+        }
+        ReportFormat::Pdf => {
+            let output = output
+                .unwrap_or_else(|| PathBuf::from("output/reports").join(&report.name))
+                .with_extension("pdf");
+            compile_pdf(report, &output)
+        }
+    }
+}
+
+fn write_output(content: &str, output: Option<PathBuf>) -> Result<()> {
+    match output {
+        Some(path) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, content)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("{} Report written to {}", "✓".green(), path.display());
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+fn print_table(report: &Report) {
+    let all_rows: Vec<&Vec<Cell>> = report.rows.iter().chain(report.totals.iter()).collect();
+    let widths: Vec<usize> = report
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            all_rows
+                .iter()
+                .map(|row| row[i].display().chars().count())
+                .chain(std::iter::once(column.label.chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let line_width = widths.iter().sum::<usize>() + 3 * widths.len().saturating_sub(1);
+
+    let format_row = |cells: Vec<(String, bool)>| -> String {
+        cells
+            .into_iter()
+            .zip(&widths)
+            .map(|((text, right), width)| {
+                if right {
+                    format!("{:>width$}", text, width = width)
+                } else {
+                    format!("{:<width$}", text, width = width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" │ ")
+    };
+
+    println!("{}", report.title.bold());
+    if let Some(subtitle) = &report.subtitle {
+        println!("{}", subtitle.dimmed());
+    }
+    println!("{:-<width$}", "", width = line_width);
+
+    let numeric: Vec<bool> = (0..report.columns.len())
+        .map(|i| report.rows.iter().any(|row| row[i].is_numeric()))
+        .collect();
+    println!(
+        "{}",
+        format_row(
+            report
+                .columns
+                .iter()
+                .zip(&numeric)
+                .map(|(c, right)| (c.label.to_string(), *right))
+                .collect()
+        )
+        .bold()
+    );
+    println!("{:-<width$}", "", width = line_width);
+
+    if report.rows.is_empty() {
+        println!("{}", "No documents found.".dimmed());
+    }
+    for row in &report.rows {
+        println!(
+            "{}",
+            format_row(row.iter().map(|c| (c.display(), c.is_numeric())).collect())
+        );
+    }
+
+    if let Some(totals) = &report.totals {
+        println!("{:-<width$}", "", width = line_width);
+        println!(
+            "{}",
+            format_row(
+                totals
+                    .iter()
+                    .map(|c| (c.display(), c.is_numeric()))
+                    .collect()
+            )
+            .bold()
+        );
+    }
+}
+
+fn to_csv(report: &Report) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(report.columns.iter().map(|c| c.key))?;
+    for row in &report.rows {
+        writer.write_record(row.iter().map(Cell::raw))?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+fn to_json(report: &Report) -> Value {
+    let to_object = |row: &Vec<Cell>| -> Value {
+        let mut object = Map::new();
+        for (column, cell) in report.columns.iter().zip(row) {
+            object.insert(column.key.to_string(), cell.to_json());
+        }
+        Value::Object(object)
+    };
+
+    json!({
+        "title": report.title,
+        "subtitle": report.subtitle,
+        "rows": report.rows.iter().map(to_object).collect::<Vec<_>>(),
+        "totals": report.totals.as_ref().map(to_object),
+    })
+}
+
+/// Compile a report with the embedded `report` template
+fn compile_pdf(report: &Report, output: &Path) -> Result<()> {
+    let numeric: Vec<bool> = (0..report.columns.len())
+        .map(|i| report.rows.iter().any(|row| row[i].is_numeric()))
+        .collect();
+
+    let data = json!({
+        "title": report.title,
+        "subtitle": report.subtitle,
+        "generated_at": { "date": today().to_string() },
+        "columns": report.columns.iter().zip(&numeric).map(|(c, right)| json!({
+            "label": c.label,
+            "align": if *right { "right" } else { "left" },
+        })).collect::<Vec<_>>(),
+        "rows": report.rows.iter()
+            .map(|row| row.iter().map(Cell::display).collect::<Vec<_>>())
+            .collect::<Vec<_>>(),
+        "totals": report.totals.as_ref()
+            .map(|row| row.iter().map(Cell::display).collect::<Vec<_>>()),
+    });
+
+    // Typst only reads inputs below the project root
+    let data_dir = PathBuf::from(".docgen/reports");
+    fs::create_dir_all(&data_dir)?;
+    let data_path = data_dir.join(format!("{}.json", report.name));
+    fs::write(&data_path, serde_json::to_string_pretty(&data)?)?;
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    crate::compile_document(
+        &data_path,
+        Some(output.to_path_buf()),
+        Some("report".to_string()),
+        None,
+        false,
+        &Default::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::documents::VatLine;
+
+    fn doc(
+        kind: DocumentKind,
+        number: &str,
+        date: &str,
+        customer: &str,
+        net: i64,
+    ) -> IssuedDocument {
+        IssuedDocument {
+            kind,
+            number: number.to_string(),
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            due_date: None,
+            paid_date: None,
+            customer_number: Some(customer.to_string()),
+            recipient: "Client GmbH".to_string(),
+            recipient_country: Some("DE"),
+            recipient_vat_id: None,
+            project: None,
+            invoice_reference: None,
+            currency: "EUR".to_string(),
+            net,
+            vat: vec![VatLine {
+                rate: 1900,
+                code: None,
+                base: net,
+                amount: net * 19 / 100,
+            }],
+            total: net + net * 19 / 100,
+            path: PathBuf::from(format!("{}.json", number)),
+        }
+    }
+
+    #[test]
+    fn test_revenue_by_month_deducts_credit_notes() {
+        let docs = vec![
+            doc(
+                DocumentKind::Invoice,
+                "RE-2025-001",
+                "2025-01-10",
+                "K-001",
+                100_000,
+            ),
+            doc(
+                DocumentKind::Invoice,
+                "RE-2025-002",
+                "2025-03-05",
+                "K-002",
+                50_000,
+            ),
+            doc(
+                DocumentKind::CreditNote,
+                "GS-2025-001",
+                "2025-03-20",
+                "K-002",
+                10_000,
+            ),
+            doc(
+                DocumentKind::Invoice,
+                "RE-2024-099",
+                "2024-12-30",
+                "K-001",
+                99_900,
+            ),
+        ];
+
+        let report = revenue_report(&docs, 2025, GroupBy::Month);
+        assert_eq!(report.rows.len(), 12);
+        assert_eq!(report.rows[2][3], Cell::Money(40_000));
+        let totals = report.totals.unwrap();
+        assert_eq!(totals[1], Cell::Count(2));
+        assert_eq!(totals[2], Cell::Count(1));
+        assert_eq!(totals[3], Cell::Money(140_000));
+        assert_eq!(totals[5], Cell::Money(166_600));
+    }
+
+    #[test]
+    fn test_outstanding_respects_payments_and_credits() {
+        let mut paid = doc(
+            DocumentKind::Invoice,
+            "RE-2025-001",
+            "2025-01-10",
+            "K-001",
+            100_000,
+        );
+        paid.paid_date = NaiveDate::from_ymd_opt(2025, 1, 20);
+        let mut open = doc(
+            DocumentKind::Invoice,
+            "RE-2025-002",
+            "2025-02-01",
+            "K-001",
+            100_000,
+        );
+        open.due_date = NaiveDate::from_ymd_opt(2025, 2, 15);
+        let mut credit = doc(
+            DocumentKind::CreditNote,
+            "GS-2025-001",
+            "2025-02-10",
+            "K-001",
+            10_000,
+        );
+        credit.invoice_reference = Some("RE-2025-002".to_string());
+
+        let today = NaiveDate::from_ymd_opt(2025, 2, 25).unwrap();
+        let report = outstanding_report(&[paid.clone(), open.clone(), credit.clone()], today);
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0][6], Cell::Money(119_000 - 11_900));
+        assert_eq!(report.rows[0][7], Cell::Count(10));
+
+        // The client report shows the same open amount
+        let report = client_report(&[paid, open, credit], "K-001", None, today);
+        assert_eq!(
+            report.subtitle.as_deref(),
+            Some(format!("Client GmbH · {} open", format_money(119_000 - 11_900)).as_str())
+        );
+        assert_eq!(report.rows[1][7], Cell::Text("overdue".to_string()));
+    }
+
+    #[test]
+    fn test_client_report_matches_number_formats() {
+        let docs = vec![
+            doc(
+                DocumentKind::Invoice,
+                "RE-2025-001",
+                "2025-01-10",
+                "K-7",
+                100_000,
+            ),
+            doc(
+                DocumentKind::Invoice,
+                "RE-2025-002",
+                "2025-01-11",
+                "K-008",
+                100_000,
+            ),
+        ];
+        let today = NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+        let report = client_report(&docs, "K-007", None, today);
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0][1], Cell::Text("RE-2025-001".to_string()));
+    }
+
+    #[test]
+    fn test_csv_uses_plain_decimals() {
+        let docs = vec![doc(
+            DocumentKind::Invoice,
+            "RE-2025-001",
+            "2025-01-10",
+            "K-001",
+            123_456,
+        )];
+        let report = revenue_report(&docs, 2025, GroupBy::Client);
+        let csv = to_csv(&report).unwrap();
+        assert!(csv.starts_with("client,invoices,credit_notes,net,vat,gross\n"));
+        assert!(csv.contains("K-001 Client GmbH,1,0,1234.56,234.56,1469.12"));
+    }
+
+    #[test]
+    fn test_period_parsing() {
+        let q1: Period = "2025-Q1".parse().unwrap();
+        assert_eq!(q1.start, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+        assert_eq!(q1.end, NaiveDate::from_ymd_opt(2025, 3, 31).unwrap());
+        let feb: Period = "2024-02".parse().unwrap();
+        assert_eq!(feb.end, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+        let year: Period = "2025".parse().unwrap();
+        assert_eq!(year.end, NaiveDate::from_ymd_opt(2025, 12, 31).unwrap());
+        assert!("2025-Q5".parse::<Period>().is_err());
+        assert!("2025-13".parse::<Period>().is_err());
+    }
+
+    #[test]
+    fn test_vat_report_categories() {
+        let domestic = doc(
+            DocumentKind::Invoice,
+            "RE-2025-001",
+            "2025-01-10",
+            "K-001",
+            100_000,
+        );
+        let mut reduced = doc(
+            DocumentKind::Invoice,
+            "RE-2025-002",
+            "2025-02-10",
+            "K-001",
+            10_000,
+        );
+        reduced.vat[0].rate = 700;
+        reduced.vat[0].amount = 700;
+        let mut oss = doc(
+            DocumentKind::Invoice,
+            "RE-2025-003",
+            "2025-02-11",
+            "K-002",
+            10_000,
+        );
+        oss.recipient_country = Some("AT");
+        oss.vat[0].rate = 2000;
+        oss.vat[0].amount = 2000;
+        let mut reverse = doc(
+            DocumentKind::Invoice,
+            "RE-2025-004",
+            "2025-03-01",
+            "K-003",
+            50_000,
+        );
+        reverse.recipient_country = Some("FR");
+        reverse.recipient_vat_id = Some("FR12345678901".to_string());
+        reverse.vat[0].rate = 0;
+        reverse.vat[0].amount = 0;
+        reverse.vat[0].code = Some("ReverseCharge".to_string());
+        let april = doc(
+            DocumentKind::Invoice,
+            "RE-2025-005",
+            "2025-04-01",
+            "K-001",
+            100_000,
+        );
+
+        let mut swiss = doc(
+            DocumentKind::Invoice,
+            "RE-2025-006",
+            "2025-03-10",
+            "K-004",
+            30_000,
+        );
+        swiss.recipient_country = Some("CH");
+        swiss.vat[0].rate = 0;
+        swiss.vat[0].amount = 0;
+        let mut swiss_b2b = swiss.clone();
+        swiss_b2b.number = "RE-2025-007".to_string();
+        swiss_b2b.vat[0].code = Some("ReverseCharge".to_string());
+
+        let period: Period = "2025-Q1".parse().unwrap();
+        let report = vat_report(
+            &[domestic, reduced, oss, reverse, april, swiss, swiss_b2b],
+            &period,
+            Basis::Accrual,
+            "DE",
+        );
+
+        let row = |label: &str| {
+            report
+                .rows
+                .iter()
+                .find(|r| r[0] == Cell::Text(label.to_string()))
+                .unwrap_or_else(|| panic!("missing row {}", label))
+                .clone()
+        };
+        assert_eq!(row("Taxable 19%")[2], Cell::Money(100_000));
+        assert_eq!(row("Taxable 19%")[1], Cell::Text("81".to_string()));
+        assert_eq!(row("Taxable 7%")[3], Cell::Money(700));
+        assert_eq!(row("OSS AT 20%")[3], Cell::Money(2000));
+        assert_eq!(row("EU services, reverse charge")[2], Cell::Money(50_000));
+        // Swiss recipients are exports, not domestic tax-free turnover
+        assert_eq!(
+            row("Non-EU (export, services abroad)")[2],
+            Cell::Money(60_000)
+        );
+        assert!(!report
+            .rows
+            .iter()
+            .any(|r| r[0] == Cell::Text("Tax-free 0%".to_string())));
+        // OSS and reverse-charge turnover is declared separately, not in the domestic total
+        let totals = report.totals.unwrap();
+        assert_eq!(totals[2], Cell::Money(100_000 + 10_000));
+        assert_eq!(totals[3], Cell::Money(19_000 + 700));
+    }
+
+    #[test]
+    fn test_vat_report_cash_basis_uses_payment_date() {
+        let mut paid_later = doc(
+            DocumentKind::Invoice,
+            "RE-2025-001",
+            "2025-03-20",
+            "K-001",
+            100_000,
+        );
+        paid_later.paid_date = NaiveDate::from_ymd_opt(2025, 4, 2);
+        let unpaid = doc(
+            DocumentKind::Invoice,
+            "RE-2025-002",
+            "2025-03-21",
+            "K-001",
+            100_000,
+        );
+
+        let q1: Period = "2025-Q1".parse().unwrap();
+        let report = vat_report(
+            &[paid_later.clone(), unpaid.clone()],
+            &q1,
+            Basis::Cash,
+            "DE",
+        );
+        assert!(report.rows.is_empty());
+        assert!(report.subtitle.unwrap().contains("1 unpaid"));
+
+        let q2: Period = "2025-Q2".parse().unwrap();
+        let report = vat_report(&[paid_later, unpaid], &q2, Basis::Cash, "DE");
+        assert_eq!(report.rows[0][2], Cell::Money(100_000));
+    }
+}
@@ -1,7 +1,12 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::Colorize;
 
+use crate::document_types::{self, NewType};
+use crate::forks;
+use crate::lint::{self, Case};
 use crate::local_templates;
+use crate::packages::{self, Manifest};
+use crate::schema;
 use crate::TemplateAction;
 
 pub fn handle(action: TemplateAction) -> Result<()> {
@@ -32,6 +37,10 @@ pub fn handle(action: TemplateAction) -> Result<()> {
             println!();
             println!("Custom template created at: templates/{}/", name);
             println!("This template is now stable and will never be auto-updated.");
+            println!(
+                "Check for upstream changes with: docgen template diff {}",
+                name
+            );
             println!();
             println!("Import in .typ files:");
             println!(
@@ -40,12 +49,199 @@ pub fn handle(action: TemplateAction) -> Result<()> {
                 name.replace('-', "_")
             );
             println!();
+            println!("Use in JSON documents:");
+            println!(
+                "  \"template\": \"{}\"  or  docgen compile <file> --template {}",
+                name, name
+            );
+            println!();
             println!("Commit to Git:");
             println!("  git add templates/{}/", name);
             println!("  git commit -m \"Add custom template: {}\"", name);
         }
 
-        TemplateAction::List => {
+        TemplateAction::New {
+            name,
+            layout,
+            title,
+            prefix,
+            global,
+        } => {
+            let manifest = document_types::scaffold(&NewType {
+                name,
+                layout,
+                title,
+                prefix,
+                scope: scope(global),
+            })?;
+            let dir = scope(global).dir()?.join(&manifest.name);
+            println!(
+                "{} Created document type '{}' in {}/",
+                "✓".green(),
+                manifest.name,
+                dir.display()
+            );
+            for file in [
+                manifest.entry.as_str(),
+                packages::MANIFEST_FILE,
+                "schema.json",
+                "sample.json",
+                "locale.json",
+            ] {
+                println!("  {}", file);
+            }
+            println!();
+            if global {
+                println!("Available in all projects; new projects get its documents/ directory.");
+            } else {
+                let documents = document_types::register_in_project(&manifest)?;
+                println!("Documents go into: {}/", documents.display());
+                println!(
+                    "Number prefix:     {} (numbering.prefixes in data/company.json)",
+                    manifest.prefix.as_deref().unwrap_or_default()
+                );
+            }
+            println!();
+            let year = chrono::Local::now().format("%Y");
+            println!("Start a document from the sample:");
+            println!(
+                "  docgen template info {} --sample > documents/{}/{}/{}-{}-001.json",
+                manifest.name,
+                document_types::directory(&manifest),
+                year,
+                manifest.prefix.as_deref().unwrap_or_default(),
+                year
+            );
+        }
+
+        TemplateAction::Diff { name } => {
+            let dir = local_templates::get_custom_templates_dir().join(&name);
+            let (info, diff) = forks::diff(&dir)?;
+            if diff.is_empty() {
+                println!(
+                    "{} '{}' is up to date with '{}' (forked from v{})",
+                    "✓".green(),
+                    name,
+                    info.template,
+                    info.version
+                );
+            } else {
+                println!(
+                    "{} Changes to '{}' since v{} (current v{}):",
+                    "→".blue(),
+                    info.template,
+                    info.version,
+                    local_templates::get_docgen_version()
+                );
+                println!();
+                for line in diff.lines() {
+                    if line.starts_with("+++") || line.starts_with("---") {
+                        println!("{}", line.bold());
+                    } else if line.starts_with('+') {
+                        println!("{}", line.green());
+                    } else if line.starts_with('-') {
+                        println!("{}", line.red());
+                    } else if line.starts_with("@@") {
+                        println!("{}", line.cyan());
+                    } else {
+                        println!("{}", line);
+                    }
+                }
+                println!();
+                println!("Apply with: docgen template upgrade {}", name);
+            }
+        }
+
+        TemplateAction::Upgrade { name } => {
+            let dir = local_templates::get_custom_templates_dir().join(&name);
+            println!("{} Upgrading '{}'...", "→".blue(), name);
+            let upgrade = forks::upgrade(&dir)?;
+            for path in &upgrade.merged {
+                println!("  {} {}", "✓".green(), path);
+            }
+            for path in &upgrade.skipped {
+                println!("  {} {} (changed upstream, kept yours)", "⚠".yellow(), path);
+            }
+            for path in &upgrade.conflicts {
+                println!("  {} {} (conflict)", "✗".red(), path);
+            }
+            if !upgrade.conflicts.is_empty() {
+                anyhow::bail!(
+                    "{} file(s) in templates/{}/ have conflicts. Resolve the <<<<<<< / >>>>>>> markers before compiling.",
+                    upgrade.conflicts.len(),
+                    name
+                );
+            }
+            if upgrade.merged.is_empty() && upgrade.skipped.is_empty() {
+                println!("{} Already up to date", "✓".green());
+            } else {
+                println!(
+                    "{} Upgraded from v{} to v{}",
+                    "✓".green(),
+                    upgrade.from,
+                    local_templates::get_docgen_version()
+                );
+            }
+        }
+
+        TemplateAction::Info { name, sample } => info(&name, sample)?,
+
+        TemplateAction::Lint { name } => lint(name.as_deref())?,
+
+        TemplateAction::Install {
+            source,
+            global,
+            force,
+        } => {
+            println!("{} Installing template from {}...", "→".blue(), source);
+            let installed = packages::install(&source, scope(global), force)?;
+            let manifest = &installed.manifest;
+            println!(
+                "{} Installed {} {} ({}) to {}/",
+                "✓".green(),
+                manifest.name,
+                manifest.version,
+                manifest.doc_type,
+                installed.dir.display()
+            );
+            println!();
+            println!("Use in JSON documents:");
+            println!("  \"template\": \"{}\"", manifest.name);
+        }
+
+        TemplateAction::Uninstall { name, global } => {
+            let dir = packages::uninstall(&name, scope(global))?;
+            println!("{} Removed {}/", "✓".green(), dir.display());
+        }
+
+        TemplateAction::List { installed: true } => {
+            println!("{}", "Installed Template Packages:".bold());
+            println!("{:-<60}", "");
+            let mut found = false;
+            for scope in [packages::Scope::Project, packages::Scope::User] {
+                for package in packages::installed(scope) {
+                    let manifest = &package.manifest;
+                    println!(
+                        "  {} {:<20} {:<10} {:<15} {}",
+                        "→".green(),
+                        manifest.name,
+                        manifest.version,
+                        manifest.doc_type,
+                        format!("{}/", package.dir.display()).dimmed()
+                    );
+                    found = true;
+                }
+            }
+            if !found {
+                println!(
+                    "  {}",
+                    "No packages installed. Install with: docgen template install <dir|.tar.gz|git-url>"
+                        .yellow()
+                );
+            }
+        }
+
+        TemplateAction::List { installed: false } => {
             // List standard templates
             let standard_dir = local_templates::get_local_templates_dir();
             println!("{}", "Standard Templates (.docgen/templates/):".bold());
@@ -53,11 +249,8 @@ pub fn handle(action: TemplateAction) -> Result<()> {
             println!("{:-<60}", "");
 
             if standard_dir.exists() {
-                for template in local_templates::get_available_templates() {
-                    let template_path = standard_dir.join(&template);
-                    if template_path.exists() {
-                        println!("  {} {}", "→".blue(), template);
-                    }
+                for template in local_templates::standard_templates() {
+                    print_template(&template, "→".blue());
                 }
             } else {
                 println!(
@@ -68,6 +261,45 @@ pub fn handle(action: TemplateAction) -> Result<()> {
 
             println!();
 
+            // Shared components replaced for all standard templates
+            let overrides = local_templates::component_overrides();
+            if !overrides.is_empty() {
+                println!("{}", "Component Overrides:".bold());
+                println!(
+                    "{}",
+                    "(Replace shared components of all standard templates)".dimmed()
+                );
+                println!("{:-<60}", "");
+                for component in &overrides {
+                    let note = if component.replaces { "" } else { " (new)" };
+                    println!(
+                        "  {} {:<30} {}{}",
+                        "→".magenta(),
+                        component.path,
+                        format!("{}", component.source.display()).dimmed(),
+                        note
+                    );
+                }
+                println!();
+            }
+
+            // List user-global templates
+            if let Some(user_dir) = local_templates::get_user_templates_dir() {
+                let templates = local_templates::templates_in(&user_dir);
+                if !templates.is_empty() {
+                    println!(
+                        "{}",
+                        format!("User Templates ({}/):", user_dir.display()).bold()
+                    );
+                    println!("{}", "(Shared by all projects of this user)".dimmed());
+                    println!("{:-<60}", "");
+                    for template in &templates {
+                        print_template(template, "→".cyan());
+                    }
+                    println!();
+                }
+            }
+
             // List custom templates
             let custom_dir = local_templates::get_custom_templates_dir();
             println!("{}", "Custom Templates (templates/):".bold());
@@ -75,20 +307,11 @@ pub fn handle(action: TemplateAction) -> Result<()> {
             println!("{:-<60}", "");
 
             if custom_dir.exists() {
-                let mut found_custom = false;
-                if let Ok(entries) = std::fs::read_dir(&custom_dir) {
-                    for entry in entries.filter_map(|e| e.ok()) {
-                        if entry.file_type().ok().map(|t| t.is_dir()).unwrap_or(false) {
-                            let name = entry.file_name();
-                            let name_str = name.to_string_lossy();
-                            if name_str != ".gitkeep" {
-                                println!("  {} {}", "→".green(), name_str);
-                                found_custom = true;
-                            }
-                        }
-                    }
+                let templates = local_templates::templates_in(&custom_dir);
+                for template in &templates {
+                    print_template(template, "→".green());
                 }
-                if !found_custom {
+                if templates.is_empty() {
                     println!("  {}", "No custom templates. Fork with: docgen template fork <name> --name <custom-name>".yellow());
                 }
             } else {
@@ -97,11 +320,22 @@ pub fn handle(action: TemplateAction) -> Result<()> {
                     "Not initialized. Run: docgen template init".yellow()
                 );
             }
+
+            println!();
+            println!(
+                "{}",
+                "Select a variant with \"variant\" in the document, --variant or templates.variant in company.json"
+                    .dimmed()
+            );
         }
 
-        TemplateAction::Update => {
+        TemplateAction::Update { force } => {
             println!("{} Updating standard templates...", "→".blue());
-            local_templates::ensure_local_templates_updated()?;
+            if force {
+                local_templates::reset_local_templates()?;
+            } else {
+                local_templates::ensure_local_templates_updated()?;
+            }
             println!(
                 "{} Standard templates updated to v{}",
                 "✓".green(),
@@ -111,3 +345,235 @@ pub fn handle(action: TemplateAction) -> Result<()> {
     }
     Ok(())
 }
+
+/// One line of `template list`: name and variants
+fn print_template(template: &local_templates::Template, marker: colored::ColoredString) {
+    println!(
+        "  {} {:<20} {}",
+        marker,
+        template.name,
+        template.variants.join(", ").dimmed()
+    );
+}
+
+fn scope(global: bool) -> packages::Scope {
+    if global {
+        packages::Scope::User
+    } else {
+        packages::Scope::Project
+    }
+}
+
+/// `docgen template info`: metadata of the template `name`
+fn info(name: &str, sample: bool) -> Result<()> {
+    local_templates::ensure_local_templates_updated()?;
+    let (source, dir) = local_templates::find_template(name)
+        .with_context(|| format!("Template '{}' not found", name))?;
+    let manifest = Manifest::load(&dir)?;
+
+    if sample {
+        let file = manifest
+            .as_ref()
+            .and_then(|m| m.sample.as_ref())
+            .with_context(|| format!("Template '{}' ships no sample document", name))?;
+        print!("{}", std::fs::read_to_string(dir.join(file))?);
+        return Ok(());
+    }
+
+    println!(
+        "{} {}",
+        name.bold(),
+        format!("({}, {}/)", source, dir.display()).dimmed()
+    );
+    let Some(manifest) = manifest else {
+        println!("Variants:  {}", local_templates::variants(&dir).join(", "));
+        println!();
+        println!(
+            "{}",
+            format!("No {} metadata for this template.", packages::MANIFEST_FILE).yellow()
+        );
+        return Ok(());
+    };
+
+    if let Some(description) = &manifest.description {
+        println!("{}", description);
+    }
+    println!();
+    println!("Type:      {}", manifest.doc_type);
+    if !manifest.version.is_empty() {
+        println!("Version:   {}", manifest.version);
+    }
+    println!("Variants:  {}", local_templates::variants(&dir).join(", "));
+    if !manifest.locales.is_empty() {
+        println!("Locales:   {}", manifest.locales.join(", "));
+    }
+
+    if let Some(file) = &manifest.schema {
+        let content = std::fs::read_to_string(dir.join(file))
+            .with_context(|| format!("Failed to read {}", dir.join(file).display()))?;
+        let schema: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid JSON schema: {}", dir.join(file).display()))?;
+        let fields = schema::fields(&schema);
+        let width = fields.iter().map(|f| f.path.len()).max().unwrap_or(0);
+
+        println!();
+        println!("{}", "Data fields:".bold());
+        for field in &fields {
+            let marker = if field.required {
+                "✓".green()
+            } else {
+                "·".dimmed()
+            };
+            let example = field
+                .example
+                .as_ref()
+                .map(|e| shorten(&e.to_string(), 40))
+                .unwrap_or_default();
+            println!(
+                "  {} {:<width$}  {:<8} {}",
+                marker,
+                field.path,
+                field.kind,
+                example.dimmed(),
+                width = width
+            );
+            if let Some(description) = &field.description {
+                println!(
+                    "    {:<width$}  {}",
+                    "",
+                    description.dimmed(),
+                    width = width
+                );
+            }
+        }
+        println!("  {}", "✓ required  · optional".dimmed());
+    }
+
+    if !manifest.company_fields.is_empty() {
+        println!();
+        println!("{}", "company.json fields:".bold());
+        println!("  {}", manifest.company_fields.join(", "));
+    }
+
+    if manifest.sample.is_some() {
+        println!();
+        println!("Sample document: docgen template info {} --sample", name);
+    }
+    Ok(())
+}
+
+/// `docgen template lint`: compile `name` (or every template) with edge cases
+fn lint(name: Option<&str>) -> Result<()> {
+    local_templates::ensure_local_templates_updated()?;
+    let names = match name {
+        Some(name) => vec![name.to_string()],
+        None => lint_candidates(),
+    };
+    let company: serde_json::Value = std::fs::read_to_string("data/company.json")
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let lang = company
+        .get("language")
+        .and_then(|l| l.as_str())
+        .filter(|l| lint::LOCALES.contains(l))
+        .unwrap_or("de");
+
+    let (mut failed, mut checked) = (0, 0);
+    for name in &names {
+        let (_, dir) = local_templates::find_template(name)
+            .with_context(|| format!("Template '{}' not found", name))?;
+        let manifest = Manifest::load(&dir)?;
+        let Some(sample) = manifest.as_ref().and_then(|m| m.sample.as_ref()) else {
+            println!(
+                "{} {} {}",
+                "-".dimmed(),
+                name.bold(),
+                "skipped (no sample document)".dimmed()
+            );
+            continue;
+        };
+        let sample = read_json(&dir.join(sample))?;
+        let schema = match manifest.as_ref().and_then(|m| m.schema.as_ref()) {
+            Some(file) => Some(read_json(&dir.join(file))?),
+            None => None,
+        };
+        let cases: Vec<Case> = lint::cases(&sample, schema.as_ref(), lang);
+
+        for variant in local_templates::variants(&dir) {
+            let entry = local_templates::resolve_template(name, &variant)?;
+            let outcomes = lint::lint(&format!("{}/{}", name, variant), &entry, &cases)?;
+            checked += 1;
+            let broken = outcomes.iter().any(|o| o.error.is_some());
+            if broken {
+                failed += 1;
+            }
+            let marker = if broken { "✗".red() } else { "✓".green() };
+            println!("{} {} ({})", marker, name.bold(), variant);
+            for outcome in &outcomes {
+                if let Some(error) = &outcome.error {
+                    println!("    {} {}: {}", "✗".red(), outcome.case, error);
+                    continue;
+                }
+                let pages = format!(
+                    "{} page{}",
+                    outcome.pages,
+                    if outcome.pages == 1 { "" } else { "s" }
+                );
+                if let Some(allowed) = outcome.overflow {
+                    println!(
+                        "    {} {}: {} {}",
+                        "⚠".yellow(),
+                        outcome.case,
+                        pages,
+                        format!("(expected at most {})", allowed).yellow()
+                    );
+                } else {
+                    println!("    {} {}: {}", "✓".green(), outcome.case, pages.dimmed());
+                }
+                for warning in &outcome.warnings {
+                    println!("      {} {}", "warning:".yellow(), warning);
+                }
+            }
+        }
+    }
+
+    println!();
+    if failed > 0 {
+        anyhow::bail!("{} of {} template variant(s) failed", failed, checked);
+    }
+    println!("{} {} template variant(s) passed", "✓".green(), checked);
+    Ok(())
+}
+
+/// Every template by name: custom, user-global and standard
+fn lint_candidates() -> Vec<String> {
+    let mut names: Vec<String> = [
+        Some(local_templates::get_custom_templates_dir()),
+        local_templates::get_user_templates_dir(),
+    ]
+    .iter()
+    .flatten()
+    .flat_map(|dir| local_templates::templates_in(dir))
+    .chain(local_templates::standard_templates())
+    .map(|template| template.name)
+    .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn read_json(path: &std::path::Path) -> Result<serde_json::Value> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Invalid JSON: {}", path.display()))
+}
+
+/// Cut `text` to `max` characters for table output
+fn shorten(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        format!("{}…", text.chars().take(max - 1).collect::<String>())
+    }
+}
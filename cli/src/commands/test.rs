@@ -0,0 +1,164 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::discovery::Discovery;
+use crate::local_templates;
+use crate::render::{self, OutputFormat, RenderOptions};
+use crate::visual::{self, Image};
+
+/// Rendered pages and diff images of the last run
+const TEST_DIR: &str = ".docgen/test";
+
+/// Command line options of `docgen test`
+pub struct TestOptions {
+    /// Baseline directory (default: `baselines/` in the fixture directory)
+    pub baselines: Option<PathBuf>,
+    /// Replace the baselines with the rendered pages
+    pub update_baselines: bool,
+    /// Changed pixels allowed per page, in percent
+    pub tolerance: f64,
+    /// Resolution of the rendered pages
+    pub ppi: u32,
+}
+
+/// Render every document below `path` and compare its pages with the baselines
+///
+/// Baselines are named `<document>-<page>.png` after the document's path
+/// relative to `path`. Failing pages get a `<document>-<page>.diff.png`
+/// in .docgen/test/.
+pub fn run(path: &Path, options: TestOptions) -> Result<()> {
+    if !path.is_dir() {
+        anyhow::bail!("Fixture directory {} not found", path.display());
+    }
+    let baselines = options
+        .baselines
+        .clone()
+        .unwrap_or_else(|| path.join("baselines"));
+    local_templates::ensure_local_templates_updated()?;
+
+    let out = Path::new(TEST_DIR);
+    if out.exists() {
+        fs::remove_dir_all(out)?;
+    }
+    let render = RenderOptions {
+        format: OutputFormat::Png,
+        ppi: Some(options.ppi),
+        pages: None,
+    };
+
+    let documents = Discovery::new(&[], &[])?.find(path);
+    if documents.is_empty() {
+        anyhow::bail!("No documents in {}", path.display());
+    }
+    let mut failed = 0;
+    for input in &documents {
+        let name = input
+            .strip_prefix(path)
+            .unwrap_or(input)
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/");
+        let output = out.join(format!("{}-{}.png", name, render::PAGE_PLACEHOLDER));
+        fs::create_dir_all(output.parent().unwrap_or(out))?;
+
+        if let Err(e) = crate::render_document(input, &output, None, None, &render) {
+            failed += 1;
+            let error = e.to_string();
+            println!(
+                "{} {}: {}",
+                "✗".red(),
+                name,
+                render::first_diagnostic(&error)
+            );
+            continue;
+        }
+        let pages = render::page_files(&output);
+        let baseline = |page: usize| baselines.join(format!("{}-{}.png", name, page));
+        let existing = render::page_files(&baselines.join(format!(
+            "{}-{}.png",
+            name,
+            render::PAGE_PLACEHOLDER
+        )));
+
+        if options.update_baselines {
+            for stale in &existing {
+                fs::remove_file(stale)?;
+            }
+            fs::create_dir_all(baseline(1).parent().unwrap_or(&baselines))?;
+            for (i, page) in pages.iter().enumerate() {
+                fs::copy(page, baseline(i + 1))?;
+            }
+            println!(
+                "{} {}: {} baseline page(s) written",
+                "✓".green(),
+                name,
+                pages.len()
+            );
+            continue;
+        }
+
+        if existing.is_empty() {
+            failed += 1;
+            println!(
+                "{} {}: {}",
+                "✗".red(),
+                name,
+                "no baseline (run docgen test --update-baselines)".yellow()
+            );
+            continue;
+        }
+
+        let mut problems = Vec::new();
+        if pages.len() != existing.len() {
+            problems.push(format!(
+                "{} page(s), baseline has {}",
+                pages.len(),
+                existing.len()
+            ));
+        }
+        for (i, page) in pages.iter().enumerate().take(existing.len()) {
+            let comparison = visual::compare(&Image::load(&baseline(i + 1))?, &Image::load(page)?);
+            if comparison.percent() > options.tolerance {
+                let diff = out.join(format!("{}-{}.diff.png", name, i + 1));
+                comparison.diff.save(&diff)?;
+                problems.push(format!(
+                    "page {}: {:.2}% of pixels changed → {}",
+                    i + 1,
+                    comparison.percent(),
+                    diff.display()
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            println!("{} {}: {} page(s)", "✓".green(), name, pages.len());
+        } else {
+            failed += 1;
+            println!("{} {}", "✗".red(), name);
+            for problem in &problems {
+                println!("    {}", problem);
+            }
+        }
+    }
+
+    println!();
+    if failed > 0 {
+        anyhow::bail!("{} of {} document(s) failed", failed, documents.len());
+    }
+    if options.update_baselines {
+        println!(
+            "{} Baselines updated in {}/",
+            "✓".green(),
+            baselines.display()
+        );
+    } else {
+        println!(
+            "{} {} document(s) match their baselines",
+            "✓".green(),
+            documents.len()
+        );
+    }
+    Ok(())
+}
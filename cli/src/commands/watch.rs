@@ -0,0 +1,192 @@
+use anyhow::Result;
+use colored::Colorize;
+use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use super::build::{self, BuildOptions, Outcome};
+use crate::local_templates;
+use crate::locale::{t, tf};
+use crate::preview::Preview;
+
+/// Quiet period after the last event before rebuilding
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Project directories besides the documents that documents depend on
+const DEPENDENCY_DIRS: [&str; 4] = ["data", "locale", "templates", "fonts"];
+
+/// Rebuild stale documents whenever a document or one of its dependencies changes
+///
+/// Events are coalesced until the project has been quiet for a moment, then
+/// an incremental build runs: the build manifest decides which documents
+/// are affected, so a change to company.json or a forked template rebuilds
+/// every document using it. With `serve`, a preview server on that port
+/// shows the documents and reloads after every pass.
+pub fn run(path: &Path, output: &Path, options: BuildOptions, serve: Option<u16>) -> Result<()> {
+    let preview = serve.map(Preview::start).transpose()?;
+    let on_build = |outcome: &Outcome| {
+        if let Some(preview) = &preview {
+            preview.update(outcome);
+        }
+    };
+
+    // Templates are synced once; rewriting .docgen/ on every pass would
+    // trigger the watcher again
+    local_templates::ensure_local_templates_updated()?;
+    on_build(&build_pass(path, output, &options));
+
+    println!();
+    println!(
+        "{} {}",
+        "→".blue(),
+        tf("watch", "watching", &[&path.display().to_string()])
+    );
+    if let Some(port) = serve {
+        println!("{} Preview: http://127.0.0.1:{}/", "→".blue(), port);
+    }
+
+    let root = std::env::current_dir()?;
+    let ignored = [root.join(".docgen"), absolute(&root, output)];
+
+    let (tx, rx) = channel();
+    let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
+    watcher.watch(path, RecursiveMode::Recursive)?;
+    for dir in DEPENDENCY_DIRS
+        .map(Path::new)
+        .into_iter()
+        .filter(|d| d.is_dir())
+    {
+        // A document directory inside data/ must not be watched twice
+        if !absolute(&root, path).starts_with(absolute(&root, dir)) {
+            watcher.watch(dir, RecursiveMode::Recursive)?;
+        }
+    }
+
+    while let Some(changed) = next_changes(&rx, &ignored) {
+        println!();
+        let mut names: Vec<String> = changed
+            .iter()
+            .map(|p| p.strip_prefix(&root).unwrap_or(p).display().to_string())
+            .collect();
+        if names.len() > 3 {
+            let more = names.len() - 3;
+            names.truncate(3);
+            names.push(format!("+{}", more));
+        }
+        println!(
+            "{} {}: {}",
+            "⟳".yellow(),
+            t("watch", "change_detected"),
+            names.join(", ")
+        );
+        // Component overrides are copied into .docgen/templates/ first
+        let overrides = root
+            .join(local_templates::get_custom_templates_dir())
+            .join(local_templates::COMMON_DIR);
+        if changed.iter().any(|p| p.starts_with(&overrides)) {
+            if let Err(e) = local_templates::ensure_local_templates_updated() {
+                println!("{} {}: {}", "✗".red(), t("common", "error"), e);
+            }
+        }
+        on_build(&build_pass(path, output, &options));
+    }
+    Ok(())
+}
+
+/// One incremental build; errors are shown and watching continues
+fn build_pass(path: &Path, output: &Path, options: &BuildOptions) -> Outcome {
+    build::build(path, output, options).unwrap_or_else(|e| {
+        println!("{} {}: {}", "✗".red(), t("common", "error"), e);
+        Outcome {
+            failed: vec![(path.to_path_buf(), e.to_string())],
+            ..Outcome::default()
+        }
+    })
+}
+
+/// Wait for the next burst of relevant events; `None` once the watcher stops
+fn next_changes(
+    rx: &Receiver<notify::Result<notify::Event>>,
+    ignored: &[PathBuf],
+) -> Option<BTreeSet<PathBuf>> {
+    let mut changed = BTreeSet::new();
+    loop {
+        // Block until something happens, then drain until it is quiet again
+        let event = if changed.is_empty() {
+            rx.recv().ok()?
+        } else {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => return Some(changed),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        };
+        match event {
+            Ok(event) => {
+                if matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                ) {
+                    changed.extend(event.paths.into_iter().filter(|p| is_relevant(p, ignored)));
+                }
+            }
+            Err(e) => println!("{}: {}", t("common", "error"), e),
+        }
+    }
+}
+
+/// Skip build products, docgen's own files and editor temp files
+fn is_relevant(path: &Path, ignored: &[PathBuf]) -> bool {
+    if ignored.iter().any(|dir| path.starts_with(dir)) {
+        return false;
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_default();
+    !(name.ends_with(".lock")
+        || name.ends_with('~')
+        || name.ends_with(".swp")
+        || (name.starts_with('.') && name.contains(".tmp-")))
+}
+
+fn absolute(root: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        root.join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_relevant() {
+        let ignored = [PathBuf::from("/p/.docgen"), PathBuf::from("/p/output")];
+        assert!(is_relevant(Path::new("/p/data/company.json"), &ignored));
+        assert!(is_relevant(
+            Path::new("/p/templates/my-invoice/default.typ"),
+            &ignored
+        ));
+        assert!(!is_relevant(
+            Path::new("/p/.docgen/templates/invoice/default.typ"),
+            &ignored
+        ));
+        assert!(!is_relevant(
+            Path::new("/p/output/invoices/a.pdf"),
+            &ignored
+        ));
+        assert!(!is_relevant(
+            Path::new("/p/data/counters.json.lock"),
+            &ignored
+        ));
+        assert!(!is_relevant(
+            Path::new("/p/data/.clients.json.tmp-42"),
+            &ignored
+        ));
+    }
+}
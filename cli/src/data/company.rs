@@ -0,0 +1,92 @@
+// Default company.json written by `docgen init`
+
+/// company.json for new projects; `docgen migrate` adds missing blocks from it
+pub const TEMPLATE: &str = r##"{
+  "version": 2,
+  "name": "Your Company",
+  "language": "en",
+  "address": {
+    "street": "Street",
+    "house_number": "1",
+    "postal_code": "12345",
+    "city": "City",
+    "country": "Country"
+  },
+  "contact": {
+    "phone": "+1 234 567890",
+    "email": "info@example.com",
+    "website": "www.example.com"
+  },
+  "tax_id": "123/456/78901",
+  "vat_id": "XX123456789",
+  "business_owner": "Your Name",
+  "bank_account": {
+    "bank_name": "Bank",
+    "account_holder": "Your Company",
+    "iban": "XX00 0000 0000 0000 0000 00",
+    "bic": "BANKXXXX"
+  },
+  "branding": {
+    "accent_color": "#E94B3C",
+    "primary_color": "#2c3e50",
+    "font_preset": "inter"
+  },
+  "numbering": {
+    "year_format": "short",
+    "prefixes": {
+      "invoice": "RE",
+      "offer": "AN",
+      "credentials": "ZD",
+      "concept": "KO",
+      "documentation": "DOC"
+    }
+  },
+  "structure": {
+    "organize_by_year": true,
+    "output_pattern": "{path}.pdf"
+  },
+  "templates": {
+    "variant": {},
+    "clients": {}
+  },
+  "default_terms": {
+    "hourly_rate": "95.00",
+    "currency": "EUR",
+    "payment_days": 14,
+    "warranty_months": 12,
+    "vat_rate": 19,
+    "standard_terms": [
+      "All prices are net plus statutory VAT.",
+      "Payment due within 14 days without deduction.",
+      "Copyright remains with the contractor until full payment."
+    ]
+  },
+  "accounting": {
+    "chart": "SKR03",
+    "debtor_offset": 10000,
+    "revenue_accounts": {
+      "19": 8400,
+      "7": 8300
+    }
+  },
+  "storage": {
+    "backend": "json"
+  }
+}"##;
+
+/// Top-level blocks that older company.json files may lack
+pub const BLOCKS: [&str; 3] = ["numbering", "structure", "default_terms"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_is_valid_json() {
+        let company: serde_json::Value = serde_json::from_str(TEMPLATE).unwrap();
+        assert_eq!(company["version"], crate::data::DATA_VERSION);
+        for block in BLOCKS {
+            assert!(company[block].is_object(), "missing {}", block);
+        }
+    }
+}
@@ -0,0 +1,654 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Kind of issued accounting document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Invoice,
+    CreditNote,
+}
+
+impl DocumentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentKind::Invoice => "invoice",
+            DocumentKind::CreditNote => "credit-note",
+        }
+    }
+}
+
+/// One line of a document's VAT breakdown (amounts in cents)
+#[derive(Debug, Clone, PartialEq)]
+pub struct VatLine {
+    /// VAT rate in basis points (19% = 1900)
+    pub rate: i64,
+    /// Rate code from the document, e.g. "Standard" or "ReverseCharge"
+    pub code: Option<String>,
+    pub base: i64,
+    pub amount: i64,
+}
+
+/// VAT treatment of one breakdown line
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VatCategory {
+    /// Domestic taxable turnover at a rate (basis points)
+    Taxable(i64),
+    /// Domestic tax-free turnover (0%)
+    TaxFree,
+    /// Intra-community supply of goods to an EU business
+    IntraCommunity,
+    /// B2B service to an EU business, recipient pays VAT (§3a, §13b)
+    EuReverseCharge,
+    /// Domestic reverse charge (§13b Abs. 5, recipient pays VAT)
+    ReverseCharge,
+    /// B2C sale to another EU country, declared in the One-Stop-Shop
+    Oss(&'static str, i64),
+    /// Tax-free export or service to a recipient outside the EU
+    Export,
+}
+
+impl VatCategory {
+    /// Classify a breakdown line by its rate code and the recipient
+    pub fn classify(doc: &IssuedDocument, rate: i64, code: Option<&str>, home: &str) -> Self {
+        let code = code.unwrap_or_default().to_lowercase();
+        let foreign = doc.recipient_country.filter(|c| *c != home);
+        let foreign_eu = foreign.filter(|c| is_eu(c));
+        let reverse = code.contains("reverse") || code.contains("13b");
+
+        if foreign.is_some() && foreign_eu.is_none() && (rate == 0 || reverse) {
+            return VatCategory::Export;
+        }
+        if reverse {
+            return match foreign_eu {
+                Some(_) => VatCategory::EuReverseCharge,
+                None => VatCategory::ReverseCharge,
+            };
+        }
+        if code.contains("intra") || code.contains("innergemeinschaftlich") || code == "ig" {
+            return VatCategory::IntraCommunity;
+        }
+        match foreign_eu {
+            Some(country) if doc.recipient_vat_id.is_none() && rate > 0 => {
+                VatCategory::Oss(country, rate)
+            }
+            _ if rate == 0 => VatCategory::TaxFree,
+            _ => VatCategory::Taxable(rate),
+        }
+    }
+}
+
+/// An invoice or credit note read from the documents/ tree
+///
+/// All amounts are in cents and always positive; use `sign()` to get
+/// the direction (credit notes reduce revenue).
+#[derive(Debug, Clone)]
+pub struct IssuedDocument {
+    pub kind: DocumentKind,
+    pub number: String,
+    pub date: NaiveDate,
+    pub due_date: Option<NaiveDate>,
+    pub paid_date: Option<NaiveDate>,
+    pub customer_number: Option<String>,
+    pub recipient: String,
+    /// ISO country code of the recipient address, if recognisable
+    pub recipient_country: Option<&'static str>,
+    pub recipient_vat_id: Option<String>,
+    pub project: Option<String>,
+    /// Invoice number a credit note refers to
+    pub invoice_reference: Option<String>,
+    pub currency: String,
+    pub net: i64,
+    pub vat: Vec<VatLine>,
+    pub total: i64,
+    pub path: PathBuf,
+}
+
+impl IssuedDocument {
+    /// +1 for invoices, -1 for credit notes
+    pub fn sign(&self) -> i64 {
+        match self.kind {
+            DocumentKind::Invoice => 1,
+            DocumentKind::CreditNote => -1,
+        }
+    }
+
+    pub fn vat_total(&self) -> i64 {
+        self.vat.iter().map(|v| v.amount).sum()
+    }
+
+    /// Parse an issued document from its JSON data
+    ///
+    /// Returns `Ok(None)` for JSON files that are not invoices or credit notes.
+    pub fn from_json(path: &Path, json: &Value) -> Result<Option<Self>> {
+        let Some(metadata) = json.get("metadata") else {
+            return Ok(None);
+        };
+
+        let (kind, number, date) = if let Some(number) = metadata.get("invoice_number") {
+            (DocumentKind::Invoice, number, metadata.get("invoice_date"))
+        } else if let Some(number) = metadata.get("credit_note_number") {
+            (DocumentKind::CreditNote, number, metadata.get("date"))
+        } else {
+            return Ok(None);
+        };
+
+        let number = number
+            .as_str()
+            .with_context(|| format!("{}: document number is not a string", path.display()))?
+            .to_string();
+        let date = date
+            .and_then(parse_date)
+            .with_context(|| format!("{}: missing or invalid document date", path.display()))?;
+
+        let totals = json.get("totals");
+        let total = totals
+            .and_then(|t| t.get("total"))
+            .and_then(parse_money)
+            .with_context(|| format!("{}: missing totals.total", path.display()))?;
+        let net = totals
+            .and_then(|t| t.get("subtotal"))
+            .and_then(parse_money)
+            .unwrap_or(total);
+
+        let mut vat = Vec::new();
+        if let Some(lines) = totals
+            .and_then(|t| t.get("vat_breakdown"))
+            .and_then(|v| v.as_array())
+        {
+            for line in lines {
+                let rate = line
+                    .get("rate")
+                    .and_then(|r| r.get("percentage"))
+                    .and_then(parse_decimal)
+                    .unwrap_or(0);
+                let code = line
+                    .get("rate")
+                    .and_then(|r| r.get("code"))
+                    .and_then(|c| c.as_str())
+                    .map(String::from);
+                let amount = line.get("amount").and_then(parse_money).unwrap_or(0);
+                // Older credit notes carry no base per rate
+                let base = match line.get("base").and_then(parse_money) {
+                    Some(base) => base,
+                    None if lines.len() == 1 => net,
+                    None if rate > 0 => amount * 10_000 / rate,
+                    None => 0,
+                };
+                vat.push(VatLine {
+                    rate,
+                    code,
+                    base,
+                    amount,
+                });
+            }
+        }
+
+        let recipient = json.get("recipient");
+        let recipient_name = recipient
+            .and_then(|r| r.get("company"))
+            .and_then(|c| c.as_str())
+            .filter(|c| !c.is_empty())
+            .or_else(|| {
+                recipient
+                    .and_then(|r| r.get("name"))
+                    .and_then(|n| n.as_str())
+            })
+            .unwrap_or_default()
+            .to_string();
+
+        let currency = totals
+            .and_then(|t| t.get("total"))
+            .and_then(|t| t.get("currency"))
+            .and_then(|c| c.as_str())
+            .unwrap_or("EUR")
+            .to_string();
+
+        let payment = json.get("payment");
+        let due_date = metadata
+            .get("due_date")
+            .or_else(|| payment.and_then(|p| p.get("due_date")))
+            .and_then(parse_date);
+        let paid_date = payment
+            .and_then(|p| p.get("paid_date"))
+            .or_else(|| metadata.get("paid_date"))
+            .and_then(parse_date);
+
+        Ok(Some(Self {
+            kind,
+            number,
+            date,
+            due_date,
+            paid_date,
+            customer_number: metadata
+                .get("customer_number")
+                .and_then(|c| c.as_str())
+                .map(String::from),
+            recipient: recipient_name,
+            recipient_country: recipient
+                .and_then(|r| r.get("address"))
+                .and_then(|a| a.get("country"))
+                .and_then(|c| c.as_str())
+                .and_then(country_code),
+            recipient_vat_id: recipient
+                .and_then(|r| r.get("vat_id"))
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.trim().is_empty())
+                .map(String::from),
+            project: metadata
+                .get("project_reference")
+                .and_then(|p| p.as_str())
+                .map(String::from),
+            invoice_reference: metadata
+                .get("invoice_reference")
+                .and_then(|r| r.as_str())
+                .map(String::from),
+            currency,
+            net,
+            vat,
+            total,
+            path: path.to_path_buf(),
+        }))
+    }
+}
+
+/// Load all invoices and credit notes below a directory, sorted by date
+///
+/// Credit notes without their own customer or project reference inherit
+/// them from the invoice they refer to.
+pub fn load_issued_documents(root: &Path) -> Result<Vec<IssuedDocument>> {
+    let mut documents = Vec::new();
+
+    if !root.exists() {
+        return Ok(documents);
+    }
+
+    for entry in WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+    {
+        let path = entry.path();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        // Helper and sample JSON files are not documents
+        let Ok(json) = serde_json::from_str::<Value>(&content) else {
+            continue;
+        };
+        if let Some(doc) = IssuedDocument::from_json(path, &json)? {
+            documents.push(doc);
+        }
+    }
+
+    let invoices: Vec<(String, Option<String>, Option<String>)> = documents
+        .iter()
+        .filter(|d| d.kind == DocumentKind::Invoice)
+        .map(|d| {
+            (
+                d.number.clone(),
+                d.customer_number.clone(),
+                d.project.clone(),
+            )
+        })
+        .collect();
+    for doc in documents
+        .iter_mut()
+        .filter(|d| d.kind == DocumentKind::CreditNote)
+    {
+        let Some(reference) = &doc.invoice_reference else {
+            continue;
+        };
+        if let Some((_, customer, project)) = invoices.iter().find(|(n, _, _)| n == reference) {
+            if doc.customer_number.is_none() {
+                doc.customer_number = customer.clone();
+            }
+            if doc.project.is_none() {
+                doc.project = project.clone();
+            }
+        }
+    }
+
+    documents.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.number.cmp(&b.number)));
+    Ok(documents)
+}
+
+/// Metadata fields holding the number of a document, by document type
+const NUMBER_FIELDS: [&str; 10] = [
+    "invoice_number",
+    "credit_note_number",
+    "offer_number",
+    "document_number",
+    "confirmation_number",
+    "delivery_note_number",
+    "reminder_number",
+    "request_number",
+    "timesheet_number",
+    "reference_number",
+];
+
+/// Document number of any document JSON ("RE-2025-001", "ZD-2025-007", ...)
+pub fn document_number(json: &Value) -> Option<String> {
+    let metadata = json.get("metadata").unwrap_or(json);
+    NUMBER_FIELDS
+        .iter()
+        .find_map(|field| metadata.get(*field)?.as_str())
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+}
+
+/// Parse a `{ "date": "YYYY-MM-DD" }` object (or a plain date string)
+pub fn parse_date(value: &Value) -> Option<NaiveDate> {
+    let s = value.get("date").unwrap_or(value).as_str()?;
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(s, "%d.%m.%Y"))
+        .ok()
+}
+
+/// Parse a `{ "amount": ... }` money object (or a bare amount) into cents
+pub fn parse_money(value: &Value) -> Option<i64> {
+    parse_decimal(value.get("amount").unwrap_or(value))
+}
+
+/// Parse a decimal number given as JSON number or string into hundredths
+///
+/// Accepts "1234.5", "1234,50" and 1234.5 alike, as well as thousands
+/// separators ("1.234,56", "1,234.56", "1.234.567"). Anything else, such
+/// as misplaced separators or letters, is rejected.
+pub fn parse_decimal(value: &Value) -> Option<i64> {
+    let s = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        _ => return None,
+    };
+
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.as_str()),
+    };
+    let (int_part, frac_part) = split_decimal(s)?;
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if (int_part.is_empty() && frac_part.is_empty())
+        || !is_digits(&int_part)
+        || !is_digits(frac_part)
+    {
+        return None;
+    }
+    let int: i64 = if int_part.is_empty() {
+        0
+    } else {
+        int_part.parse().ok()?
+    };
+    let mut frac_digits = frac_part.bytes().map(|b| i64::from(b - b'0'));
+    let frac = frac_digits.next().unwrap_or(0) * 10 + frac_digits.next().unwrap_or(0);
+    // Round half up once, on everything after the cents
+    let round_up = frac_digits.next().is_some_and(|digit| digit >= 5);
+    let cents = int
+        .checked_mul(100)?
+        .checked_add(frac + i64::from(round_up))?;
+
+    Some(if negative { -cents } else { cents })
+}
+
+/// Integer part without thousands separators, and the fraction digits
+///
+/// With both ',' and '.' the last one is the decimal separator. A single
+/// ',' or '.' is a decimal separator; a repeated one separates thousands.
+fn split_decimal(s: &str) -> Option<(String, &str)> {
+    let decimal = match (s.rfind(','), s.rfind('.')) {
+        (Some(comma), Some(dot)) => Some(comma.max(dot)),
+        (Some(comma), None) if s.matches(',').count() == 1 => Some(comma),
+        (None, Some(dot)) if s.matches('.').count() == 1 => Some(dot),
+        _ => None,
+    };
+    let (int_part, frac_part) = match decimal {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => (s, ""),
+    };
+    let thousands = match decimal.map(|i| &s[i..=i]) {
+        Some(",") => '.',
+        Some(_) => ',',
+        None if s.contains('.') => '.',
+        None => ',',
+    };
+
+    // Thousands separators must split the digits into groups of three
+    let groups: Vec<&str> = int_part.split(thousands).collect();
+    if groups.len() > 1
+        && (groups[0].is_empty() || groups[0].len() > 3 || groups[1..].iter().any(|g| g.len() != 3))
+    {
+        return None;
+    }
+    Some((groups.concat(), frac_part))
+}
+
+/// Format cents as a plain decimal ("1234.56"), used for CSV and JSON output
+pub fn format_decimal(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, cents.abs() / 100, cents.abs() % 100)
+}
+
+/// Format cents in German notation ("1.234,56"), used for tables and PDFs
+pub fn format_money(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let int = (cents.abs() / 100).to_string();
+    let mut grouped = String::new();
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i).is_multiple_of(3) {
+            grouped.push('.');
+        }
+        grouped.push(c);
+    }
+    format!("{}{},{:02}", sign, grouped, cents.abs() % 100)
+}
+
+/// Format a rate in basis points as percentage ("19%", "7,5%")
+pub fn format_rate(rate: i64) -> String {
+    if rate % 100 == 0 {
+        format!("{}%", rate / 100)
+    } else {
+        format!("{}%", format_money(rate).trim_end_matches('0'))
+    }
+}
+
+/// Country of the company address (data/company.json), DE if unknown
+pub fn home_country() -> String {
+    fs::read_to_string("data/company.json")
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|company| {
+            company
+                .pointer("/address/country")
+                .and_then(|c| c.as_str())
+                .and_then(country_code)
+        })
+        .unwrap_or("DE")
+        .to_string()
+}
+
+/// EU member states with their ISO code and common spellings
+const EU_COUNTRIES: &[(&str, &[&str])] = &[
+    ("AT", &["austria", "österreich", "oesterreich"]),
+    ("BE", &["belgium", "belgien", "belgique", "belgië"]),
+    ("BG", &["bulgaria", "bulgarien"]),
+    ("CY", &["cyprus", "zypern"]),
+    ("CZ", &["czech republic", "czechia", "tschechien"]),
+    ("DE", &["germany", "deutschland"]),
+    ("DK", &["denmark", "dänemark", "daenemark"]),
+    ("EE", &["estonia", "estland"]),
+    ("ES", &["spain", "spanien", "españa"]),
+    ("FI", &["finland", "finnland"]),
+    ("FR", &["france", "frankreich"]),
+    ("GR", &["greece", "griechenland", "el"]),
+    ("HR", &["croatia", "kroatien"]),
+    ("HU", &["hungary", "ungarn"]),
+    ("IE", &["ireland", "irland"]),
+    ("IT", &["italy", "italien", "italia"]),
+    ("LT", &["lithuania", "litauen"]),
+    ("LU", &["luxembourg", "luxemburg"]),
+    ("LV", &["latvia", "lettland"]),
+    ("MT", &["malta"]),
+    ("NL", &["netherlands", "niederlande", "nederland"]),
+    ("PL", &["poland", "polen", "polska"]),
+    ("PT", &["portugal"]),
+    ("RO", &["romania", "rumänien", "rumaenien"]),
+    ("SE", &["sweden", "schweden", "sverige"]),
+    ("SI", &["slovenia", "slowenien"]),
+    ("SK", &["slovakia", "slowakei"]),
+];
+
+/// Frequent countries outside the EU with their ISO code and common spellings
+const NON_EU_COUNTRIES: &[(&str, &[&str])] = &[
+    ("AU", &["australia", "australien"]),
+    ("CA", &["canada", "kanada"]),
+    ("CH", &["switzerland", "schweiz", "suisse", "svizzera"]),
+    ("CN", &["china"]),
+    (
+        "GB",
+        &[
+            "united kingdom",
+            "uk",
+            "great britain",
+            "england",
+            "großbritannien",
+            "grossbritannien",
+            "vereinigtes königreich",
+        ],
+    ),
+    ("IN", &["india", "indien"]),
+    ("IS", &["iceland", "island"]),
+    ("JP", &["japan"]),
+    ("LI", &["liechtenstein"]),
+    ("NO", &["norway", "norwegen", "norge"]),
+    ("RS", &["serbia", "serbien"]),
+    ("SG", &["singapore", "singapur"]),
+    ("TR", &["turkey", "türkei", "tuerkei", "türkiye"]),
+    ("UA", &["ukraine"]),
+    ("US", &["united states", "usa", "vereinigte staaten"]),
+];
+
+/// Map a country name or ISO code to its ISO code
+///
+/// Knows all EU members and frequent other countries; anything else is
+/// unknown and treated like the home country.
+pub fn country_code(country: &str) -> Option<&'static str> {
+    let needle = country.trim().to_lowercase();
+    EU_COUNTRIES
+        .iter()
+        .chain(NON_EU_COUNTRIES)
+        .find(|(code, names)| {
+            code.eq_ignore_ascii_case(&needle) || names.contains(&needle.as_str())
+        })
+        .map(|(code, _)| *code)
+}
+
+/// Whether an ISO code belongs to an EU member state
+pub fn is_eu(code: &str) -> bool {
+    EU_COUNTRIES.iter().any(|(eu, _)| *eu == code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_decimal() {
+        assert_eq!(parse_decimal(&json!("39900")), Some(3_990_000));
+        assert_eq!(parse_decimal(&json!(95.5)), Some(9550));
+        assert_eq!(parse_decimal(&json!("12,345")), Some(1235));
+        assert_eq!(parse_decimal(&json!("-0.5")), Some(-50));
+        assert_eq!(parse_decimal(&json!(null)), None);
+    }
+
+    #[test]
+    fn test_parse_decimal_thousands_separators() {
+        assert_eq!(parse_decimal(&json!("1.234,56")), Some(123_456));
+        assert_eq!(parse_decimal(&json!("1,234.56")), Some(123_456));
+        assert_eq!(parse_decimal(&json!("1.234.567")), Some(123_456_700));
+        assert_eq!(parse_decimal(&json!("-12.345,6")), Some(-1_234_560));
+
+        // Misplaced separators and stray characters are errors, not truncated
+        assert_eq!(parse_decimal(&json!("1.234.56")), None);
+        assert_eq!(parse_decimal(&json!("12.34,56")), None);
+        assert_eq!(parse_decimal(&json!("1,5,0")), None);
+        assert_eq!(parse_decimal(&json!("12.5 EUR")), None);
+        assert_eq!(parse_decimal(&json!("")), None);
+        assert_eq!(parse_decimal(&json!("-")), None);
+    }
+
+    #[test]
+    fn test_parse_decimal_rounds_once() {
+        assert_eq!(parse_decimal(&json!("0.0049")), Some(0));
+        assert_eq!(parse_decimal(&json!("0.0049999")), Some(0));
+        assert_eq!(parse_decimal(&json!("0.005")), Some(1));
+        assert_eq!(parse_decimal(&json!("1,23456")), Some(123));
+        assert_eq!(parse_decimal(&json!("-2.675")), Some(-268));
+
+        // Too large for cents
+        assert_eq!(
+            parse_decimal(&json!("92233720368547758.07")),
+            Some(i64::MAX)
+        );
+        assert_eq!(parse_decimal(&json!("92233720368547758.08")), None);
+        assert_eq!(parse_decimal(&json!("99999999999999999999")), None);
+    }
+
+    #[test]
+    fn test_format_money() {
+        assert_eq!(format_money(123_456_789), "1.234.567,89");
+        assert_eq!(format_money(-5), "-0,05");
+        assert_eq!(format_decimal(-123_456), "-1234.56");
+        assert_eq!(format_rate(1900), "19%");
+        assert_eq!(format_rate(750), "7,5%");
+    }
+
+    #[test]
+    fn test_country_code() {
+        assert_eq!(country_code("Deutschland"), Some("DE"));
+        assert_eq!(country_code(" Österreich "), Some("AT"));
+        assert_eq!(country_code("fr"), Some("FR"));
+        assert_eq!(country_code("Schweiz"), Some("CH"));
+        assert_eq!(country_code("USA"), Some("US"));
+        assert_eq!(country_code("Atlantis"), None);
+        assert!(is_eu("AT") && !is_eu("CH"));
+    }
+
+    #[test]
+    fn test_credit_note_without_base() {
+        let json = json!({
+            "metadata": {
+                "credit_note_number": "GS-2025-001",
+                "invoice_reference": "RE-2024-156",
+                "date": { "date": "2025-01-20" }
+            },
+            "recipient": { "name": "Dr. Schmidt", "company": "DataFlow AG" },
+            "totals": {
+                "subtotal": { "amount": 1900.00, "currency": "EUR" },
+                "vat_breakdown": [
+                    { "rate": { "percentage": 19 }, "amount": { "amount": 361.00 } }
+                ],
+                "total": { "amount": 2261.00, "currency": "EUR" }
+            }
+        });
+
+        let doc = IssuedDocument::from_json(Path::new("gs.json"), &json)
+            .unwrap()
+            .unwrap();
+        assert_eq!(doc.kind, DocumentKind::CreditNote);
+        assert_eq!(doc.sign(), -1);
+        assert_eq!(doc.recipient, "DataFlow AG");
+        assert_eq!(doc.vat[0].base, 190_000);
+        assert_eq!(doc.vat_total(), 36_100);
+    }
+
+    #[test]
+    fn test_non_accounting_json_is_skipped() {
+        let json = json!({ "metadata": { "offer_number": "AN-2025-001" } });
+        assert!(IssuedDocument::from_json(Path::new("an.json"), &json)
+            .unwrap()
+            .is_none());
+    }
+}
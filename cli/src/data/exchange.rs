@@ -0,0 +1,484 @@
+// Client import/export: CSV with column mapping and vCard (.vcf)
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+use super::models::{Client, NewClient};
+
+/// A client field that import columns can be mapped to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientField {
+    Name,
+    Company,
+    Street,
+    HouseNumber,
+    PostalCode,
+    City,
+    Country,
+    Email,
+    Phone,
+    Notes,
+}
+
+impl ClientField {
+    /// Parse a field name as used in `--map` ("company", "postal_code", ...)
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(
+            match name.trim().to_lowercase().replace(['-', ' '], "_").as_str() {
+                "name" => ClientField::Name,
+                "company" => ClientField::Company,
+                "street" => ClientField::Street,
+                "house_number" => ClientField::HouseNumber,
+                "postal_code" | "zip" => ClientField::PostalCode,
+                "city" => ClientField::City,
+                "country" => ClientField::Country,
+                "email" => ClientField::Email,
+                "phone" => ClientField::Phone,
+                "notes" => ClientField::Notes,
+                _ => return None,
+            },
+        )
+    }
+
+    /// Guess the field from a CSV header (English and German spellings)
+    fn guess(header: &str) -> Option<Self> {
+        let header = header.trim().to_lowercase();
+        Self::parse(&header).or_else(|| {
+            Some(match header.as_str() {
+                "kunde" | "ansprechpartner" | "contact" | "contact name" | "full name" => {
+                    ClientField::Name
+                }
+                "firma" | "unternehmen" | "organization" | "organisation" => ClientField::Company,
+                "straße" | "strasse" | "address" | "adresse" => ClientField::Street,
+                "hausnummer" | "hausnr" | "nr" => ClientField::HouseNumber,
+                "plz" | "postleitzahl" | "zip code" | "postcode" => ClientField::PostalCode,
+                "ort" | "stadt" | "town" => ClientField::City,
+                "land" => ClientField::Country,
+                "e-mail" | "mail" => ClientField::Email,
+                "telefon" | "tel" | "telephone" | "mobile" => ClientField::Phone,
+                "notizen" | "bemerkung" | "note" => ClientField::Notes,
+                _ => return None,
+            })
+        })
+    }
+
+    fn set(self, client: &mut NewClient, value: String) {
+        let slot = match self {
+            ClientField::Name => {
+                client.name = value;
+                return;
+            }
+            ClientField::Company => &mut client.company,
+            ClientField::Street => &mut client.street,
+            ClientField::HouseNumber => &mut client.house_number,
+            ClientField::PostalCode => &mut client.postal_code,
+            ClientField::City => &mut client.city,
+            ClientField::Country => &mut client.country,
+            ClientField::Email => &mut client.email,
+            ClientField::Phone => &mut client.phone,
+            ClientField::Notes => &mut client.notes,
+        };
+        *slot = Some(value);
+    }
+}
+
+/// Parse a `--map` option: "Firma=company,Ort=city"
+pub fn parse_mapping(input: &str) -> Result<HashMap<String, ClientField>> {
+    let mut mapping = HashMap::new();
+    for pair in input.split(',').filter(|p| !p.trim().is_empty()) {
+        let (column, field) = pair
+            .split_once('=')
+            .with_context(|| format!("Invalid mapping '{}', expected Column=field", pair))?;
+        let field = ClientField::parse(field).with_context(|| {
+            format!(
+                "Unknown client field '{}'. Valid fields: name, company, street, house_number, \
+                 postal_code, city, country, email, phone, notes",
+                field.trim()
+            )
+        })?;
+        mapping.insert(column.trim().to_lowercase(), field);
+    }
+    Ok(mapping)
+}
+
+/// Read clients from CSV; explicit mappings win over guessed headers
+///
+/// The delimiter (',' or ';') is detected from the header line.
+pub fn read_csv(content: &str, mapping: &HashMap<String, ClientField>) -> Result<Vec<NewClient>> {
+    let header_line = content.lines().next().unwrap_or_default();
+    let delimiter = if header_line.matches(';').count() > header_line.matches(',').count() {
+        b';'
+    } else {
+        b','
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(content.trim_start_matches('\u{feff}').as_bytes());
+
+    let columns: Vec<Option<ClientField>> = reader
+        .headers()
+        .context("Failed to read CSV header")?
+        .iter()
+        .map(|h| {
+            mapping
+                .get(&h.trim().to_lowercase())
+                .copied()
+                .or_else(|| ClientField::guess(h))
+        })
+        .collect();
+
+    if !columns.contains(&Some(ClientField::Name)) && !columns.contains(&Some(ClientField::Company))
+    {
+        anyhow::bail!("CSV has no name or company column. Map one with --map \"<Column>=name\"");
+    }
+
+    let mut clients = Vec::new();
+    for record in reader.records() {
+        let record = record.context("Failed to read CSV row")?;
+        let mut client = NewClient::default();
+        for (value, field) in record.iter().zip(&columns) {
+            let value = value.trim();
+            if let (Some(field), false) = (field, value.is_empty()) {
+                field.set(&mut client, value.to_string());
+            }
+        }
+        if !columns.contains(&Some(ClientField::HouseNumber)) {
+            split_house_number(&mut client);
+        }
+        clients.push(client);
+    }
+
+    Ok(clients)
+}
+
+/// Write clients as CSV using the field names as header
+pub fn write_csv(clients: &[Client]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "number",
+        "name",
+        "company",
+        "street",
+        "house_number",
+        "postal_code",
+        "city",
+        "country",
+        "email",
+        "phone",
+        "notes",
+    ])?;
+    for c in clients {
+        let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+        writer.write_record([
+            c.formatted_number(),
+            c.name.clone(),
+            opt(&c.company),
+            opt(&c.street),
+            opt(&c.house_number),
+            opt(&c.postal_code),
+            opt(&c.city),
+            opt(&c.country),
+            opt(&c.email),
+            opt(&c.phone),
+            opt(&c.notes),
+        ])?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// "Hauptstraße 12a" → street "Hauptstraße", house number "12a"
+fn split_house_number(client: &mut NewClient) {
+    let Some(street) = &client.street else {
+        return;
+    };
+    if let Some((name, number)) = street.trim().rsplit_once(' ') {
+        if number.chars().next().is_some_and(|c| c.is_ascii_digit()) {
+            client.house_number = Some(number.to_string());
+            client.street = Some(name.trim().to_string());
+        }
+    }
+}
+
+/// Read all contacts from a vCard file (versions 2.1, 3.0 and 4.0)
+pub fn read_vcards(content: &str) -> Result<Vec<NewClient>> {
+    // Unfold continuation lines (RFC 6350 §3.2)
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match line.strip_prefix([' ', '\t']) {
+            Some(rest) if !lines.is_empty() => lines.last_mut().unwrap().push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut clients = Vec::new();
+    let mut current: Option<NewClient> = None;
+
+    for line in lines {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Strip group prefix ("item1.EMAIL") and parameters ("EMAIL;TYPE=work")
+        let key = key.split(';').next().unwrap_or_default();
+        let key = key.rsplit('.').next().unwrap_or_default().to_uppercase();
+
+        match key.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VCARD") => current = Some(NewClient::default()),
+            "END" if value.eq_ignore_ascii_case("VCARD") => {
+                if let Some(mut client) = current.take() {
+                    if client.name.is_empty() {
+                        client.name = client.company.clone().unwrap_or_default();
+                    }
+                    if !client.name.is_empty() {
+                        clients.push(client);
+                    }
+                }
+            }
+            _ => {
+                let Some(client) = current.as_mut() else {
+                    continue;
+                };
+                let fields = split_unescaped(value, ';');
+                let first = fields.first().cloned().unwrap_or_default();
+                match key.as_str() {
+                    "FN" => client.name = first,
+                    "N" if client.name.is_empty() => {
+                        // N:Family;Given;Additional;Prefix;Suffix
+                        let given = fields.get(1).cloned().unwrap_or_default();
+                        client.name = format!("{} {}", given, first).trim().to_string();
+                    }
+                    "ORG" => client.company = Some(first).filter(|s| !s.is_empty()),
+                    "ADR" if client.street.is_none() => {
+                        // ADR:PO box;extended;street;city;region;postal code;country
+                        let get =
+                            |i: usize| fields.get(i).cloned().filter(|s: &String| !s.is_empty());
+                        client.street = get(2);
+                        client.city = get(3);
+                        client.postal_code = get(5);
+                        if let Some(country) = get(6) {
+                            client.country = Some(country);
+                        }
+                        split_house_number(client);
+                    }
+                    "EMAIL" if client.email.is_none() => client.email = Some(first),
+                    "TEL" if client.phone.is_none() => {
+                        client.phone = Some(first.trim_start_matches("tel:").to_string())
+                    }
+                    "NOTE" => client.notes = Some(unescape(value)),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok(clients)
+}
+
+/// Write clients as vCard 3.0
+pub fn write_vcards(clients: &[Client]) -> String {
+    let mut out = String::new();
+    for c in clients {
+        out.push_str("BEGIN:VCARD\r\nVERSION:3.0\r\n");
+        out.push_str(&format!("FN:{}\r\n", escape(&c.name)));
+        let (given, family) = match c.name.rsplit_once(' ') {
+            Some((given, family)) => (given, family),
+            None => ("", c.name.as_str()),
+        };
+        out.push_str(&format!("N:{};{};;;\r\n", escape(family), escape(given)));
+        if let Some(company) = &c.company {
+            out.push_str(&format!("ORG:{}\r\n", escape(company)));
+        }
+        if c.street.is_some() || c.city.is_some() || c.postal_code.is_some() {
+            let street = match (&c.street, &c.house_number) {
+                (Some(street), Some(number)) => format!("{} {}", street, number),
+                (Some(street), None) => street.clone(),
+                _ => String::new(),
+            };
+            out.push_str(&format!(
+                "ADR;TYPE=work:;;{};{};;{};{}\r\n",
+                escape(&street),
+                escape(c.city.as_deref().unwrap_or_default()),
+                escape(c.postal_code.as_deref().unwrap_or_default()),
+                escape(c.country.as_deref().unwrap_or_default()),
+            ));
+        }
+        if let Some(email) = &c.email {
+            out.push_str(&format!("EMAIL;TYPE=work:{}\r\n", escape(email)));
+        }
+        if let Some(phone) = &c.phone {
+            out.push_str(&format!("TEL;TYPE=work:{}\r\n", escape(phone)));
+        }
+        if let Some(notes) = &c.notes {
+            out.push_str(&format!("NOTE:{}\r\n", escape(notes)));
+        }
+        out.push_str(&format!("X-DOCGEN-NUMBER:{}\r\n", c.formatted_number()));
+        out.push_str("END:VCARD\r\n");
+    }
+    out
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => {}
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Split on a separator that is not escaped with a backslash, unescaping parts
+fn split_unescaped(value: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut escaped = false;
+    for c in value.chars() {
+        if escaped {
+            parts.last_mut().unwrap().push('\\');
+            parts.last_mut().unwrap().push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+    parts.into_iter().map(|p| unescape(&p)).collect()
+}
+
+/// Find an existing client with the same email or company name
+pub fn find_duplicate<'a>(
+    candidate: &NewClient,
+    existing: impl IntoIterator<Item = (&'a str, &'a Option<String>, &'a Option<String>)>,
+) -> Option<&'a str> {
+    let normalize = |s: &Option<String>| {
+        s.as_deref()
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+    };
+    let email = normalize(&candidate.email);
+    let company = normalize(&candidate.company);
+
+    existing
+        .into_iter()
+        .find(|(_, other_email, other_company)| {
+            (email.is_some() && email == normalize(other_email))
+                || (company.is_some() && company == normalize(other_company))
+        })
+        .map(|(label, _, _)| label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_with_german_headers_and_mapping() {
+        let csv = "Kunde;Firma;Straße;PLZ;Ort;E-Mail;Kundennr\n\
+                   Max Muster;Muster GmbH;Hauptstraße 12a;12345;Berlin;max@muster.de;4711\n";
+        let mapping = parse_mapping("Kundennr=notes").unwrap();
+        let clients = read_csv(csv, &mapping).unwrap();
+
+        assert_eq!(clients.len(), 1);
+        let c = &clients[0];
+        assert_eq!(c.name, "Max Muster");
+        assert_eq!(c.company.as_deref(), Some("Muster GmbH"));
+        assert_eq!(c.street.as_deref(), Some("Hauptstraße"));
+        assert_eq!(c.house_number.as_deref(), Some("12a"));
+        assert_eq!(c.postal_code.as_deref(), Some("12345"));
+        assert_eq!(c.email.as_deref(), Some("max@muster.de"));
+        assert_eq!(c.notes.as_deref(), Some("4711"));
+    }
+
+    #[test]
+    fn test_mapping_rejects_unknown_field() {
+        assert!(parse_mapping("Firma=firm").is_err());
+        assert!(parse_mapping("Firma").is_err());
+    }
+
+    #[test]
+    fn test_vcard_roundtrip() {
+        let vcf = "BEGIN:VCARD\r\nVERSION:3.0\r\nN:Schmidt;Sarah;;Dr.;\r\n\
+                   ORG:DataFlow Analytics AG\r\n\
+                   ADR;TYPE=work:;;Datenweg 15;Berlin;;10115;Deutschland\r\n\
+                   item1.EMAIL;TYPE=INTERNET:sarah@dataflow.example\r\n\
+                   NOTE:Prefers email\\, not phone\r\n  (since 2024)\r\n\
+                   END:VCARD\r\n";
+        let clients = read_vcards(vcf).unwrap();
+        assert_eq!(clients.len(), 1);
+        let c = &clients[0];
+        assert_eq!(c.name, "Sarah Schmidt");
+        assert_eq!(c.street.as_deref(), Some("Datenweg"));
+        assert_eq!(c.house_number.as_deref(), Some("15"));
+        assert_eq!(c.postal_code.as_deref(), Some("10115"));
+        assert_eq!(c.email.as_deref(), Some("sarah@dataflow.example"));
+        assert_eq!(
+            c.notes.as_deref(),
+            Some("Prefers email, not phone (since 2024)")
+        );
+
+        let client = Client {
+            id: 1,
+            number: 3,
+            name: c.name.clone(),
+            company: c.company.clone(),
+            street: c.street.clone(),
+            house_number: c.house_number.clone(),
+            postal_code: c.postal_code.clone(),
+            city: c.city.clone(),
+            country: c.country.clone(),
+            email: c.email.clone(),
+            phone: None,
+            notes: c.notes.clone(),
+            created_at: String::new(),
+        };
+        let exported = write_vcards(&[client]);
+        assert!(exported.contains("ADR;TYPE=work:;;Datenweg 15;Berlin;;10115;Deutschland\r\n"));
+        assert!(exported.contains("X-DOCGEN-NUMBER:K-003\r\n"));
+        let again = read_vcards(&exported).unwrap();
+        assert_eq!(again[0].company, c.company);
+        assert_eq!(again[0].notes, c.notes);
+    }
+
+    #[test]
+    fn test_find_duplicate() {
+        let email = Some("Info@Acme.example".to_string());
+        let company = Some("ACME GmbH".to_string());
+        let none = None;
+        let existing = vec![("K-001", &email, &none), ("K-002", &none, &company)];
+
+        let by_email = NewClient {
+            email: Some("info@acme.example ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(find_duplicate(&by_email, existing.clone()), Some("K-001"));
+
+        let by_company = NewClient {
+            company: Some("acme gmbh".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(find_duplicate(&by_company, existing.clone()), Some("K-002"));
+
+        let fresh = NewClient {
+            company: Some("Other AG".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(find_duplicate(&fresh, existing), None);
+    }
+}
@@ -0,0 +1,131 @@
+// Format versions of the JSON data files
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+
+use super::write_atomic;
+
+/// Format version of newly created data files
+///
+/// Version 1 files have no version field (clients.json and projects.json are
+/// bare arrays). Older files are upgraded with `docgen migrate`.
+pub const DATA_VERSION: u32 = 2;
+
+/// Version of a parsed data file
+pub fn version_of(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .unwrap_or(1)
+}
+
+/// Read a data file, rejecting versions newer than this docgen understands
+pub fn read(path: &Path) -> Result<Option<(u32, Value)>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let value: Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let version = version_of(&value);
+    if version > DATA_VERSION {
+        anyhow::bail!(
+            "{} has format version {}, but this docgen only supports up to version {}. \
+             Please update docgen.",
+            path.display(),
+            version,
+            DATA_VERSION
+        );
+    }
+    Ok(Some((version, value)))
+}
+
+/// Items of a list file: a bare array in version 1, under `key` since version 2
+pub fn read_list<T: DeserializeOwned>(path: &Path, key: &str) -> Result<Vec<T>> {
+    let Some((_, value)) = read(path)? else {
+        return Ok(Vec::new());
+    };
+    let items = match value {
+        Value::Array(_) => value,
+        Value::Object(mut object) => object.remove(key).unwrap_or(json!([])),
+        _ => anyhow::bail!("{} is neither a list nor an object", path.display()),
+    };
+    serde_json::from_value(items).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Write a list file in the version it already has (new files: current version)
+pub fn write_list<T: Serialize>(path: &Path, key: &str, items: &[T]) -> Result<()> {
+    let version = read(path)?.map_or(DATA_VERSION, |(version, _)| version);
+    let value = if version == 1 {
+        serde_json::to_value(items)?
+    } else {
+        json!({ "version": version, key: items })
+    };
+    write_atomic(path, serde_json::to_string_pretty(&value)?)
+}
+
+/// Read an object file, dropping the version field
+pub fn read_object<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    let Some((_, mut value)) = read(path)? else {
+        return Ok(T::default());
+    };
+    if let Some(object) = value.as_object_mut() {
+        object.remove("version");
+    }
+    serde_json::from_value(value).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Write an object file with its version field first
+pub fn write_object<T: Serialize>(path: &Path, data: &T) -> Result<()> {
+    let version = read(path)?.map_or(DATA_VERSION, |(version, _)| version);
+    let mut object = Map::new();
+    if version > 1 {
+        object.insert("version".to_string(), json!(version));
+    }
+    if let Value::Object(fields) = serde_json::to_value(data)? {
+        object.extend(fields);
+    }
+    write_atomic(path, serde_json::to_string_pretty(&object)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_list_keeps_file_version() {
+        let tmp = TempDir::new().unwrap();
+        let legacy = tmp.path().join("legacy.json");
+        fs::write(&legacy, "[1, 2]").unwrap();
+        let items: Vec<i64> = read_list(&legacy, "items").unwrap();
+        write_list(&legacy, "items", &[items, vec![3]].concat()).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&fs::read_to_string(&legacy).unwrap()).unwrap(),
+            json!([1, 2, 3])
+        );
+
+        let fresh = tmp.path().join("fresh.json");
+        write_list(&fresh, "items", &[1]).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&fs::read_to_string(&fresh).unwrap()).unwrap(),
+            json!({"version": DATA_VERSION, "items": [1]})
+        );
+        assert_eq!(read_list::<i64>(&fresh, "items").unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("clients.json");
+        fs::write(&path, r#"{"version": 99, "clients": []}"#).unwrap();
+        let err = read_list::<Value>(&path, "clients").unwrap_err();
+        assert!(err.to_string().contains("Please update docgen"));
+    }
+}
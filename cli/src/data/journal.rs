@@ -0,0 +1,395 @@
+// Append-only, hash-chained journal of issued documents (GoBD)
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::{write_atomic, FileLock};
+
+/// `prev` of the first journal entry
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// One issued document
+///
+/// `hash` covers all other fields including `prev`, the hash of the previous
+/// entry, so editing or removing any line breaks the chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub seq: u64,
+    pub issued_at: String,
+    pub doc_type: String,
+    pub number: String,
+    pub source: String,
+    pub source_sha256: String,
+    pub pdf: Option<String>,
+    pub pdf_sha256: Option<String>,
+    pub prev: String,
+    #[serde(default)]
+    pub hash: String,
+}
+
+impl JournalEntry {
+    pub fn compute_hash(&self) -> String {
+        let mut unhashed = self.clone();
+        unhashed.hash = String::new();
+        let bytes = serde_json::to_vec(&unhashed).expect("journal entry serializes");
+        sha256_bytes(&bytes)
+    }
+}
+
+/// Document data recorded when issuing
+pub struct Issue {
+    pub doc_type: String,
+    pub number: String,
+    pub source: PathBuf,
+    pub pdf: Option<PathBuf>,
+}
+
+/// Sequence number and hash of the last entry, kept next to the journal
+///
+/// The chain cannot show that lines were cut off its end; the head can.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Head {
+    seq: u64,
+    hash: String,
+}
+
+/// A broken link in the journal
+#[derive(Debug, PartialEq)]
+pub struct ChainError {
+    pub line: usize,
+    pub message: String,
+}
+
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn default() -> Self {
+        Self::new("data/journal.jsonl")
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// `journal.jsonl.head`
+    fn head_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".head");
+        self.path.with_file_name(name)
+    }
+
+    /// All entries in order; unparsable lines are reported by `verify`
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        Ok(content
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .filter_map(|l| serde_json::from_str(l).ok())
+            .collect())
+    }
+
+    /// Find the entry of an already issued document number
+    pub fn find(&self, doc_type: &str, number: &str) -> Result<Option<JournalEntry>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .find(|e| e.doc_type == doc_type && e.number == number))
+    }
+
+    /// Hash the document files and append a new entry to the chain
+    ///
+    /// Fails if the number is already journaled; the check runs while the
+    /// journal is locked, so concurrent runs cannot both issue it.
+    pub fn append(&self, issue: Issue) -> Result<JournalEntry> {
+        let lock = FileLock::acquire(&self.path)?;
+
+        let errors = self.verify()?;
+        if let Some(error) = errors.first() {
+            anyhow::bail!(
+                "{} is damaged (line {}: {}). Run 'docgen audit' before issuing documents.",
+                self.path.display(),
+                error.line,
+                error.message
+            );
+        }
+
+        let mut entries = self.entries()?;
+        if let Some(entry) = entries
+            .iter()
+            .find(|e| e.doc_type == issue.doc_type && e.number == issue.number)
+        {
+            return Err(already_issued(entry));
+        }
+
+        let last = entries.pop();
+        let pdf_sha256 = issue.pdf.as_deref().map(sha256_file).transpose()?;
+        let mut entry = JournalEntry {
+            seq: last.as_ref().map_or(1, |e| e.seq + 1),
+            issued_at: chrono::Utc::now().to_rfc3339(),
+            doc_type: issue.doc_type,
+            number: issue.number,
+            source: path_string(&issue.source),
+            source_sha256: sha256_file(&issue.source)?,
+            pdf: issue.pdf.as_deref().map(path_string),
+            pdf_sha256,
+            prev: last.map_or_else(|| GENESIS.to_string(), |e| e.hash),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        lock.ensure_unchanged()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        file.sync_all()?;
+        let head = Head {
+            seq: entry.seq,
+            hash: entry.hash.clone(),
+        };
+        write_atomic(&self.head_path(), serde_json::to_string(&head)?)?;
+
+        Ok(entry)
+    }
+
+    /// Check every line: parsable, consecutive, correctly hashed and linked,
+    /// and the last entry against the recorded head
+    pub fn verify(&self) -> Result<Vec<ChainError>> {
+        let mut errors = Vec::new();
+        let head_path = self.head_path();
+        let head: Option<Head> = match fs::read_to_string(&head_path) {
+            Ok(content) => Some(
+                serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse {}", head_path.display()))?,
+            ),
+            Err(_) => None,
+        };
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(_) if !self.path.exists() => String::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.path.display()))
+            }
+        };
+
+        let mut prev = GENESIS.to_string();
+        let mut expected_seq = 1;
+        // The last two entries as (seq, hash)
+        let mut last: Option<(u64, String)> = None;
+        let mut before_last: Option<(u64, String)> = None;
+        for (index, line) in content.lines().enumerate() {
+            let line_no = index + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut error = |message: String| {
+                errors.push(ChainError {
+                    line: line_no,
+                    message,
+                })
+            };
+            let entry: JournalEntry = match serde_json::from_str(line) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    error(format!("unreadable entry ({})", e));
+                    continue;
+                }
+            };
+            if entry.seq != expected_seq {
+                error(format!(
+                    "entry {} follows {} (entries removed or reordered)",
+                    entry.seq,
+                    expected_seq - 1
+                ));
+            }
+            if entry.prev != prev {
+                error(format!(
+                    "{} does not link to the previous entry",
+                    entry.number
+                ));
+            }
+            if entry.hash != entry.compute_hash() {
+                error(format!(
+                    "{} was modified after it was recorded",
+                    entry.number
+                ));
+            }
+            before_last = last.replace((entry.seq, entry.hash.clone()));
+            prev = entry.hash;
+            expected_seq = entry.seq + 1;
+        }
+
+        // An append interrupted between the journal line and the head
+        // leaves the head one entry behind
+        let line = content.lines().count() + 1;
+        match head {
+            None if last.is_some() => errors.push(ChainError {
+                line,
+                message: format!("{} is missing", head_path.display()),
+            }),
+            Some(head) => {
+                let recorded = Some((head.seq, head.hash));
+                if recorded != last && recorded != before_last {
+                    errors.push(ChainError {
+                        line,
+                        message: format!(
+                            "the journal ends at entry {}, but entry {} was recorded \
+                             (entries removed from the end)",
+                            last.map_or(0, |l| l.0),
+                            head.seq
+                        ),
+                    });
+                }
+            }
+            None => {}
+        }
+        Ok(errors)
+    }
+}
+
+/// Error for issuing a number that is already in the journal
+pub fn already_issued(entry: &JournalEntry) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} was already issued on {}. Issued documents are immutable; \
+         issue a credit note or correction instead.",
+        entry.number,
+        entry.issued_at
+    )
+}
+
+/// Project-relative path with forward slashes
+fn path_string(path: &Path) -> String {
+    path.strip_prefix("./")
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn sha256_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// SHA-256 of a file as lowercase hex
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(sha256_bytes(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn issue(tmp: &TempDir, journal: &Journal, number: &str) -> JournalEntry {
+        let source = tmp.path().join(format!("{}.json", number));
+        fs::write(&source, format!("{{\"number\": \"{}\"}}", number)).unwrap();
+        journal
+            .append(Issue {
+                doc_type: "invoice".to_string(),
+                number: number.to_string(),
+                source,
+                pdf: None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_chain_links_entries() {
+        let tmp = TempDir::new().unwrap();
+        let journal = Journal::new(tmp.path().join("journal.jsonl"));
+
+        let first = issue(&tmp, &journal, "RE-2025-001");
+        let second = issue(&tmp, &journal, "RE-2025-002");
+        assert_eq!(first.prev, GENESIS);
+        assert_eq!(second.prev, first.hash);
+        assert_eq!(second.seq, 2);
+        assert!(journal.verify().unwrap().is_empty());
+        assert!(journal.find("invoice", "RE-2025-002").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_append_rejects_issued_number() {
+        let tmp = TempDir::new().unwrap();
+        let journal = Journal::new(tmp.path().join("journal.jsonl"));
+        issue(&tmp, &journal, "RE-2025-001");
+
+        let source = tmp.path().join("RE-2025-001.json");
+        let error = journal
+            .append(Issue {
+                doc_type: "invoice".to_string(),
+                number: "RE-2025-001".to_string(),
+                source,
+                pdf: None,
+            })
+            .unwrap_err();
+        assert!(error.to_string().contains("already issued"));
+        assert_eq!(journal.entries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let tmp = TempDir::new().unwrap();
+        let journal = Journal::new(tmp.path().join("journal.jsonl"));
+        issue(&tmp, &journal, "RE-2025-001");
+        issue(&tmp, &journal, "RE-2025-002");
+        issue(&tmp, &journal, "RE-2025-003");
+
+        // Edit a recorded document number
+        let content = fs::read_to_string(journal.path()).unwrap();
+        let edited = content.replacen("RE-2025-002", "RE-2025-009", 1);
+        fs::write(journal.path(), &edited).unwrap();
+        let errors = journal.verify().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 2);
+        assert!(errors[0].message.contains("modified"));
+
+        // Remove a line
+        let lines: Vec<&str> = content.lines().collect();
+        fs::write(journal.path(), format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        let errors = journal.verify().unwrap();
+        assert!(errors.iter().any(|e| e.message.contains("removed")));
+        assert!(errors.iter().any(|e| e.message.contains("does not link")));
+
+        // Cut entries off the end
+        fs::write(journal.path(), format!("{}\n{}\n", lines[0], lines[1])).unwrap();
+        let errors = journal.verify().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("entries removed from the end"));
+        fs::write(journal.path(), "").unwrap();
+        assert!(journal.verify().unwrap()[0]
+            .message
+            .contains("ends at entry 0, but entry 3"));
+
+        // A damaged journal refuses new entries
+        let source = tmp.path().join("x.json");
+        fs::write(&source, "{}").unwrap();
+        assert!(journal
+            .append(Issue {
+                doc_type: "invoice".to_string(),
+                number: "RE-2025-004".to_string(),
+                source,
+                pdf: None,
+            })
+            .is_err());
+    }
+}
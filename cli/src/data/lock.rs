@@ -0,0 +1,275 @@
+// Crash-safe writes and advisory locks for the JSON data files
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for another docgen process before giving up
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Write to a temporary file next to `path`, then rename it over `path`
+///
+/// A crash leaves either the old or the new content, never a truncated file.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Invalid file path {}", path.display()))?;
+    let tmp = path.with_file_name(format!(
+        ".{}.tmp-{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = (|| -> std::io::Result<()> {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result.with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Exclusive advisory lock on `<file>.lock`, released and removed on drop
+///
+/// Held around read-modify-write cycles so that two processes cannot hand
+/// out the same number. The lock only covers processes on the same machine;
+/// for folders synced between machines (Nextcloud, Dropbox, Syncthing) it
+/// refuses to work on a file with sync conflict copies, and
+/// `ensure_unchanged` refuses to overwrite a file another writer changed.
+pub struct FileLock {
+    file: File,
+    lock_path: PathBuf,
+    /// The locked data file and its content when the lock was taken
+    path: PathBuf,
+    content: Option<Vec<u8>>,
+}
+
+impl FileLock {
+    /// Lock the data file at `path`, waiting a few seconds for other processes
+    pub fn acquire(path: &Path) -> Result<Self> {
+        Self::acquire_with_timeout(path, LOCK_TIMEOUT)
+    }
+
+    pub fn acquire_with_timeout(path: &Path, timeout: Duration) -> Result<Self> {
+        let lock_path = lock_path(path);
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let start = Instant::now();
+        let mut file = loop {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&lock_path)
+                .with_context(|| format!("Failed to open lock file {}", lock_path.display()))?;
+
+            match file.try_lock() {
+                // The previous holder may have removed the file after we opened it
+                Ok(()) if is_same_file(&file, &lock_path) => break file,
+                Ok(()) => continue,
+                Err(TryLockError::WouldBlock) if start.elapsed() < timeout => {
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(TryLockError::WouldBlock) => {
+                    let mut holder = String::new();
+                    let _ = (&file).read_to_string(&mut holder);
+                    let holder = match holder.trim() {
+                        "" => String::new(),
+                        pid => format!(" (pid {})", pid),
+                    };
+                    anyhow::bail!(
+                        "{} is locked by another docgen process{} on this machine. \
+                         Wait for it to finish and try again; the lock is held on {}. \
+                         Note that the lock does not reach other machines sharing \
+                         a synced folder",
+                        path.display(),
+                        holder,
+                        lock_path.display()
+                    );
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to lock {}", lock_path.display()));
+                }
+            }
+        };
+
+        // Record the holder for the error message of waiting processes
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+
+        let conflicts = conflict_copies(path);
+        if !conflicts.is_empty() {
+            let names: Vec<String> = conflicts.iter().map(|p| p.display().to_string()).collect();
+            anyhow::bail!(
+                "{} was changed on two machines at once; the sync client kept {}. \
+                 Merge the copies into {} by hand and delete them, then try again",
+                path.display(),
+                names.join(", "),
+                path.display()
+            );
+        }
+
+        Ok(Self {
+            file,
+            lock_path,
+            path: path.to_path_buf(),
+            content: fs::read(path).ok(),
+        })
+    }
+
+    /// Fail if the data file changed since the lock was taken
+    ///
+    /// Locks don't reach other machines, but a sync client replacing the
+    /// file in between does show up here.
+    pub fn ensure_unchanged(&self) -> Result<()> {
+        if fs::read(&self.path).ok() != self.content {
+            anyhow::bail!(
+                "{} was changed by another writer (e.g. a sync client) while docgen \
+                 was updating it. Nothing was written; check the file and try again",
+                self.path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Remove while still locked, so no one waits on a deleted file
+        let _ = fs::remove_file(&self.lock_path);
+        let _ = self.file.unlock();
+    }
+}
+
+/// Whether `file` is still the one at `path`
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// Windows cannot remove a file another process has open
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+/// Copies sync clients create when a file changed on two machines:
+/// `clients (conflicted copy 2025-03-01 101500).json` (Nextcloud, Dropbox),
+/// `clients.sync-conflict-20250301-101500-ABC.json` (Syncthing)
+fn conflict_copies(path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(stem)) = (path.parent(), path.file_stem()) else {
+        return Vec::new();
+    };
+    let stem = stem.to_string_lossy();
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut copies: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            p.as_path() != path
+                && name.starts_with(stem.as_ref())
+                && name.to_lowercase().contains("conflict")
+                && p.extension() == path.extension()
+        })
+        .collect();
+    copies.sort();
+    copies
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_atomic_replaces_content() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("clients.json");
+        fs::write(&path, "[1, 2, 3]").unwrap();
+
+        write_atomic(&path, "[]").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[]");
+
+        // No temporary files left behind
+        let entries: Vec<_> = fs::read_dir(tmp.path()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("counters.json");
+
+        let held = FileLock::acquire(&path).unwrap();
+        let err = FileLock::acquire_with_timeout(&path, Duration::from_millis(100))
+            .err()
+            .unwrap();
+        let message = err.to_string();
+        assert!(message.contains("locked by another docgen process"));
+        assert!(message.contains(&format!("pid {}", std::process::id())));
+        assert!(message.contains("other machines"));
+
+        drop(held);
+        assert!(!tmp.path().join("counters.json.lock").exists());
+        assert!(FileLock::acquire_with_timeout(&path, Duration::from_millis(100)).is_ok());
+    }
+
+    #[test]
+    fn test_lock_detects_other_writers() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("counters.json");
+        fs::write(&path, r#"{"invoice": 1}"#).unwrap();
+
+        let lock = FileLock::acquire(&path).unwrap();
+        assert!(lock.ensure_unchanged().is_ok());
+        fs::write(&path, r#"{"invoice": 2}"#).unwrap();
+        let err = lock.ensure_unchanged().unwrap_err();
+        assert!(err.to_string().contains("changed by another writer"));
+        drop(lock);
+
+        let copy = tmp
+            .path()
+            .join("counters (conflicted copy 2025-03-01 101500).json");
+        fs::write(&copy, r#"{"invoice": 1}"#).unwrap();
+        let err = FileLock::acquire(&path).err().unwrap();
+        assert!(err.to_string().contains("conflicted copy"));
+        fs::remove_file(&copy).unwrap();
+
+        fs::write(
+            tmp.path()
+                .join("counters.sync-conflict-20250301-101500-ABC.json"),
+            "{}",
+        )
+        .unwrap();
+        assert!(FileLock::acquire(&path).is_err());
+    }
+}
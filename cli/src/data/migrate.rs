@@ -0,0 +1,454 @@
+// Step-by-step upgrades of project data files and layout (`docgen migrate`)
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::format::{self, DATA_VERSION};
+use super::{company, write_atomic, FileLock};
+use crate::local_templates::get_available_templates;
+
+/// Upgrade of one data file from version `from` to `from + 1`
+struct Step {
+    file: &'static str,
+    from: u32,
+    description: &'static str,
+    apply: fn(Value) -> Result<Value>,
+}
+
+const STEPS: &[Step] = &[
+    Step {
+        file: "clients.json",
+        from: 1,
+        description: "add format version",
+        apply: wrap_clients,
+    },
+    Step {
+        file: "projects.json",
+        from: 1,
+        description: "add format version",
+        apply: wrap_projects,
+    },
+    Step {
+        file: "counters.json",
+        from: 1,
+        description: "add format version and missing counters",
+        apply: complete_counters,
+    },
+    Step {
+        file: "company.json",
+        from: 1,
+        description: "add format version and missing numbering, structure and default_terms",
+        apply: complete_company,
+    },
+];
+
+fn wrap_list(value: Value, key: &str) -> Result<Value> {
+    anyhow::ensure!(value.is_array(), "expected a list");
+    Ok(json!({ key: value }))
+}
+
+fn wrap_clients(value: Value) -> Result<Value> {
+    wrap_list(value, "clients")
+}
+
+fn wrap_projects(value: Value) -> Result<Value> {
+    wrap_list(value, "projects")
+}
+
+fn complete_counters(mut value: Value) -> Result<Value> {
+    let object = value.as_object_mut().context("expected an object")?;
+    for name in super::COUNTER_TYPES {
+        object.entry(name).or_insert(json!(0));
+    }
+    Ok(value)
+}
+
+fn complete_company(mut value: Value) -> Result<Value> {
+    let defaults: Value = serde_json::from_str(company::TEMPLATE)?;
+    let object = value.as_object_mut().context("expected an object")?;
+    for block in company::BLOCKS {
+        if !object.contains_key(block) {
+            object.insert(block.to_string(), defaults[block].clone());
+        }
+    }
+    Ok(value)
+}
+
+/// Put the version field first so it is easy to spot
+fn with_version(value: Value, version: u32) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut object = Map::new();
+            object.insert("version".to_string(), json!(version));
+            object.extend(fields.into_iter().filter(|(key, _)| key != "version"));
+            Value::Object(object)
+        }
+        other => other,
+    }
+}
+
+/// A change `docgen migrate` makes to the project
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    /// Upgrade a data file to the current format version
+    Upgrade {
+        file: PathBuf,
+        from: u32,
+        steps: Vec<&'static str>,
+    },
+    /// Move a pre-0.5 copy of a standard template out of templates/
+    RetireTemplate { dir: PathBuf },
+    /// Point pre-0.5 template imports at .docgen/templates
+    RewriteImports { file: PathBuf, imports: usize },
+}
+
+impl Change {
+    pub fn touches_templates(&self) -> bool {
+        !matches!(self, Change::Upgrade { .. })
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Upgrade { file, from, steps } => write!(
+                f,
+                "{}: version {} → {} ({})",
+                file.display(),
+                from,
+                DATA_VERSION,
+                steps.join("; ")
+            ),
+            Change::RetireTemplate { dir } => write!(
+                f,
+                "{}: pre-0.5 template copy, replaced by .docgen/templates",
+                dir.display()
+            ),
+            Change::RewriteImports { file, imports } => write!(
+                f,
+                "{}: rewrite {} template import(s) to /.docgen/templates",
+                file.display(),
+                imports
+            ),
+        }
+    }
+}
+
+/// Everything that needs to change to bring the project at `root` up to date
+pub fn plan(root: &Path) -> Result<Vec<Change>> {
+    let mut changes = Vec::new();
+
+    for file in [
+        "clients.json",
+        "projects.json",
+        "counters.json",
+        "company.json",
+    ] {
+        let path = root.join("data").join(file);
+        let Some((from, _)) = format::read(&path)? else {
+            continue;
+        };
+        if from < DATA_VERSION {
+            let steps = STEPS
+                .iter()
+                .filter(|s| s.file == file && s.from >= from)
+                .map(|s| s.description)
+                .collect();
+            changes.push(Change::Upgrade {
+                file: PathBuf::from("data").join(file),
+                from,
+                steps,
+            });
+        }
+    }
+
+    // Before 0.5, standard templates were copied into templates/ and there
+    // was no .docgen/ directory
+    let mut retired = Vec::new();
+    if !root.join(".docgen/templates").exists() && root.join("templates").is_dir() {
+        let standard = get_available_templates();
+        for entry in fs::read_dir(root.join("templates"))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type()?.is_dir() && standard.contains(&name) {
+                changes.push(Change::RetireTemplate {
+                    dir: PathBuf::from("templates").join(&name),
+                });
+                retired.push(name);
+            }
+        }
+    }
+
+    for dir in ["documents", "templates"] {
+        for entry in WalkDir::new(root.join(dir))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "typ"))
+        {
+            let relative = entry.path().strip_prefix(root)?;
+            if retired
+                .iter()
+                .any(|name| relative.starts_with(Path::new("templates").join(name)))
+            {
+                continue;
+            }
+            let content = fs::read_to_string(entry.path())?;
+            let (_, imports) = rewrite_imports(&content, &retired);
+            if imports > 0 {
+                changes.push(Change::RewriteImports {
+                    file: relative.to_path_buf(),
+                    imports,
+                });
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Rewrite `@local/docgen-<name>[:version]` package imports and imports of
+/// retired template copies to the project-local standard templates
+fn rewrite_imports(content: &str, retired: &[String]) -> (String, usize) {
+    const PACKAGE: &str = "\"@local/docgen-";
+    let mut out = String::with_capacity(content.len());
+    let mut count = 0;
+    let mut rest = content;
+
+    while let Some(start) = rest.find(PACKAGE) {
+        let after = &rest[start + PACKAGE.len()..];
+        let Some(end) = after.find('"') else {
+            break;
+        };
+        let name = after[..end].split(':').next().unwrap_or_default();
+        out.push_str(&rest[..start]);
+        out.push_str(&format!("\"/.docgen/templates/{}/default.typ\"", name));
+        rest = &after[end + 1..];
+        count += 1;
+    }
+    out.push_str(rest);
+
+    for name in retired {
+        let old = format!("\"/templates/{}/", name);
+        count += out.matches(&old).count();
+        out = out.replace(&old, &format!("\"/.docgen/templates/{}/", name));
+    }
+
+    (out, count)
+}
+
+/// Apply the planned changes, backing up every touched file first
+///
+/// Returns the backup directory, if anything was changed.
+pub fn apply(root: &Path, changes: &[Change]) -> Result<Option<PathBuf>> {
+    if changes.is_empty() {
+        return Ok(None);
+    }
+
+    // Lock the data files for the whole run, before anything is changed,
+    // so no other docgen process writes them in between
+    let mut upgrades: Vec<&PathBuf> = changes
+        .iter()
+        .filter_map(|c| match c {
+            Change::Upgrade { file, .. } => Some(file),
+            _ => None,
+        })
+        .collect();
+    upgrades.sort();
+    let mut locks = BTreeMap::new();
+    for file in upgrades {
+        locks.insert(file, FileLock::acquire(&root.join(file))?);
+    }
+
+    let backup_dir = PathBuf::from(".docgen/backups").join(format!(
+        "migrate-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    let backup = |relative: &Path| -> Result<PathBuf> {
+        let target = root.join(&backup_dir).join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(target)
+    };
+
+    for change in changes {
+        match change {
+            Change::Upgrade { file, from, .. } => {
+                let path = root.join(file);
+                let content = fs::read_to_string(&path)?;
+                let mut value: Value = serde_json::from_str(&content)?;
+                for version in *from..DATA_VERSION {
+                    let name = file.file_name().unwrap_or_default().to_string_lossy();
+                    for step in STEPS.iter().filter(|s| s.file == name && s.from == version) {
+                        value = (step.apply)(value).with_context(|| {
+                            format!(
+                                "Failed to upgrade {} ({})",
+                                file.display(),
+                                step.description
+                            )
+                        })?;
+                    }
+                    value = with_version(value, version + 1);
+                }
+
+                locks[file].ensure_unchanged()?;
+                fs::write(backup(file)?, &content)?;
+                write_atomic(&path, serde_json::to_string_pretty(&value)? + "\n")?;
+            }
+            Change::RetireTemplate { dir } => {
+                fs::rename(root.join(dir), backup(dir)?)
+                    .with_context(|| format!("Failed to move {}", dir.display()))?;
+            }
+            Change::RewriteImports { file, .. } => {
+                let path = root.join(file);
+                let content = fs::read_to_string(&path)?;
+                let retired: Vec<String> = changes
+                    .iter()
+                    .filter_map(|c| match c {
+                        Change::RetireTemplate { dir } => {
+                            Some(dir.file_name()?.to_string_lossy().to_string())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let (rewritten, _) = rewrite_imports(&content, &retired);
+
+                fs::write(backup(file)?, &content)?;
+                write_atomic(&path, rewritten)?;
+            }
+        }
+    }
+
+    Ok(Some(backup_dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{ClientStore, CounterStore};
+    use tempfile::TempDir;
+
+    fn legacy_project() -> TempDir {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(
+            root.join("data/clients.json"),
+            r#"[{"id": 1, "number": 7, "name": "Acme", "company": null, "street": null,
+                "house_number": null, "postal_code": null, "city": null, "country": null,
+                "email": null, "phone": null, "notes": null, "created_at": "2024-01-01"}]"#,
+        )
+        .unwrap();
+        fs::write(root.join("data/projects.json"), "[]").unwrap();
+        fs::write(
+            root.join("data/counters.json"),
+            r#"{"client": 7, "invoice": 3}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("data/company.json"),
+            r#"{"name": "Old GmbH", "numbering": {"year_format": "long"}}"#,
+        )
+        .unwrap();
+
+        fs::create_dir_all(root.join("templates/invoice")).unwrap();
+        fs::write(root.join("templates/invoice/default.typ"), "// old copy").unwrap();
+        fs::create_dir_all(root.join("templates/my-concept")).unwrap();
+        fs::create_dir_all(root.join("documents/concepts")).unwrap();
+        fs::write(
+            root.join("documents/concepts/ko.typ"),
+            "#import \"@local/docgen-concept:0.4.12\": concept\n\
+             #import \"/templates/invoice/default.typ\": invoice\n",
+        )
+        .unwrap();
+        tmp
+    }
+
+    #[test]
+    fn test_plan_legacy_project() {
+        let tmp = legacy_project();
+        let changes = plan(tmp.path()).unwrap();
+
+        assert_eq!(changes.len(), 6);
+        assert!(changes.contains(&Change::RetireTemplate {
+            dir: PathBuf::from("templates/invoice")
+        }));
+        assert!(changes.contains(&Change::RewriteImports {
+            file: PathBuf::from("documents/concepts/ko.typ"),
+            imports: 2
+        }));
+        // Planning changes nothing
+        assert!(tmp.path().join("templates/invoice").exists());
+    }
+
+    #[test]
+    fn test_apply_upgrades_with_backups() {
+        let tmp = legacy_project();
+        let root = tmp.path();
+        let changes = plan(root).unwrap();
+        let backup_dir = root.join(apply(root, &changes).unwrap().unwrap());
+
+        let clients: Value =
+            serde_json::from_str(&fs::read_to_string(root.join("data/clients.json")).unwrap())
+                .unwrap();
+        assert_eq!(clients["version"], DATA_VERSION);
+        assert_eq!(clients["clients"][0]["number"], 7);
+
+        let company: Value =
+            serde_json::from_str(&fs::read_to_string(root.join("data/company.json")).unwrap())
+                .unwrap();
+        assert_eq!(company["numbering"]["year_format"], "long");
+        assert_eq!(company["structure"]["organize_by_year"], true);
+        assert!(company["default_terms"].is_object());
+        assert!(fs::read_to_string(root.join("data/company.json"))
+            .unwrap()
+            .starts_with("{\n  \"version\": 2,"));
+
+        // Stores read the upgraded files and keep the new format
+        let store = ClientStore::new(root.join("data/clients.json"));
+        let mut counter = CounterStore::new(root.join("data/counters.json"));
+        assert_eq!(counter.next("invoice").unwrap(), 4);
+        assert_eq!(counter.get("documentation").unwrap(), 0);
+        assert_eq!(store.list().unwrap()[0].name, "Acme");
+
+        let typ = fs::read_to_string(root.join("documents/concepts/ko.typ")).unwrap();
+        assert!(typ.contains("\"/.docgen/templates/concept/default.typ\": concept"));
+        assert!(typ.contains("\"/.docgen/templates/invoice/default.typ\": invoice"));
+
+        assert!(!root.join("templates/invoice").exists());
+        assert!(root.join("templates/my-concept").exists());
+        assert!(backup_dir.join("templates/invoice/default.typ").exists());
+        assert_eq!(
+            fs::read_to_string(backup_dir.join("data/clients.json")).unwrap()[..1],
+            *"["
+        );
+
+        // Nothing left to do
+        fs::create_dir_all(root.join(".docgen/templates")).unwrap();
+        assert!(plan(root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_apply_holds_store_locks() {
+        let tmp = legacy_project();
+        let root = tmp.path();
+        let changes = plan(root).unwrap();
+
+        // Locks are taken before anything changes
+        let copy = root.join("data/counters (conflicted copy 2025-03-01 101500).json");
+        fs::write(&copy, "{}").unwrap();
+        assert!(apply(root, &changes).is_err());
+        assert!(root.join("templates/invoice").exists());
+        assert!(fs::read_to_string(root.join("data/clients.json"))
+            .unwrap()
+            .starts_with('['));
+
+        fs::remove_file(&copy).unwrap();
+        apply(root, &changes).unwrap();
+        assert!(!root.join("data/clients.json.lock").exists());
+    }
+}
@@ -1,10 +1,22 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+pub mod company;
+pub mod documents;
+pub mod exchange;
+pub mod format;
+pub mod journal;
+pub mod lock;
+pub mod migrate;
 pub mod models;
+pub mod sqlite;
+pub mod storage;
+pub use format::DATA_VERSION;
+pub use lock::{write_atomic, FileLock};
 pub use models::*;
+pub use storage::{open, Backend, Storage};
 
 /// Simple JSON-based storage for clients
 pub struct ClientStore {
@@ -18,10 +30,6 @@ impl ClientStore {
         }
     }
 
-    pub fn default() -> Self {
-        Self::new("data/clients.json")
-    }
-
     /// Ensure data directory exists
     fn ensure_dir(&self) -> Result<()> {
         if let Some(parent) = self.path.parent() {
@@ -34,24 +42,14 @@ impl ClientStore {
     pub fn init(&self) -> Result<()> {
         self.ensure_dir()?;
         if !self.path.exists() {
-            fs::write(&self.path, "[]")?;
+            format::write_list::<Client>(&self.path, "clients", &[])?;
         }
         Ok(())
     }
 
     /// List all clients
     pub fn list(&self) -> Result<Vec<Client>> {
-        if !self.path.exists() {
-            return Ok(Vec::new());
-        }
-
-        let content = fs::read_to_string(&self.path)
-            .with_context(|| format!("Failed to read {}", self.path.display()))?;
-
-        let clients: Vec<Client> = serde_json::from_str(&content)
-            .with_context(|| format!("Failed to parse {}", self.path.display()))?;
-
-        Ok(clients)
+        format::read_list(&self.path, "clients")
     }
 
     /// Get client by ID
@@ -70,6 +68,7 @@ impl ClientStore {
     /// Add new client (assigns ID and number automatically)
     pub fn add(&self, new_client: NewClient, counter: &mut CounterStore) -> Result<Client> {
         self.init()?;
+        let lock = FileLock::acquire(&self.path)?;
 
         let mut clients = self.list()?;
         let next_id = clients.iter().map(|c| c.id).max().unwrap_or(0) + 1;
@@ -92,6 +91,7 @@ impl ClientStore {
         };
 
         clients.push(client.clone());
+        lock.ensure_unchanged()?;
         self.save(&clients)?;
 
         Ok(client)
@@ -99,6 +99,7 @@ impl ClientStore {
 
     /// Delete a client by ID
     pub fn delete(&self, id: i64) -> Result<()> {
+        let lock = FileLock::acquire(&self.path)?;
         let mut clients = self.list()?;
         let initial_len = clients.len();
         clients.retain(|c| c.id != id);
@@ -107,16 +108,14 @@ impl ClientStore {
             anyhow::bail!("Client with ID {} not found", id);
         }
 
+        lock.ensure_unchanged()?;
         self.save(&clients)?;
         Ok(())
     }
 
     /// Save all clients
     fn save(&self, clients: &[Client]) -> Result<()> {
-        let json = serde_json::to_string_pretty(clients)?;
-        fs::write(&self.path, json)
-            .with_context(|| format!("Failed to write {}", self.path.display()))?;
-        Ok(())
+        format::write_list(&self.path, "clients", clients)
     }
 }
 
@@ -132,10 +131,6 @@ impl ProjectStore {
         }
     }
 
-    pub fn default() -> Self {
-        Self::new("data/projects.json")
-    }
-
     fn ensure_dir(&self) -> Result<()> {
         if let Some(parent) = self.path.parent() {
             fs::create_dir_all(parent)?;
@@ -146,23 +141,13 @@ impl ProjectStore {
     pub fn init(&self) -> Result<()> {
         self.ensure_dir()?;
         if !self.path.exists() {
-            fs::write(&self.path, "[]")?;
+            format::write_list::<Project>(&self.path, "projects", &[])?;
         }
         Ok(())
     }
 
     pub fn list(&self) -> Result<Vec<Project>> {
-        if !self.path.exists() {
-            return Ok(Vec::new());
-        }
-
-        let content = fs::read_to_string(&self.path)
-            .with_context(|| format!("Failed to read {}", self.path.display()))?;
-
-        let projects: Vec<Project> = serde_json::from_str(&content)
-            .with_context(|| format!("Failed to parse {}", self.path.display()))?;
-
-        Ok(projects)
+        format::read_list(&self.path, "projects")
     }
 
     pub fn list_by_client(&self, client_id: i64) -> Result<Vec<Project>> {
@@ -181,6 +166,7 @@ impl ProjectStore {
 
     pub fn add(&self, new_project: NewProject, _counter: &mut CounterStore) -> Result<Project> {
         self.init()?;
+        let lock = FileLock::acquire(&self.path)?;
 
         let mut projects = self.list()?;
         let next_id = projects.iter().map(|p| p.id).max().unwrap_or(0) + 1;
@@ -206,6 +192,7 @@ impl ProjectStore {
         };
 
         projects.push(project.clone());
+        lock.ensure_unchanged()?;
         self.save(&projects)?;
 
         Ok(project)
@@ -213,6 +200,7 @@ impl ProjectStore {
 
     /// Delete a project by ID
     pub fn delete(&self, id: i64) -> Result<()> {
+        let lock = FileLock::acquire(&self.path)?;
         let mut projects = self.list()?;
         let initial_len = projects.len();
         projects.retain(|p| p.id != id);
@@ -221,20 +209,18 @@ impl ProjectStore {
             anyhow::bail!("Project with ID {} not found", id);
         }
 
+        lock.ensure_unchanged()?;
         self.save(&projects)?;
         Ok(())
     }
 
     fn save(&self, projects: &[Project]) -> Result<()> {
-        let json = serde_json::to_string_pretty(projects)?;
-        fs::write(&self.path, json)
-            .with_context(|| format!("Failed to write {}", self.path.display()))?;
-        Ok(())
+        format::write_list(&self.path, "projects", projects)
     }
 }
 
 /// Counter store for auto-incrementing document numbers
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Counters {
     #[serde(default)]
     pub client: i64,
@@ -250,16 +236,40 @@ pub struct Counters {
     pub documentation: i64,
 }
 
-impl Default for Counters {
-    fn default() -> Self {
-        Self {
-            client: 0,
-            invoice: 0,
-            offer: 0,
-            credentials: 0,
-            concept: 0,
-            documentation: 0,
-        }
+/// Names of all document counters
+pub const COUNTER_TYPES: [&str; 6] = [
+    "client",
+    "invoice",
+    "offer",
+    "credentials",
+    "concept",
+    "documentation",
+];
+
+impl Counters {
+    /// Value of a counter by name
+    pub fn get(&self, counter_type: &str) -> Option<i64> {
+        Some(match counter_type {
+            "client" => self.client,
+            "invoice" => self.invoice,
+            "offer" => self.offer,
+            "credentials" => self.credentials,
+            "concept" => self.concept,
+            "documentation" => self.documentation,
+            _ => return None,
+        })
+    }
+
+    pub fn get_mut(&mut self, counter_type: &str) -> Option<&mut i64> {
+        Some(match counter_type {
+            "client" => &mut self.client,
+            "invoice" => &mut self.invoice,
+            "offer" => &mut self.offer,
+            "credentials" => &mut self.credentials,
+            "concept" => &mut self.concept,
+            "documentation" => &mut self.documentation,
+            _ => return None,
+        })
     }
 }
 
@@ -274,10 +284,6 @@ impl CounterStore {
         }
     }
 
-    pub fn default() -> Self {
-        Self::new("data/counters.json")
-    }
-
     fn ensure_dir(&self) -> Result<()> {
         if let Some(parent) = self.path.parent() {
             fs::create_dir_all(parent)?;
@@ -295,60 +301,27 @@ impl CounterStore {
     }
 
     fn load(&self) -> Result<Counters> {
-        if !self.path.exists() {
-            return Ok(Counters::default());
-        }
-
-        let content = fs::read_to_string(&self.path)
-            .with_context(|| format!("Failed to read {}", self.path.display()))?;
-
-        let counters: Counters = serde_json::from_str(&content)
-            .with_context(|| format!("Failed to parse {}", self.path.display()))?;
-
-        Ok(counters)
+        format::read_object(&self.path)
     }
 
     fn save(&self, counters: &Counters) -> Result<()> {
-        let json = serde_json::to_string_pretty(counters)?;
-        fs::write(&self.path, json)
-            .with_context(|| format!("Failed to write {}", self.path.display()))?;
-        Ok(())
+        format::write_object(&self.path, counters)
     }
 
     /// Get next number for a counter type
     pub fn next(&mut self, counter_type: &str) -> Result<i64> {
         self.init()?;
+        let lock = FileLock::acquire(&self.path)?;
 
         let mut counters = self.load()?;
 
-        let next = match counter_type {
-            "client" => {
-                counters.client += 1;
-                counters.client
-            }
-            "invoice" => {
-                counters.invoice += 1;
-                counters.invoice
-            }
-            "offer" => {
-                counters.offer += 1;
-                counters.offer
-            }
-            "credentials" => {
-                counters.credentials += 1;
-                counters.credentials
-            }
-            "concept" => {
-                counters.concept += 1;
-                counters.concept
-            }
-            "documentation" => {
-                counters.documentation += 1;
-                counters.documentation
-            }
-            _ => anyhow::bail!("Unknown counter type: {}", counter_type),
-        };
+        let counter = counters
+            .get_mut(counter_type)
+            .ok_or_else(|| anyhow::anyhow!("Unknown counter type: {}", counter_type))?;
+        *counter += 1;
+        let next = *counter;
 
+        lock.ensure_unchanged()?;
         self.save(&counters)?;
         Ok(next)
     }
@@ -356,24 +329,15 @@ impl CounterStore {
     /// Get current value without incrementing
     #[allow(dead_code)]
     pub fn get(&self, counter_type: &str) -> Result<i64> {
-        let counters = self.load()?;
-
-        Ok(match counter_type {
-            "client" => counters.client,
-            "invoice" => counters.invoice,
-            "offer" => counters.offer,
-            "credentials" => counters.credentials,
-            "concept" => counters.concept,
-            "documentation" => counters.documentation,
-            _ => anyhow::bail!("Unknown counter type: {}", counter_type),
-        })
+        self.load()?
+            .get(counter_type)
+            .ok_or_else(|| anyhow::anyhow!("Unknown counter type: {}", counter_type))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
     use tempfile::TempDir;
 
     #[test]
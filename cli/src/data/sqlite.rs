@@ -0,0 +1,405 @@
+// SQLite storage backend (data/docgen.db)
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row, TransactionBehavior};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::storage::{Backend, Snapshot, Storage};
+use super::{Client, Counters, NewClient, NewProject, Project, COUNTER_TYPES, DATA_VERSION};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS clients (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    number INTEGER UNIQUE NOT NULL,
+    name TEXT NOT NULL,
+    company TEXT,
+    street TEXT,
+    house_number TEXT,
+    postal_code TEXT,
+    city TEXT,
+    country TEXT,
+    email TEXT,
+    phone TEXT,
+    notes TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS projects (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    number INTEGER NOT NULL,
+    client_id INTEGER NOT NULL REFERENCES clients(id),
+    name TEXT NOT NULL,
+    description TEXT,
+    hourly_rate REAL,
+    status TEXT NOT NULL DEFAULT 'active',
+    created_at TEXT NOT NULL,
+    UNIQUE(client_id, number)
+);
+
+CREATE TABLE IF NOT EXISTS counters (
+    name TEXT PRIMARY KEY,
+    value INTEGER NOT NULL DEFAULT 0
+);
+";
+
+const CLIENT_COLUMNS: &str = "id, number, name, company, street, house_number, postal_code, \
+                              city, country, email, phone, notes, created_at";
+
+const PROJECT_COLUMNS: &str =
+    "id, number, client_id, name, description, hourly_rate, status, created_at";
+
+/// Clients, projects and counters in a single SQLite database
+pub struct SqliteStorage {
+    path: PathBuf,
+    conn: Connection,
+}
+
+impl SqliteStorage {
+    /// Open (and create if needed) the database at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        // Wait for concurrent writers instead of failing with "database is locked"
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+
+        let version: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > DATA_VERSION {
+            anyhow::bail!(
+                "{} has format version {}, but this docgen only supports up to version {}. \
+                 Please update docgen.",
+                path.display(),
+                version,
+                DATA_VERSION
+            );
+        }
+        conn.execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to create tables in {}", path.display()))?;
+        // The schema was introduced with data format version 2
+        conn.pragma_update(None, "user_version", DATA_VERSION)?;
+        Ok(Self { path, conn })
+    }
+}
+
+fn client_from_row(row: &Row) -> rusqlite::Result<Client> {
+    Ok(Client {
+        id: row.get(0)?,
+        number: row.get(1)?,
+        name: row.get(2)?,
+        company: row.get(3)?,
+        street: row.get(4)?,
+        house_number: row.get(5)?,
+        postal_code: row.get(6)?,
+        city: row.get(7)?,
+        country: row.get(8)?,
+        email: row.get(9)?,
+        phone: row.get(10)?,
+        notes: row.get(11)?,
+        created_at: row.get(12)?,
+    })
+}
+
+fn project_from_row(row: &Row) -> rusqlite::Result<Project> {
+    Ok(Project {
+        id: row.get(0)?,
+        number: row.get(1)?,
+        client_id: row.get(2)?,
+        name: row.get(3)?,
+        description: row.get(4)?,
+        hourly_rate: row.get(5)?,
+        status: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+fn insert_client(conn: &Connection, c: &Client) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO clients ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            CLIENT_COLUMNS
+        ),
+        params![
+            c.id,
+            c.number,
+            c.name,
+            c.company,
+            c.street,
+            c.house_number,
+            c.postal_code,
+            c.city,
+            c.country,
+            c.email,
+            c.phone,
+            c.notes,
+            c.created_at
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_project(conn: &Connection, p: &Project) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO projects ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            PROJECT_COLUMNS
+        ),
+        params![
+            p.id,
+            p.number,
+            p.client_id,
+            p.name,
+            p.description,
+            p.hourly_rate,
+            p.status,
+            p.created_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// Increment a counter inside the caller's transaction
+fn bump_counter(conn: &Connection, counter_type: &str) -> Result<i64> {
+    if !COUNTER_TYPES.contains(&counter_type) {
+        anyhow::bail!("Unknown counter type: {}", counter_type);
+    }
+    Ok(conn.query_row(
+        "INSERT INTO counters (name, value) VALUES (?1, 1)
+         ON CONFLICT(name) DO UPDATE SET value = value + 1
+         RETURNING value",
+        [counter_type],
+        |row| row.get(0),
+    )?)
+}
+
+impl Storage for SqliteStorage {
+    fn backend(&self) -> Backend {
+        Backend::Sqlite
+    }
+
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn list_clients(&self) -> Result<Vec<Client>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM clients ORDER BY id",
+            CLIENT_COLUMNS
+        ))?;
+        let clients = stmt
+            .query_map([], client_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(clients)
+    }
+
+    fn get_client(&self, id: i64) -> Result<Option<Client>> {
+        Ok(self
+            .conn
+            .query_row(
+                &format!("SELECT {} FROM clients WHERE id = ?1", CLIENT_COLUMNS),
+                [id],
+                client_from_row,
+            )
+            .optional()?)
+    }
+
+    fn add_client(&mut self, new_client: NewClient) -> Result<Client> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let number = bump_counter(&tx, "client")?;
+        let created_at = chrono::Utc::now().to_rfc3339();
+        tx.execute(
+            "INSERT INTO clients (number, name, company, street, house_number, postal_code,
+                                  city, country, email, phone, notes, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                number,
+                new_client.name,
+                new_client.company,
+                new_client.street,
+                new_client.house_number,
+                new_client.postal_code,
+                new_client.city,
+                new_client.country,
+                new_client.email,
+                new_client.phone,
+                new_client.notes,
+                created_at
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        tx.commit()?;
+
+        self.get_client(id)?
+            .context("Client disappeared after insert")
+    }
+
+    fn delete_client(&mut self, id: i64) -> Result<()> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM clients WHERE id = ?1", [id])?;
+        if deleted == 0 {
+            anyhow::bail!("Client with ID {} not found", id);
+        }
+        Ok(())
+    }
+
+    fn list_projects(&self) -> Result<Vec<Project>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM projects ORDER BY id",
+            PROJECT_COLUMNS
+        ))?;
+        let projects = stmt
+            .query_map([], project_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(projects)
+    }
+
+    fn list_projects_by_client(&self, client_id: i64) -> Result<Vec<Project>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM projects WHERE client_id = ?1 ORDER BY number",
+            PROJECT_COLUMNS
+        ))?;
+        let projects = stmt
+            .query_map([client_id], project_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(projects)
+    }
+
+    fn add_project(&mut self, new_project: NewProject) -> Result<Project> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        // Project number is per-client
+        let number: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(number), 0) + 1 FROM projects WHERE client_id = ?1",
+            [new_project.client_id],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "INSERT INTO projects (number, client_id, name, description, hourly_rate, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                number,
+                new_project.client_id,
+                new_project.name,
+                new_project.description,
+                new_project.hourly_rate,
+                new_project.status,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        let project = tx.query_row(
+            &format!("SELECT {} FROM projects WHERE id = ?1", PROJECT_COLUMNS),
+            [id],
+            project_from_row,
+        )?;
+        tx.commit()?;
+        Ok(project)
+    }
+
+    fn delete_project(&mut self, id: i64) -> Result<()> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM projects WHERE id = ?1", [id])?;
+        if deleted == 0 {
+            anyhow::bail!("Project with ID {} not found", id);
+        }
+        Ok(())
+    }
+
+    fn counters(&self) -> Result<Counters> {
+        let mut counters = Counters::default();
+        let mut stmt = self.conn.prepare("SELECT name, value FROM counters")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let name: String = row.get(0)?;
+            if let Some(value) = counters.get_mut(&name) {
+                *value = row.get(1)?;
+            }
+        }
+        Ok(counters)
+    }
+
+    fn next_number(&mut self, counter_type: &str) -> Result<i64> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let next = bump_counter(&tx, counter_type)?;
+        tx.commit()?;
+        Ok(next)
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<()> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute_batch("DELETE FROM projects; DELETE FROM clients; DELETE FROM counters;")?;
+        for client in &snapshot.clients {
+            insert_client(&tx, client).with_context(|| {
+                format!("Failed to insert client {}", client.formatted_number())
+            })?;
+        }
+        for project in &snapshot.projects {
+            insert_project(&tx, project)
+                .with_context(|| format!("Failed to insert project '{}'", project.name))?;
+        }
+        for name in COUNTER_TYPES {
+            tx.execute(
+                "INSERT INTO counters (name, value) VALUES (?1, ?2)",
+                params![name, snapshot.counters.get(name).unwrap_or(0)],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sqlite_numbering() {
+        let tmp = TempDir::new().unwrap();
+        let mut db = SqliteStorage::open(tmp.path().join("docgen.db")).unwrap();
+
+        let a = db
+            .add_client(NewClient {
+                name: "A".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        let b = db
+            .add_client(NewClient {
+                name: "B".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!((a.number, b.number), (1, 2));
+
+        let p1 = db.add_project(NewProject::new(a.id, "One".into())).unwrap();
+        let p2 = db.add_project(NewProject::new(a.id, "Two".into())).unwrap();
+        let p3 = db.add_project(NewProject::new(b.id, "One".into())).unwrap();
+        assert_eq!((p1.number, p2.number, p3.number), (1, 2, 1));
+
+        // Clients with projects cannot be removed
+        assert!(db.delete_client(a.id).is_err());
+
+        assert_eq!(db.next_number("invoice").unwrap(), 1);
+        assert_eq!(db.next_number("invoice").unwrap(), 2);
+        assert!(db.next_number("receipt").is_err());
+
+        // Data survives reopening
+        drop(db);
+        let db = SqliteStorage::open(tmp.path().join("docgen.db")).unwrap();
+        assert_eq!(db.counters().unwrap().invoice, 2);
+        assert_eq!(db.list_projects_by_client(a.id).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,315 @@
+// Storage backends for clients, projects and document counters
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::sqlite::SqliteStorage;
+use super::{write_atomic, FileLock, ProjectStore, COUNTER_TYPES};
+use super::{Client, ClientStore, CounterStore, Counters, NewClient, NewProject, Project};
+
+/// Where clients, projects and counters are kept
+///
+/// Selected with `"storage": { "backend": "json" | "sqlite" }` in data/company.json.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// clients.json, projects.json and counters.json (default)
+    Json,
+    /// data/docgen.db
+    Sqlite,
+}
+
+impl Backend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Json => "json",
+            Backend::Sqlite => "sqlite",
+        }
+    }
+
+    /// Read the configured backend from company.json in `data_dir`
+    pub fn configured(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join("company.json");
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Ok(Backend::Json);
+        };
+        let company: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        match company.pointer("/storage/backend").and_then(|b| b.as_str()) {
+            None | Some("json") => Ok(Backend::Json),
+            Some("sqlite") => Ok(Backend::Sqlite),
+            Some(other) => anyhow::bail!(
+                "Unknown storage backend '{}' in {} (expected \"json\" or \"sqlite\")",
+                other,
+                path.display()
+            ),
+        }
+    }
+
+    /// Persist this backend as `storage.backend` in company.json
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        let path = data_dir.join("company.json");
+        let mut company: serde_json::Value = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?,
+            Err(_) => serde_json::json!({}),
+        };
+        let object = company
+            .as_object_mut()
+            .with_context(|| format!("{} is not a JSON object", path.display()))?;
+        let storage = object
+            .entry("storage")
+            .or_insert_with(|| serde_json::json!({}));
+        storage["backend"] = serde_json::json!(self.as_str());
+
+        write_atomic(&path, serde_json::to_string_pretty(&company)? + "\n")
+    }
+}
+
+/// Complete content of a storage backend, used to convert between backends
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub clients: Vec<Client>,
+    pub projects: Vec<Project>,
+    pub counters: Counters,
+}
+
+/// Storage for clients, projects and document counters
+///
+/// Client and project numbers are assigned by the backend so that numbering
+/// and insert happen together.
+pub trait Storage {
+    fn backend(&self) -> Backend;
+
+    /// Human readable location of the data
+    fn location(&self) -> String;
+
+    fn list_clients(&self) -> Result<Vec<Client>>;
+
+    fn get_client(&self, id: i64) -> Result<Option<Client>> {
+        Ok(self.list_clients()?.into_iter().find(|c| c.id == id))
+    }
+
+    /// Add a new client with the next K-number
+    fn add_client(&mut self, new_client: NewClient) -> Result<Client>;
+
+    fn delete_client(&mut self, id: i64) -> Result<()>;
+
+    fn list_projects(&self) -> Result<Vec<Project>>;
+
+    fn list_projects_by_client(&self, client_id: i64) -> Result<Vec<Project>> {
+        Ok(self
+            .list_projects()?
+            .into_iter()
+            .filter(|p| p.client_id == client_id)
+            .collect())
+    }
+
+    /// Add a new project with the next per-client number
+    fn add_project(&mut self, new_project: NewProject) -> Result<Project>;
+
+    fn delete_project(&mut self, id: i64) -> Result<()>;
+
+    fn counters(&self) -> Result<Counters>;
+
+    /// Increment and return a document counter ("invoice", "offer", ...)
+    #[allow(dead_code)]
+    fn next_number(&mut self, counter_type: &str) -> Result<i64>;
+
+    /// Whether the backend holds any clients, projects or counters
+    fn is_empty(&self) -> Result<bool> {
+        let counters = self.counters()?;
+        Ok(self.list_clients()?.is_empty()
+            && self.list_projects()?.is_empty()
+            && COUNTER_TYPES.iter().all(|t| counters.get(t) == Some(0)))
+    }
+
+    fn snapshot(&self) -> Result<Snapshot> {
+        Ok(Snapshot {
+            clients: self.list_clients()?,
+            projects: self.list_projects()?,
+            counters: self.counters()?,
+        })
+    }
+
+    /// Replace all data with the snapshot, keeping IDs and numbers
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<()>;
+}
+
+/// Open the backend configured for the project in the current directory
+pub fn open() -> Result<Box<dyn Storage>> {
+    open_in(Path::new("data"))
+}
+
+/// Open the backend configured in `data_dir`/company.json
+pub fn open_in(data_dir: &Path) -> Result<Box<dyn Storage>> {
+    open_backend(data_dir, Backend::configured(data_dir)?)
+}
+
+/// Open a specific backend regardless of configuration
+pub fn open_backend(data_dir: &Path, backend: Backend) -> Result<Box<dyn Storage>> {
+    Ok(match backend {
+        Backend::Json => Box::new(JsonStorage::new(data_dir)),
+        Backend::Sqlite => Box::new(SqliteStorage::open(data_dir.join("docgen.db"))?),
+    })
+}
+
+/// The flat JSON files in the data directory
+pub struct JsonStorage {
+    dir: PathBuf,
+    clients: ClientStore,
+    projects: ProjectStore,
+    counters: CounterStore,
+}
+
+impl JsonStorage {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        Self {
+            clients: ClientStore::new(dir.join("clients.json")),
+            projects: ProjectStore::new(dir.join("projects.json")),
+            counters: CounterStore::new(dir.join("counters.json")),
+            dir,
+        }
+    }
+}
+
+impl Storage for JsonStorage {
+    fn backend(&self) -> Backend {
+        Backend::Json
+    }
+
+    fn location(&self) -> String {
+        format!("{}/*.json", self.dir.display())
+    }
+
+    fn list_clients(&self) -> Result<Vec<Client>> {
+        self.clients.list()
+    }
+
+    fn get_client(&self, id: i64) -> Result<Option<Client>> {
+        self.clients.get(id)
+    }
+
+    fn add_client(&mut self, new_client: NewClient) -> Result<Client> {
+        self.clients.add(new_client, &mut self.counters)
+    }
+
+    fn delete_client(&mut self, id: i64) -> Result<()> {
+        self.clients.delete(id)
+    }
+
+    fn list_projects(&self) -> Result<Vec<Project>> {
+        self.projects.list()
+    }
+
+    fn list_projects_by_client(&self, client_id: i64) -> Result<Vec<Project>> {
+        self.projects.list_by_client(client_id)
+    }
+
+    fn add_project(&mut self, new_project: NewProject) -> Result<Project> {
+        self.projects.add(new_project, &mut self.counters)
+    }
+
+    fn delete_project(&mut self, id: i64) -> Result<()> {
+        self.projects.delete(id)
+    }
+
+    fn counters(&self) -> Result<Counters> {
+        self.counters.load()
+    }
+
+    fn next_number(&mut self, counter_type: &str) -> Result<i64> {
+        self.counters.next(counter_type)
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<()> {
+        let _locks = [
+            FileLock::acquire(&self.clients.path)?,
+            FileLock::acquire(&self.projects.path)?,
+            FileLock::acquire(&self.counters.path)?,
+        ];
+        self.clients.init()?;
+        self.clients.save(&snapshot.clients)?;
+        self.projects.init()?;
+        self.projects.save(&snapshot.projects)?;
+        self.counters.init()?;
+        self.counters.save(&snapshot.counters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sample(storage: &mut dyn Storage) {
+        let client = storage
+            .add_client(NewClient {
+                name: "Acme".to_string(),
+                email: Some("info@acme.example".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        storage
+            .add_project(NewProject::new(client.id, "Website".to_string()))
+            .unwrap();
+        storage.next_number("invoice").unwrap();
+        storage.next_number("invoice").unwrap();
+    }
+
+    #[test]
+    fn test_convert_json_to_sqlite_and_back() {
+        let tmp = TempDir::new().unwrap();
+        let mut json = open_backend(tmp.path(), Backend::Json).unwrap();
+        sample(json.as_mut());
+
+        let mut sqlite = open_backend(tmp.path(), Backend::Sqlite).unwrap();
+        assert!(sqlite.is_empty().unwrap());
+        sqlite.restore(&json.snapshot().unwrap()).unwrap();
+
+        assert_eq!(sqlite.list_clients().unwrap()[0].name, "Acme");
+        assert_eq!(sqlite.list_projects_by_client(1).unwrap().len(), 1);
+        assert_eq!(sqlite.next_number("invoice").unwrap(), 3);
+        let second = sqlite
+            .add_client(NewClient {
+                name: "Beta".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!((second.id, second.number), (2, 2));
+
+        let back = TempDir::new().unwrap();
+        let mut json = open_backend(back.path(), Backend::Json).unwrap();
+        json.restore(&sqlite.snapshot().unwrap()).unwrap();
+        assert_eq!(json.list_clients().unwrap().len(), 2);
+        assert_eq!(json.counters().unwrap().invoice, 3);
+        assert_eq!(json.next_number("client").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_configured_backend() {
+        let tmp = TempDir::new().unwrap();
+        assert_eq!(Backend::configured(tmp.path()).unwrap(), Backend::Json);
+
+        fs::write(
+            tmp.path().join("company.json"),
+            r#"{"name": "Test", "storage": {"backend": "json"}}"#,
+        )
+        .unwrap();
+        Backend::Sqlite.save(tmp.path()).unwrap();
+        assert_eq!(Backend::configured(tmp.path()).unwrap(), Backend::Sqlite);
+
+        // Other settings and their order are kept
+        let content = fs::read_to_string(tmp.path().join("company.json")).unwrap();
+        assert!(content.find("\"name\"").unwrap() < content.find("\"storage\"").unwrap());
+
+        fs::write(
+            tmp.path().join("company.json"),
+            r#"{"storage": {"backend": "mysql"}}"#,
+        )
+        .unwrap();
+        assert!(Backend::configured(tmp.path()).is_err());
+    }
+}
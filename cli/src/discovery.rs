@@ -0,0 +1,271 @@
+// Which files below a directory are documents
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::local_templates;
+
+/// Project-wide ignore file in gitignore syntax
+pub const IGNORE_FILE: &str = ".docgenignore";
+
+/// Key of the document marker in JSON files: `"$docgen": {"type": "invoice"}`
+const JSON_MARKER: &str = "$docgen";
+
+/// Prefix of the marker line in .typ files: `// docgen: type=concept`
+const TYP_MARKER: &str = "// docgen:";
+
+/// Filters applied when looking for documents to build or watch
+///
+/// `.docgen/`, the template directories and the output directory are never
+/// searched. A .json or .typ file is a document unless `.docgenignore` or
+/// `--exclude` matches it, or `--include` is given and does not. Files with a marker are
+/// always documents; without one, JSON files need a `metadata` object and
+/// .typ files a top-level `#show:` rule, so partials and helper data are
+/// skipped.
+pub struct Discovery {
+    ignore: Gitignore,
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    /// Absolute directories skipped with everything below them
+    pruned: Vec<PathBuf>,
+}
+
+impl Discovery {
+    /// Load `.docgenignore` from the project root (the current directory)
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let root = std::env::current_dir()?;
+        let mut builder = GitignoreBuilder::new(&root);
+        let ignore_file = root.join(IGNORE_FILE);
+        if ignore_file.exists() {
+            if let Some(e) = builder.add(&ignore_file) {
+                return Err(e).with_context(|| format!("Invalid {}", IGNORE_FILE));
+            }
+        }
+        let mut pruned = vec![
+            root.join(".docgen"),
+            root.join(local_templates::get_custom_templates_dir()),
+        ];
+        pruned.extend(local_templates::get_user_templates_dir());
+        Ok(Self {
+            ignore: builder.build().context("Invalid .docgenignore")?,
+            include: glob_set(include)?,
+            exclude: glob_set(exclude)?,
+            pruned,
+        })
+    }
+
+    /// Also skip `dir`, e.g. the output directory of a build
+    pub fn prune(mut self, dir: &Path) -> Self {
+        self.pruned.push(self.ignore.path().join(dir));
+        self
+    }
+
+    /// All documents below `root`, sorted by path
+    pub fn find(&self, root: &Path) -> Vec<PathBuf> {
+        WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| {
+                !self.is_pruned(e) && !self.is_ignored(e.path(), e.file_type().is_dir())
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && self.is_document(root, e.path()))
+            .map(|e| e.into_path())
+            .collect()
+    }
+
+    /// Whether `path` (found below `root`) should be compiled
+    pub fn is_document(&self, root: &Path, path: &Path) -> bool {
+        if path
+            .extension()
+            .is_none_or(|ext| ext != "json" && ext != "typ")
+        {
+            return false;
+        }
+        if self.is_ignored(path, false) {
+            return false;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let matches = |set: &GlobSet| set.is_match(path) || set.is_match(relative);
+        if self.exclude.as_ref().is_some_and(matches) {
+            return false;
+        }
+        if self.include.as_ref().is_some_and(|set| !matches(set)) {
+            return false;
+        }
+        marker(path) != Marker::NotADocument
+    }
+
+    fn is_pruned(&self, entry: &walkdir::DirEntry) -> bool {
+        if !entry.file_type().is_dir() {
+            return false;
+        }
+        self.pruned.contains(&self.ignore.path().join(entry.path()))
+    }
+
+    /// Ignore rules only apply inside the project
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if path.is_absolute() && !path.starts_with(self.ignore.path()) {
+            return false;
+        }
+        self.ignore
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Marker {
+    /// Marked as a document, optionally with its type
+    Document(Option<String>),
+    /// No marker, but looks like a document
+    Unmarked,
+    NotADocument,
+}
+
+/// Type declared by a document's marker, if any
+pub fn declared_type(path: &Path) -> Option<String> {
+    match marker(path) {
+        Marker::Document(doc_type) => doc_type,
+        _ => None,
+    }
+}
+
+fn marker(path: &Path) -> Marker {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Marker::NotADocument;
+    };
+    if path.extension().is_some_and(|ext| ext == "typ") {
+        typ_marker(&content)
+    } else {
+        json_marker(&content)
+    }
+}
+
+fn json_marker(content: &str) -> Marker {
+    let Ok(Value::Object(json)) = serde_json::from_str::<Value>(content) else {
+        // Broken JSON is still reported as a failed document
+        return if content.trim_start().starts_with('{') {
+            Marker::Unmarked
+        } else {
+            Marker::NotADocument
+        };
+    };
+    match json.get(JSON_MARKER) {
+        Some(marker) => {
+            Marker::Document(marker.get("type").and_then(Value::as_str).map(String::from))
+        }
+        None if json.get("metadata").is_some_and(Value::is_object) => Marker::Unmarked,
+        None => Marker::NotADocument,
+    }
+}
+
+fn typ_marker(content: &str) -> Marker {
+    // The marker must be in the leading comment block
+    for line in content.lines().map(str::trim) {
+        if let Some(options) = line.strip_prefix(TYP_MARKER) {
+            let doc_type = options
+                .split([' ', ','])
+                .find_map(|option| option.strip_prefix("type="))
+                .map(String::from);
+            return Marker::Document(doc_type);
+        }
+        if !line.is_empty() && !line.starts_with("//") {
+            break;
+        }
+    }
+    if content.lines().any(|line| line.starts_with("#show:")) {
+        Marker::Unmarked
+    } else {
+        Marker::NotADocument
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid glob '{}'", pattern))?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_marker() {
+        assert_eq!(
+            json_marker(r#"{"$docgen": {"type": "concept"}, "title": "x"}"#),
+            Marker::Document(Some("concept".to_string()))
+        );
+        assert_eq!(
+            json_marker(r#"{"metadata": {"invoice_number": "RE-1"}}"#),
+            Marker::Unmarked
+        );
+        assert_eq!(json_marker(r#"{"items": []}"#), Marker::NotADocument);
+        assert_eq!(json_marker("[1, 2]"), Marker::NotADocument);
+    }
+
+    #[test]
+    fn test_typ_marker() {
+        assert_eq!(
+            typ_marker("// Offer for Hofbauer\n// docgen: type=concept\n\n#import \"x.typ\""),
+            Marker::Document(Some("concept".to_string()))
+        );
+        assert_eq!(
+            typ_marker("#import \"x.typ\": concept\n#show: concept.with(title: \"A\")\n"),
+            Marker::Unmarked
+        );
+        // A partial defining functions only
+        assert_eq!(
+            typ_marker("#let box(body) = block(body)\n// docgen: type=concept\n"),
+            Marker::NotADocument
+        );
+    }
+
+    #[test]
+    fn test_ignore_file_and_globs() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let docs = tmp.path().join("documents");
+        for file in ["invoices/a.json", "drafts/b.json", "invoices/partial.typ"] {
+            let path = docs.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, r#"{"metadata": {}}"#).unwrap();
+        }
+
+        let mut builder = GitignoreBuilder::new(tmp.path());
+        builder.add_line(None, "drafts/").unwrap();
+        let discovery = Discovery {
+            ignore: builder.build().unwrap(),
+            include: None,
+            exclude: glob_set(&["**/z*.json".to_string()]).unwrap(),
+            pruned: Vec::new(),
+        };
+        assert_eq!(discovery.find(&docs), vec![docs.join("invoices/a.json")]);
+
+        let discovery = Discovery {
+            ignore: Gitignore::empty(),
+            include: glob_set(&["drafts/*".to_string()]).unwrap(),
+            exclude: None,
+            pruned: Vec::new(),
+        };
+        assert_eq!(discovery.find(&docs), vec![docs.join("drafts/b.json")]);
+
+        // Pruned directories are skipped even when a glob matches them
+        let discovery = Discovery {
+            ignore: Gitignore::empty(),
+            include: glob_set(&["**/*.json".to_string()]).unwrap(),
+            exclude: None,
+            pruned: vec![docs.join("drafts")],
+        };
+        assert_eq!(discovery.find(&docs), vec![docs.join("invoices/a.json")]);
+    }
+}
@@ -0,0 +1,664 @@
+// Document types beyond the built-in ones
+//
+// A template whose template.json declares its own name as `type` (and which
+// is not a standard template) adds a document type. `docgen template new`
+// scaffolds such templates; installed packages can add types the same way.
+use anyhow::{Context, Result};
+use chrono::Datelike;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::data::write_atomic;
+use crate::local_templates;
+use crate::packages::{self, Manifest, Scope};
+
+static REGISTERED: OnceLock<Vec<Manifest>> = OnceLock::new();
+
+const ACCOUNTING_TEMPLATE: &str = include_str!("../scaffold/accounting.typ");
+const DOCUMENT_TEMPLATE: &str = include_str!("../scaffold/document.typ");
+const LABELS: &str = include_str!("../scaffold/locale.json");
+
+/// Built-in document types and the path fragments that detect them, in detection order
+///
+/// Fragments starting with '/' match number prefixes (`/re-` for RE-2025-001.json).
+pub const BUILTIN: &[(&str, &[&str])] = &[
+    // Accounting-Layout Templates
+    ("invoice", &["invoice", "rechnung", "/re-"]),
+    ("offer", &["offer", "angebot", "/an-"]),
+    ("letter", &["letter", "brief", "/br-"]),
+    (
+        "credit-note",
+        &["credit-note", "credit_note", "gutschrift", "/gs-"],
+    ),
+    ("reminder", &["reminder", "mahnung", "/m"]),
+    (
+        "delivery-note",
+        &["delivery-note", "delivery_note", "lieferschein", "/ls-"],
+    ),
+    (
+        "order-confirmation",
+        &[
+            "order-confirmation",
+            "order_confirmation",
+            "auftragsbestätigung",
+            "/ab-",
+        ],
+    ),
+    (
+        "time-sheet",
+        &[
+            "time-sheet",
+            "time_sheet",
+            "timesheet",
+            "stundenzettel",
+            "/ts-",
+        ],
+    ),
+    (
+        "quotation-request",
+        &[
+            "quotation-request",
+            "quotation_request",
+            "angebotsanfrage",
+            "/anf-",
+        ],
+    ),
+    // Document-Layout Templates
+    ("credentials", &["credential", "zugang", "/zd-"]),
+    ("concept", &["concept", "konzept", "/ko-"]),
+    (
+        "documentation",
+        &["documentation", "dokumentation", "/dok-"],
+    ),
+    ("contract", &["contract", "vertrag", "/vtr-"]),
+    ("protocol", &["protocol", "protokoll", "/prot-"]),
+    (
+        "specification",
+        &["specification", "spezifikation", "/spec-"],
+    ),
+    ("proposal", &["proposal", "vorschlag", "/pro-"]),
+    ("sla", &["sla", "/sla-"]),
+];
+
+/// Built-in type of a document, from its lowercased path
+pub fn detect_builtin(path: &str) -> Option<&'static str> {
+    BUILTIN
+        .iter()
+        .find(|(_, fragments)| fragments.iter().any(|fragment| path.contains(fragment)))
+        .map(|(doc_type, _)| *doc_type)
+}
+
+/// Words of a name or keyword: "order_confirmation" → ["order", "confirmation"]
+fn words(term: &str) -> Vec<&str> {
+    term.split(['-', '_', ' '])
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Built-in type whose detection overlaps a name or keyword
+///
+/// Terms containing a built-in detection word would be detected as that
+/// type; terms sharing a word with one ("order", "note") would claim its
+/// documents.
+fn builtin_term_clash(term: &str) -> Option<&'static str> {
+    let own = words(term);
+    BUILTIN
+        .iter()
+        .find(|(_, fragments)| {
+            fragments
+                .iter()
+                .filter(|fragment| !fragment.starts_with('/'))
+                .any(|word| {
+                    term.contains(word) || words(word).iter().any(|part| own.contains(part))
+                })
+        })
+        .map(|(doc_type, _)| *doc_type)
+}
+
+/// Built-in type that uses `prefix` for its numbers
+fn builtin_prefix_clash(prefix: &str) -> Option<&'static str> {
+    let prefix = prefix.to_lowercase();
+    BUILTIN
+        .iter()
+        .find(|(_, fragments)| {
+            fragments.iter().any(|fragment| {
+                fragment
+                    .strip_prefix('/')
+                    .is_some_and(|own| own.trim_end_matches('-') == prefix)
+            })
+        })
+        .map(|(doc_type, _)| *doc_type)
+}
+
+/// Page layout of a scaffolded document type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Layout {
+    /// Letter-style page with DIN 5008 address block and item table (like invoice)
+    Accounting,
+    /// Multi-page document with title page and table of contents (like concept)
+    Document,
+}
+
+impl Layout {
+    fn name(self) -> &'static str {
+        match self {
+            Layout::Accounting => "accounting",
+            Layout::Document => "document",
+        }
+    }
+}
+
+/// Document types defined by templates in templates/ and the user-global directory
+pub fn registered() -> &'static [Manifest] {
+    REGISTERED.get_or_init(|| {
+        let dirs = [
+            Some(local_templates::get_custom_templates_dir()),
+            local_templates::get_user_templates_dir(),
+        ];
+        types_in(dirs.into_iter().flatten())
+    })
+}
+
+/// Document types defined by user-global templates, available to every project
+pub fn user_types() -> Vec<Manifest> {
+    types_in(local_templates::get_user_templates_dir())
+}
+
+fn types_in(dirs: impl IntoIterator<Item = PathBuf>) -> Vec<Manifest> {
+    let standard = local_templates::get_available_templates();
+    let mut types: Vec<Manifest> = Vec::new();
+    for dir in dirs {
+        for manifest in manifests_in(&dir) {
+            if manifest.doc_type == manifest.name
+                && !standard.contains(&manifest.name)
+                && !types.iter().any(|t| t.name == manifest.name)
+            {
+                types.push(manifest);
+            }
+        }
+    }
+    types
+}
+
+fn manifests_in(dir: &Path) -> Vec<Manifest> {
+    let mut manifests: Vec<Manifest> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let manifest = Manifest::load(&entry.path()).ok()??;
+            (entry.file_name().to_string_lossy() == manifest.name).then_some(manifest)
+        })
+        .collect();
+    manifests.sort_by(|a, b| a.name.cmp(&b.name));
+    manifests
+}
+
+/// Directory below documents/ for a registered type
+pub fn directory(manifest: &Manifest) -> String {
+    manifest
+        .directory
+        .clone()
+        .unwrap_or_else(|| format!("{}s", manifest.name))
+}
+
+/// Registered type of a document, from its lowercased path
+///
+/// Matches whole path segments: a directory named like the type, its
+/// documents/ directory or a keyword, or a file name that is or starts with
+/// the type name or a keyword, or starts with `<prefix>-`.
+pub fn detect(path: &str) -> Option<String> {
+    registered()
+        .iter()
+        .find(|manifest| matches(manifest, path))
+        .map(|manifest| manifest.name.clone())
+}
+
+fn matches(manifest: &Manifest, path: &str) -> bool {
+    let segments: Vec<&str> = path.split(['/', '\\']).filter(|s| !s.is_empty()).collect();
+    let Some((file, dirs)) = segments.split_last() else {
+        return false;
+    };
+    let stem = file.rsplit_once('.').map_or(*file, |(stem, _)| stem);
+    let starts_with = |term: &str| {
+        stem == term
+            || ['-', '_']
+                .iter()
+                .any(|sep| stem.starts_with(&format!("{}{}", term, sep)))
+    };
+    let is_term = |term: &str| dirs.contains(&term) || starts_with(term);
+
+    let directory = directory(manifest).to_lowercase();
+    dirs.contains(&directory.as_str())
+        || is_term(&manifest.name)
+        || manifest
+            .prefix
+            .as_ref()
+            .is_some_and(|prefix| stem.starts_with(&format!("{}-", prefix.to_lowercase())))
+        || manifest
+            .keywords
+            .iter()
+            .any(|keyword| is_term(&keyword.to_lowercase()))
+}
+
+/// Options of `docgen template new`
+pub struct NewType {
+    pub name: String,
+    pub layout: Layout,
+    /// Document title, e.g. "Wartungsbericht"
+    pub title: Option<String>,
+    /// Number prefix, e.g. "WB"
+    pub prefix: Option<String>,
+    pub scope: Scope,
+}
+
+/// "maintenance-report" → "Maintenance Report"
+fn title_case(name: &str) -> String {
+    name.split('-')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// "maintenance-report" → "MR"
+fn initials(name: &str) -> String {
+    name.split('-')
+        .filter_map(|word| word.chars().next())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Generate the template directory of a new document type
+///
+/// Writes default.typ (importing the shared components), template.json,
+/// schema.json, sample.json and locale.json with labels for all languages.
+pub fn scaffold(new: &NewType) -> Result<Manifest> {
+    let name = new.name.as_str();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        || !name.starts_with(|c: char| c.is_ascii_lowercase())
+    {
+        anyhow::bail!(
+            "Invalid type name '{}': start with a letter, then use lowercase letters, digits and '-'",
+            name
+        );
+    }
+    if local_templates::get_available_templates().contains(&new.name) {
+        anyhow::bail!("'{}' is a standard document type", name);
+    }
+    let dir = new.scope.dir()?.join(name);
+    if dir.exists() {
+        anyhow::bail!("{} already exists", dir.display());
+    }
+
+    if let Some(builtin) = builtin_term_clash(name) {
+        anyhow::bail!(
+            "Type name '{}' overlaps the built-in type '{}', so their documents could not \
+             be told apart. Choose another name",
+            name,
+            builtin
+        );
+    }
+
+    let title = new.title.clone().unwrap_or_else(|| title_case(name));
+    let prefix = new.prefix.clone().unwrap_or_else(|| initials(name));
+    let keyword = title.to_lowercase().replace(' ', "-");
+    if let Some(builtin) = builtin_term_clash(&keyword) {
+        anyhow::bail!(
+            "Title '{}' overlaps the built-in type '{}'. Choose another --title",
+            title,
+            builtin
+        );
+    }
+    if let Some(builtin) = builtin_prefix_clash(&prefix) {
+        anyhow::bail!(
+            "Prefix '{}' is used by the built-in type '{}'. Choose another --prefix",
+            prefix,
+            builtin
+        );
+    }
+    let mut manifest = Manifest {
+        name: name.to_string(),
+        version: "0.1.0".to_string(),
+        doc_type: name.to_string(),
+        docgen: Some(format!(">={}", local_templates::get_docgen_version())),
+        description: Some(format!("{} ({} layout)", title, new.layout.name())),
+        schema: Some("schema.json".to_string()),
+        sample: Some("sample.json".to_string()),
+        entry: format!("{}.typ", local_templates::DEFAULT_VARIANT),
+        company_fields: Vec::new(),
+        locales: Vec::new(),
+        layout: Some(new.layout.name().to_string()),
+        prefix: Some(prefix),
+        directory: Some(format!("{}s", name)),
+        keywords: if keyword != name {
+            vec![keyword]
+        } else {
+            Vec::new()
+        },
+    };
+
+    let mut labels: Value = serde_json::from_str(LABELS)?;
+    for (lang, texts) in labels.as_object_mut().context("Invalid labels")? {
+        manifest.locales.push(lang.clone());
+        texts["title"] = json!(title);
+    }
+
+    let (source, schema, sample) = match new.layout {
+        Layout::Accounting => (
+            ACCOUNTING_TEMPLATE.to_string(),
+            accounting_schema(),
+            accounting_sample(&manifest),
+        ),
+        Layout::Document => (
+            DOCUMENT_TEMPLATE.replace("{{function}}", &name.replace('-', "_")),
+            document_schema(),
+            document_sample(&manifest, &title),
+        ),
+    };
+    manifest.company_fields = company_fields(new.layout);
+
+    fs::create_dir_all(&dir)?;
+    let source = local_templates::rewrite_common_imports(&source.replace("{{title}}", &title));
+    fs::write(dir.join(&manifest.entry), source)?;
+    let pretty = |value: &Value| serde_json::to_string_pretty(value).map(|s| s + "\n");
+    fs::write(dir.join("locale.json"), pretty(&labels)?)?;
+    fs::write(dir.join("schema.json"), pretty(&schema)?)?;
+    fs::write(dir.join("sample.json"), pretty(&sample)?)?;
+    fs::write(
+        dir.join(packages::MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)? + "\n",
+    )?;
+    manifest.validate(&dir)?;
+    Ok(manifest)
+}
+
+fn company_fields(layout: Layout) -> Vec<String> {
+    let mut fields = vec![
+        "address.city",
+        "address.house_number",
+        "address.postal_code",
+        "address.street",
+        "contact.email",
+        "contact.phone",
+        "contact.website",
+        "language",
+        "logo",
+        "name",
+    ];
+    match layout {
+        Layout::Accounting => fields.extend([
+            "bank_account.account_holder",
+            "bank_account.bank_name",
+            "bank_account.bic",
+            "bank_account.iban",
+            "business_owner",
+            "vat_id",
+        ]),
+        Layout::Document => fields.extend([
+            "branding.accent_color",
+            "branding.font_preset",
+            "branding.primary_color",
+        ]),
+    }
+    fields.sort_unstable();
+    fields.into_iter().map(String::from).collect()
+}
+
+fn string(example: &str) -> Value {
+    json!({"type": "string", "examples": [example]})
+}
+
+fn date() -> Value {
+    json!({
+        "type": "object",
+        "properties": {"date": string("2025-03-14")},
+        "required": ["date"]
+    })
+}
+
+fn accounting_schema() -> Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "type": "object",
+        "properties": {
+            "metadata": {
+                "type": "object",
+                "properties": {
+                    "document_number": string("MR-2025-001"),
+                    "date": date(),
+                    "customer_number": string("K-001"),
+                    "show_footer": {"type": "boolean", "examples": [true]}
+                },
+                "required": ["document_number", "date"]
+            },
+            "recipient": {
+                "type": "object",
+                "properties": {
+                    "name": string("Max Mustermann"),
+                    "company": string("Musterfirma GmbH"),
+                    "address": {
+                        "type": "object",
+                        "properties": {
+                            "street": string("Musterstraße"),
+                            "house_number": string("1"),
+                            "postal_code": string("12345"),
+                            "city": string("Musterstadt"),
+                            "country": string("Deutschland")
+                        },
+                        "required": ["street", "house_number", "postal_code", "city"]
+                    }
+                },
+                "required": ["name", "address"]
+            },
+            "intro": {"type": "string", "description": "Text above the items"},
+            "items": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "position": {"type": "integer", "examples": [1]},
+                        "description": string("Sichtprüfung der Anlage"),
+                        "notes": string("ohne Befund")
+                    },
+                    "required": ["description"]
+                }
+            },
+            "closing": {"type": "string", "description": "Text below the items"}
+        },
+        "required": ["metadata", "recipient"]
+    })
+}
+
+fn accounting_sample(manifest: &Manifest) -> Value {
+    let today = chrono::Local::now().date_naive();
+    json!({
+        "metadata": {
+            "document_number": sample_number(manifest, today.year()),
+            "date": {"date": today.format("%Y-%m-%d").to_string()},
+            "customer_number": "K-001"
+        },
+        "recipient": {
+            "name": "Max Mustermann",
+            "company": "Musterfirma GmbH",
+            "address": {
+                "street": "Musterstraße",
+                "house_number": "1",
+                "postal_code": "12345",
+                "city": "Musterstadt",
+                "country": "Deutschland"
+            }
+        },
+        "intro": "Sehr geehrte Damen und Herren,",
+        "items": [
+            {"position": 1, "description": "Sichtprüfung der Anlage", "notes": "ohne Befund"},
+            {"position": 2, "description": "Austausch der Filter"}
+        ],
+        "closing": "Mit freundlichen Grüßen"
+    })
+}
+
+fn document_schema() -> Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "type": "object",
+        "properties": {
+            "metadata": {
+                "type": "object",
+                "properties": {
+                    "title": string("Wartung Q1"),
+                    "document_number": string("MR-2025-001"),
+                    "client_name": string("Musterfirma GmbH"),
+                    "project_name": string("Wartungsvertrag 2025"),
+                    "version": string("1.0"),
+                    "status": string("Final"),
+                    "created_at": date(),
+                    "show_toc": {"type": "boolean", "examples": [true]}
+                },
+                "required": ["document_number"]
+            },
+            "content": {
+                "type": "string",
+                "description": "Typst markup of the document body"
+            },
+            "content_file": {
+                "type": "string",
+                "description": "Typst file with the document body, instead of content"
+            }
+        },
+        "required": ["metadata"]
+    })
+}
+
+fn document_sample(manifest: &Manifest, title: &str) -> Value {
+    let today = chrono::Local::now().date_naive();
+    json!({
+        "metadata": {
+            "title": title,
+            "document_number": sample_number(manifest, today.year()),
+            "client_name": "Musterfirma GmbH",
+            "project_name": "Wartungsvertrag 2025",
+            "version": "1.0",
+            "created_at": {"date": today.format("%Y-%m-%d").to_string()}
+        },
+        "content": "= Überblick\n\nZusammenfassung.\n\n= Details\n\n- Erster Punkt\n- Zweiter Punkt\n"
+    })
+}
+
+fn sample_number(manifest: &Manifest, year: i32) -> String {
+    format!(
+        "{}-{}-001",
+        manifest.prefix.as_deref().unwrap_or("DOC"),
+        year
+    )
+}
+
+/// Register a new type in the current project
+///
+/// Creates its documents/ directory for the current year and adds its
+/// number prefix to `numbering.prefixes` in data/company.json. Returns the
+/// created directory.
+pub fn register_in_project(manifest: &Manifest) -> Result<PathBuf> {
+    let year = chrono::Local::now().year();
+    let documents = PathBuf::from("documents")
+        .join(directory(manifest))
+        .join(year.to_string());
+    fs::create_dir_all(&documents)?;
+
+    let company_path = Path::new("data/company.json");
+    if let (Some(prefix), true) = (&manifest.prefix, company_path.exists()) {
+        let content = fs::read_to_string(company_path)?;
+        let mut company: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid {}", company_path.display()))?;
+        if let Some(company) = company.as_object_mut() {
+            let numbering = company.entry("numbering").or_insert_with(|| json!({}));
+            if let Some(numbering) = numbering.as_object_mut() {
+                let prefixes = numbering.entry("prefixes").or_insert_with(|| json!({}));
+                if let Some(prefixes) = prefixes.as_object_mut() {
+                    if !prefixes.contains_key(&manifest.name) {
+                        prefixes.insert(manifest.name.clone(), json!(prefix));
+                        write_atomic(company_path, serde_json::to_string_pretty(&company)? + "\n")?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(name: &str, prefix: &str, keywords: &[&str]) -> Manifest {
+        serde_json::from_value(json!({
+            "name": name,
+            "type": name,
+            "prefix": prefix,
+            "keywords": keywords,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_matches_name_directory_prefix_and_keywords() {
+        let report = manifest("maintenance-report", "WB", &["wartungsbericht"]);
+        for path in [
+            "documents/maintenance-reports/2025/a.json",
+            "documents/x/maintenance-report-1.json",
+            "documents/x/wb-2025-001.json",
+            "documents/wartungsbericht-q1.json",
+        ] {
+            assert!(matches(&report, path), "{}", path);
+        }
+        for path in [
+            "documents/invoices/2025/re-2025-001.json",
+            "documents/x/preventive-maintenance-report.json",
+            "documents/x/wbx-2025-001.json",
+        ] {
+            assert!(!matches(&report, path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_builtin_detection_words_and_prefixes_clash() {
+        for (term, builtin) in [
+            ("order", "order-confirmation"),
+            ("note", "credit-note"),
+            ("sheet", "time-sheet"),
+            ("invoices", "invoice"),
+            ("reminder-extension", "reminder"),
+            ("angebot-neu", "offer"),
+        ] {
+            assert_eq!(builtin_term_clash(term), Some(builtin), "{}", term);
+        }
+        assert_eq!(builtin_term_clash("maintenance-report"), None);
+        assert_eq!(builtin_term_clash("wartungsbericht"), None);
+
+        assert_eq!(
+            builtin_prefix_clash(&initials("reminder-extension")),
+            Some("invoice")
+        );
+        assert_eq!(builtin_prefix_clash("AN"), Some("offer"));
+        assert_eq!(builtin_prefix_clash("M"), Some("reminder"));
+        assert_eq!(builtin_prefix_clash("MR"), None);
+        assert_eq!(builtin_prefix_clash("WB"), None);
+    }
+
+    #[test]
+    fn test_names_derived_from_type() {
+        assert_eq!(title_case("maintenance-report"), "Maintenance Report");
+        assert_eq!(initials("maintenance-report"), "MR");
+    }
+}
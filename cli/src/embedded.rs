@@ -54,10 +54,10 @@ mod tests {
     #[test]
     fn test_locales_loaded() {
         let locales = get_locales();
-        assert!(locales.len() > 0, "No locales loaded!");
+        assert!(!locales.is_empty(), "No locales loaded!");
         for l in locales {
             println!("Locale: {} (length: {})", l.path, l.content.len());
-            assert!(l.content.len() > 0, "Locale {} is empty!", l.path);
+            assert!(!l.content.is_empty(), "Locale {} is empty!", l.path);
         }
     }
 }
@@ -0,0 +1,297 @@
+// Upstream tracking for forked templates in templates/
+//
+// A fork keeps the docgen version, file hashes and a copy of every file it
+// was forked from in `.fork/`. That copy is the common ancestor for
+// `docgen template diff` and the three-way merge of `docgen template upgrade`.
+use anyhow::{Context, Result};
+use diffy::{ConflictStyle, MergeOptions};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::local_templates;
+
+/// Fork metadata and base copies, inside the fork directory
+pub const FORK_DIR: &str = ".fork";
+const INFO_FILE: &str = "fork.json";
+const BASE_DIR: &str = "base";
+
+/// Where a fork came from
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForkInfo {
+    /// Standard template the fork was made from
+    pub template: String,
+    /// docgen version of the base copy
+    pub version: String,
+    /// SHA-256 of each base file, by path relative to the fork
+    pub files: BTreeMap<String, String>,
+}
+
+/// An upstream change since the fork's base
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Added(String),
+    Removed(String),
+    Modified(String),
+}
+
+/// Result of `upgrade`
+#[derive(Debug, Default)]
+pub struct Upgrade {
+    pub from: String,
+    /// Files updated without conflicts
+    pub merged: Vec<String>,
+    /// Files written with conflict markers
+    pub conflicts: Vec<String>,
+    /// Files upstream changed but that were kept as they are (deleted or binary)
+    pub skipped: Vec<String>,
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Files of a fork by relative path, without its `.fork/` directory
+pub fn read_files(dir: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() != 1 || e.file_name() != FORK_DIR)
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let relative = entry.path().strip_prefix(dir)?;
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        files.insert(relative, fs::read(entry.path())?);
+    }
+    Ok(files)
+}
+
+/// Record `files` as the base of the fork in `dir`
+pub fn record(dir: &Path, template: &str, files: &BTreeMap<String, Vec<u8>>) -> Result<()> {
+    let fork_dir = dir.join(FORK_DIR);
+    if fork_dir.exists() {
+        fs::remove_dir_all(&fork_dir)?;
+    }
+    for (path, contents) in files {
+        let target = fork_dir.join(BASE_DIR).join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, contents)?;
+    }
+
+    let info = ForkInfo {
+        template: template.to_string(),
+        version: local_templates::get_docgen_version(),
+        files: files
+            .iter()
+            .map(|(path, contents)| (path.clone(), sha256(contents)))
+            .collect(),
+    };
+    fs::write(
+        fork_dir.join(INFO_FILE),
+        serde_json::to_string_pretty(&info)?,
+    )?;
+    Ok(())
+}
+
+/// Metadata and base files of the fork in `dir`
+pub fn load(dir: &Path) -> Result<(ForkInfo, BTreeMap<String, Vec<u8>>)> {
+    let info_path = dir.join(FORK_DIR).join(INFO_FILE);
+    let content = fs::read_to_string(&info_path).with_context(|| {
+        format!(
+            "{} has no fork metadata (.fork/fork.json). Forks made with older docgen versions cannot be diffed or upgraded; fork the template again and copy your changes over.",
+            dir.display()
+        )
+    })?;
+    let info: ForkInfo = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid fork metadata: {}", info_path.display()))?;
+
+    let base_dir = dir.join(FORK_DIR).join(BASE_DIR);
+    let mut base = BTreeMap::new();
+    for path in info.files.keys() {
+        let contents = fs::read(base_dir.join(path)).with_context(|| {
+            format!("Base copy of {} is missing in {}", path, base_dir.display())
+        })?;
+        base.insert(path.clone(), contents);
+    }
+    Ok((info, base))
+}
+
+/// Embedded files of the template the fork was made from
+fn upstream_files(info: &ForkInfo) -> Result<BTreeMap<String, Vec<u8>>> {
+    local_templates::fork_files(&info.template).with_context(|| {
+        format!(
+            "Template '{}' is no longer part of docgen {}",
+            info.template,
+            local_templates::get_docgen_version()
+        )
+    })
+}
+
+/// Changes between two sets of files
+fn changes(base: &BTreeMap<String, Vec<u8>>, upstream: &BTreeMap<String, Vec<u8>>) -> Vec<Change> {
+    let paths: BTreeSet<&String> = base.keys().chain(upstream.keys()).collect();
+    paths
+        .into_iter()
+        .filter_map(|path| match (base.get(path), upstream.get(path)) {
+            (None, Some(_)) => Some(Change::Added(path.clone())),
+            (Some(_), None) => Some(Change::Removed(path.clone())),
+            (Some(old), Some(new)) if old != new => Some(Change::Modified(path.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Unified diff of the upstream changes since the fork in `dir` was made
+///
+/// Returns the fork's metadata and the patch text; the text is empty when
+/// upstream is unchanged.
+pub fn diff(dir: &Path) -> Result<(ForkInfo, String)> {
+    let (info, base) = load(dir)?;
+    let upstream = upstream_files(&info)?;
+
+    let empty = Vec::new();
+    let mut out = String::new();
+    for change in changes(&base, &upstream) {
+        let (Change::Added(path) | Change::Removed(path) | Change::Modified(path)) = &change;
+        let old = base.get(path).unwrap_or(&empty);
+        let new = upstream.get(path).unwrap_or(&empty);
+        out.push_str(&format!("--- a/{}\n+++ b/{}\n", path, path));
+        match (std::str::from_utf8(old), std::str::from_utf8(new)) {
+            (Ok(old), Ok(new)) => {
+                let patch = diffy::create_patch(old, new).to_string();
+                // Skip diffy's own ---/+++ header lines
+                for line in patch.lines().skip(2) {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+            _ => out.push_str("Binary files differ\n"),
+        }
+    }
+    Ok((info, out))
+}
+
+/// Three-way merge of the upstream changes into the fork in `dir`
+///
+/// Afterwards the fork's base is the current upstream, so the next upgrade
+/// only brings in newer changes.
+pub fn upgrade(dir: &Path) -> Result<Upgrade> {
+    let (info, base) = load(dir)?;
+    let upstream = upstream_files(&info)?;
+    let ours = read_files(dir)?;
+    let mut report = Upgrade {
+        from: info.version.clone(),
+        ..Upgrade::default()
+    };
+
+    let mut options = MergeOptions::new();
+    options.set_conflict_style(ConflictStyle::Merge);
+    let empty = Vec::new();
+
+    for change in changes(&base, &upstream) {
+        match change {
+            Change::Removed(path) => {
+                // Only drop files the fork left untouched
+                if ours.get(&path) == base.get(&path) {
+                    fs::remove_file(dir.join(&path))?;
+                    report.merged.push(path);
+                } else if ours.contains_key(&path) {
+                    report.skipped.push(path);
+                }
+            }
+            Change::Added(path) | Change::Modified(path) => {
+                let theirs = &upstream[&path];
+                let ancestor = base.get(&path).unwrap_or(&empty);
+                let target = dir.join(&path);
+                let Some(mine) = ours.get(&path) else {
+                    if base.contains_key(&path) {
+                        // Deleted in the fork on purpose
+                        report.skipped.push(path);
+                    } else {
+                        if let Some(parent) = target.parent() {
+                            fs::create_dir_all(parent)?;
+                        }
+                        fs::write(&target, theirs)?;
+                        report.merged.push(path);
+                    }
+                    continue;
+                };
+
+                if mine == ancestor {
+                    fs::write(&target, theirs)?;
+                    report.merged.push(path);
+                    continue;
+                }
+                let texts = (
+                    std::str::from_utf8(ancestor),
+                    std::str::from_utf8(mine),
+                    std::str::from_utf8(theirs),
+                );
+                let (Ok(ancestor), Ok(mine), Ok(theirs)) = texts else {
+                    report.skipped.push(path);
+                    continue;
+                };
+                match options.merge(ancestor, mine, theirs) {
+                    Ok(merged) => {
+                        fs::write(&target, merged)?;
+                        report.merged.push(path);
+                    }
+                    Err(conflicted) => {
+                        fs::write(&target, conflicted)?;
+                        report.conflicts.push(path);
+                    }
+                }
+            }
+        }
+    }
+
+    record(dir, &info.template, &upstream)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn files(entries: &[(&str, &str)]) -> BTreeMap<String, Vec<u8>> {
+        entries
+            .iter()
+            .map(|(path, text)| (path.to_string(), text.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_changes_between_base_and_upstream() {
+        let base = files(&[("a.typ", "1"), ("b.typ", "2"), ("c.typ", "3")]);
+        let upstream = files(&[("a.typ", "1"), ("b.typ", "changed"), ("d.typ", "4")]);
+        assert_eq!(
+            changes(&base, &upstream),
+            vec![
+                Change::Modified("b.typ".into()),
+                Change::Removed("c.typ".into()),
+                Change::Added("d.typ".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_and_load_round_trip() {
+        let tmp = TempDir::new().unwrap();
+        let fork = files(&[("default.typ", "#let x = 1\n"), ("parts/a.typ", "a")]);
+        record(tmp.path(), "invoice", &fork).unwrap();
+
+        let (info, base) = load(tmp.path()).unwrap();
+        assert_eq!(info.template, "invoice");
+        assert_eq!(info.files.len(), 2);
+        assert_eq!(base, fork);
+        // The base copy is not part of the fork's own files
+        assert!(read_files(tmp.path()).unwrap().is_empty());
+    }
+}
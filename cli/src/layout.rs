@@ -0,0 +1,209 @@
+// Output paths of built documents
+use anyhow::{Context, Result};
+use chrono::Datelike;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::data::documents::{document_number, parse_date};
+
+/// Mirror the source tree: documents/invoices/2025/RE-001.json → output/invoices/2025/RE-001.pdf
+pub const DEFAULT_PATTERN: &str = "{path}.pdf";
+
+const PLACEHOLDERS: [&str; 6] = ["path", "dir", "stem", "type", "number", "year"];
+
+/// Metadata fields holding the document date, in order of preference
+const DATE_FIELDS: [&str; 4] = ["date", "invoice_date", "offer_date", "delivery_date"];
+
+/// Output path pattern, e.g. `{type}/{year}/{number}.pdf`
+///
+/// Placeholders: `{path}` (source path below the build directory, without
+/// extension), `{dir}`, `{stem}`, `{type}`, `{number}` (falls back to the
+/// file stem) and `{year}` (document date, else a year directory).
+pub struct Layout {
+    pattern: String,
+}
+
+impl Layout {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let mut rest = pattern;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .with_context(|| format!("Unclosed placeholder in output pattern '{}'", pattern))?;
+            let name = &rest[start + 1..start + end];
+            if !PLACEHOLDERS.contains(&name) {
+                anyhow::bail!(
+                    "Unknown placeholder {{{}}} in output pattern '{}' (available: {})",
+                    name,
+                    pattern,
+                    PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", ")
+                );
+            }
+            rest = &rest[start + end + 1..];
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+        })
+    }
+
+    /// `--layout` if given, else `structure.output_pattern` from company.json
+    pub fn configured(pattern: Option<String>) -> Result<Self> {
+        let pattern = pattern
+            .or_else(|| {
+                let content = fs::read_to_string("data/company.json").ok()?;
+                let company: Value = serde_json::from_str(&content).ok()?;
+                company
+                    .get("structure")?
+                    .get("output_pattern")?
+                    .as_str()
+                    .map(String::from)
+            })
+            .unwrap_or_else(|| DEFAULT_PATTERN.to_string());
+        Self::new(&pattern)
+    }
+
+    /// Path of the PDF for `input`, relative to the output directory
+    pub fn output_path(&self, root: &Path, input: &Path) -> Result<PathBuf> {
+        let relative = input.strip_prefix(root).unwrap_or(input);
+        let stem = file_name(relative.file_stem());
+        let json = read_json(input);
+
+        let mut path = self.pattern.clone();
+        for name in PLACEHOLDERS {
+            let placeholder = format!("{{{}}}", name);
+            if !path.contains(&placeholder) {
+                continue;
+            }
+            let value = match name {
+                "path" => slash_path(&relative.with_extension("")),
+                "dir" => slash_path(relative.parent().unwrap_or(Path::new(""))),
+                "stem" => stem.clone(),
+                "type" => crate::document_type(input).unwrap_or_else(|| "other".into()),
+                "number" => json
+                    .as_ref()
+                    .and_then(document_number)
+                    .map(|n| n.replace(['/', '\\'], "-"))
+                    .unwrap_or_else(|| stem.clone()),
+                _ => year(json.as_ref(), relative).with_context(|| {
+                    format!(
+                        "{}: no document date or year directory for {{year}}",
+                        input.display()
+                    )
+                })?,
+            };
+            path = path.replace(&placeholder, &value);
+        }
+
+        // Empty placeholders ({dir} at the top level) must not produce absolute paths
+        let path = path.trim_start_matches('/').replace("//", "/");
+        Ok(PathBuf::from(path))
+    }
+}
+
+/// Inputs that would be written to the same output file
+pub fn collisions(outputs: &[(PathBuf, PathBuf)]) -> Vec<(PathBuf, Vec<PathBuf>)> {
+    let mut by_output: BTreeMap<&PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for (input, output) in outputs {
+        by_output.entry(output).or_default().push(input.clone());
+    }
+    by_output
+        .into_iter()
+        .filter(|(_, inputs)| inputs.len() > 1)
+        .map(|(output, inputs)| (output.clone(), inputs))
+        .collect()
+}
+
+fn read_json(input: &Path) -> Option<Value> {
+    if input.extension().is_none_or(|ext| ext != "json") {
+        return None;
+    }
+    serde_json::from_str(&fs::read_to_string(input).ok()?).ok()
+}
+
+/// Year of the document date, else the first year-like directory
+fn year(json: Option<&Value>, relative: &Path) -> Option<String> {
+    let from_date = json.and_then(|json| {
+        let metadata = json.get("metadata").unwrap_or(json);
+        DATE_FIELDS
+            .iter()
+            .find_map(|field| parse_date(metadata.get(*field)?))
+            .map(|date| date.year().to_string())
+    });
+    from_date.or_else(|| {
+        relative.parent()?.components().find_map(|c| {
+            let name = c.as_os_str().to_str()?;
+            (name.len() == 4 && name.chars().all(|ch| ch.is_ascii_digit())).then(|| name.into())
+        })
+    })
+}
+
+fn file_name(name: Option<&std::ffi::OsStr>) -> String {
+    name.unwrap_or_default().to_string_lossy().into_owned()
+}
+
+fn slash_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_default_mirrors_source_tree() {
+        let layout = Layout::new(DEFAULT_PATTERN).unwrap();
+        let root = Path::new("documents");
+        let a = layout
+            .output_path(root, Path::new("documents/invoices/2024/RE-001.json"))
+            .unwrap();
+        let b = layout
+            .output_path(root, Path::new("documents/invoices/2025/RE-001.json"))
+            .unwrap();
+        assert_eq!(a, PathBuf::from("invoices/2024/RE-001.pdf"));
+        assert_eq!(b, PathBuf::from("invoices/2025/RE-001.pdf"));
+        assert!(collisions(&[(PathBuf::from("x"), a), (PathBuf::from("y"), b)]).is_empty());
+    }
+
+    #[test]
+    fn test_pattern_from_document_metadata() {
+        let tmp = TempDir::new().unwrap();
+        let input = tmp.path().join("invoices/draft.json");
+        fs::create_dir_all(input.parent().unwrap()).unwrap();
+        fs::write(
+            &input,
+            r#"{"metadata": {"invoice_number": "RE-2024-017", "invoice_date": {"date": "2024-12-30"}}}"#,
+        )
+        .unwrap();
+
+        let layout = Layout::new("{type}/{year}/{number}.pdf").unwrap();
+        assert_eq!(
+            layout.output_path(tmp.path(), &input).unwrap(),
+            PathBuf::from("invoice/2024/RE-2024-017.pdf")
+        );
+    }
+
+    #[test]
+    fn test_unknown_placeholder_and_collisions() {
+        assert!(Layout::new("{client}/{number}.pdf").is_err());
+
+        let layout = Layout::new("{stem}.pdf").unwrap();
+        let root = Path::new("documents");
+        let inputs = ["documents/a/RE-001.json", "documents/b/RE-001.json"];
+        let outputs: Vec<(PathBuf, PathBuf)> = inputs
+            .iter()
+            .map(|i| {
+                (
+                    PathBuf::from(i),
+                    layout.output_path(root, Path::new(i)).unwrap(),
+                )
+            })
+            .collect();
+        let found = collisions(&outputs);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, PathBuf::from("RE-001.pdf"));
+        assert_eq!(found[0].1.len(), 2);
+    }
+}
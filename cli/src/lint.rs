@@ -0,0 +1,300 @@
+// Compile templates against their sample and edge cases derived from the schema
+//
+// Every case is rendered to SVG in .docgen/lint/, one file per page, so
+// page counts are cheap to get. Typst errors fail a case; its warnings are
+// reported alongside.
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::embedded;
+use crate::render;
+use crate::schema;
+
+/// Languages docgen ships locales for
+pub const LOCALES: [&str; 7] = ["de", "en", "es", "fr", "it", "nl", "pt"];
+
+const LINT_DIR: &str = ".docgen/lint";
+
+/// Text used for the long description case, repeated
+const FILLER: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ";
+const FILLER_REPEAT: usize = 30;
+
+const SAMPLE: &str = "sample";
+
+/// Document data a template is compiled with
+pub struct Case {
+    pub name: String,
+    pub data: Value,
+    pub lang: String,
+    /// Pages the case may need beyond the sample
+    pub extra_pages: usize,
+}
+
+/// Result of compiling one case
+pub struct Outcome {
+    pub case: String,
+    pub pages: usize,
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+    /// Pages the case was allowed, if it needed more
+    pub overflow: Option<usize>,
+}
+
+/// The sample, its edge cases and the sample in every other locale
+///
+/// Edge cases identical to the sample (e.g. no optional fields to drop)
+/// are left out. Only the long descriptions may add pages: one per
+/// lengthened text, which easily fits the filler.
+pub fn cases(sample: &Value, schema: Option<&Value>, lang: &str) -> Vec<Case> {
+    let mut cases = vec![Case {
+        name: SAMPLE.to_string(),
+        data: sample.clone(),
+        lang: lang.to_string(),
+        extra_pages: 0,
+    }];
+    let mut edge = |name: &str, data: Value, extra_pages: usize| {
+        if data != *sample {
+            cases.push(Case {
+                name: name.to_string(),
+                data,
+                lang: lang.to_string(),
+                extra_pages,
+            });
+        }
+    };
+    if let Some(schema) = schema {
+        edge(
+            "missing optional fields",
+            schema::required_only(schema, sample),
+            0,
+        );
+    }
+    edge("empty lists", empty_lists(sample), 0);
+    edge(
+        "long descriptions",
+        long_descriptions(sample),
+        count_descriptions(sample),
+    );
+
+    for other in LOCALES.iter().filter(|l| **l != lang) {
+        cases.push(Case {
+            name: format!("locale {}", other),
+            data: sample.clone(),
+            lang: other.to_string(),
+            extra_pages: 0,
+        });
+    }
+    cases
+}
+
+/// Every array replaced by an empty one
+fn empty_lists(value: &Value) -> Value {
+    match value {
+        Value::Array(_) => Value::Array(Vec::new()),
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, child)| (key.clone(), empty_lists(child)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        other => other.clone(),
+    }
+}
+
+/// Every `description` (and `*_description`) text made very long
+fn long_descriptions(value: &Value) -> Value {
+    match value {
+        Value::Array(items) => items.iter().map(long_descriptions).collect(),
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, child)| {
+                let child = match child {
+                    Value::String(text)
+                        if key == "description" || key.ends_with("_description") =>
+                    {
+                        Value::String(format!("{} {}", text, FILLER.repeat(FILLER_REPEAT)))
+                    }
+                    other => long_descriptions(other),
+                };
+                (key.clone(), child)
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        other => other.clone(),
+    }
+}
+
+/// Number of texts `long_descriptions` lengthens
+fn count_descriptions(value: &Value) -> usize {
+    match value {
+        Value::Array(items) => items.iter().map(count_descriptions).sum(),
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, child)| match child {
+                Value::String(_) if key == "description" || key.ends_with("_description") => 1,
+                other => count_descriptions(other),
+            })
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Compile `entry` (a template file inside the project) with every case
+///
+/// `label` names the output directory below .docgen/lint/.
+pub fn lint(label: &str, entry: &Path, cases: &[Case]) -> Result<Vec<Outcome>> {
+    let dir = Path::new(LINT_DIR).join(label);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+
+    let mut outcomes = Vec::new();
+    for (i, case) in cases.iter().enumerate() {
+        let data = dir.join(format!("case-{:02}.json", i + 1));
+        fs::write(&data, serde_json::to_string_pretty(&case.data)?)?;
+        let output = dir.join(format!(
+            "case-{:02}-{}.svg",
+            i + 1,
+            render::PAGE_PLACEHOLDER
+        ));
+
+        let result = Command::new("typst")
+            .args(["compile", "--root", ".", "--font-path", "fonts"])
+            .arg(entry)
+            .arg("--input")
+            .arg(format!("data=/{}", slash_path(&data)))
+            .arg("--input")
+            .arg(format!(
+                "company=/{}",
+                slash_path(&company_file(&case.lang)?)
+            ))
+            .arg("--input")
+            .arg(format!("locale=/{}", slash_path(&locale_file(&case.lang)?)))
+            .args(["--format", "svg"])
+            .arg(&output)
+            .output()
+            .context("Failed to run typst. Is it installed?")?;
+
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let warnings = stderr
+            .lines()
+            .filter(|line| line.starts_with("warning:"))
+            .map(|line| line.trim_start_matches("warning:").trim().to_string())
+            .collect();
+        let error = (!result.status.success()).then(|| {
+            stderr
+                .lines()
+                .find(|line| line.starts_with("error:"))
+                .map(|line| line.trim_start_matches("error:").trim().to_string())
+                .unwrap_or_else(|| stderr.trim().to_string())
+        });
+        outcomes.push(Outcome {
+            case: case.name.clone(),
+            pages: render::page_files(&output).len(),
+            error,
+            warnings,
+            overflow: None,
+        });
+    }
+
+    let sample_pages = outcomes
+        .iter()
+        .find(|o| o.case == SAMPLE && o.error.is_none())
+        .map(|o| o.pages);
+    if let Some(sample_pages) = sample_pages {
+        for (outcome, case) in outcomes.iter_mut().zip(cases) {
+            let allowed = sample_pages + case.extra_pages;
+            if outcome.error.is_none() && outcome.pages > allowed {
+                outcome.overflow = Some(allowed);
+            }
+        }
+    }
+    Ok(outcomes)
+}
+
+/// company.json with `language` set to `lang`
+fn company_file(lang: &str) -> Result<PathBuf> {
+    let path = Path::new(LINT_DIR).join(format!("company-{}.json", lang));
+    let mut company: Value = fs::read_to_string("data/company.json")
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_else(|| serde_json::json!({}));
+    if let Some(company) = company.as_object_mut() {
+        company.insert("language".to_string(), Value::from(lang));
+    }
+    fs::write(&path, serde_json::to_string_pretty(&company)?)?;
+    Ok(path)
+}
+
+/// The project's locale file for `lang`, or the embedded one
+fn locale_file(lang: &str) -> Result<PathBuf> {
+    let project = PathBuf::from("locale").join(format!("{}.json", lang));
+    if project.exists() {
+        return Ok(project);
+    }
+    let path = Path::new(LINT_DIR)
+        .join("locale")
+        .join(format!("{}.json", lang));
+    let locale = embedded::get_locales()
+        .into_iter()
+        .find(|l| l.path == format!("{}.json", lang))
+        .with_context(|| format!("No locale for '{}'", lang))?;
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(&path, locale.content)?;
+    Ok(path)
+}
+
+fn slash_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cases_from_sample_and_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "metadata": {"type": "object", "properties": {"note": {"type": "string"}}},
+                "items": {"type": "array"}
+            },
+            "required": ["items"]
+        });
+        let sample = json!({
+            "metadata": {"note": "x"},
+            "items": [{"description": "Work"}]
+        });
+        let cases = cases(&sample, Some(&schema), "en");
+        let names: Vec<&str> = cases.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names[..4],
+            [
+                "sample",
+                "missing optional fields",
+                "empty lists",
+                "long descriptions"
+            ]
+        );
+        assert_eq!(cases.len(), 4 + 6);
+        assert!(!names.contains(&"locale en"));
+        assert_eq!(cases[1].data, json!({"items": [{"description": "Work"}]}));
+        assert_eq!(cases[2].data["items"], json!([]));
+        assert!(
+            cases[3].data["items"][0]["description"]
+                .as_str()
+                .unwrap()
+                .len()
+                > 1000
+        );
+        assert_eq!(cases[4].lang, "de");
+        // Only the long descriptions may need more pages, one per text
+        let extra: Vec<usize> = cases.iter().map(|c| c.extra_pages).collect();
+        assert_eq!(extra[..5], [0, 0, 0, 1, 0]);
+    }
+}
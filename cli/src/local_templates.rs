@@ -1,10 +1,19 @@
 // Local template management for project-based templates
 use anyhow::{Context, Result};
+use colored::Colorize;
 use include_dir::{include_dir, Dir};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use walkdir::WalkDir;
 
+use crate::data::write_atomic;
+use crate::forks;
+use crate::packages;
+
 // Embed all templates at compile time
 static TEMPLATES_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/../templates");
 
@@ -18,6 +27,216 @@ pub fn get_custom_templates_dir() -> PathBuf {
     PathBuf::from("templates")
 }
 
+/// Get the user-global templates directory (e.g. ~/.config/docgen/templates)
+pub fn get_user_templates_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("docgen").join("templates"))
+}
+
+/// Copies of user-global templates inside the project, where typst can read them
+pub fn get_user_templates_cache_dir() -> PathBuf {
+    PathBuf::from(".docgen/user-templates")
+}
+
+/// Shared components of the standard templates
+pub const COMMON_DIR: &str = "common";
+
+/// Variant compiled when neither the document nor company.json selects one
+pub const DEFAULT_VARIANT: &str = "default";
+
+/// A document template and its named variants
+///
+/// Every top-level `.typ` file of a template directory is a variant, so
+/// `invoice/minimal.typ` is the `minimal` variant of `invoice`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pub name: String,
+    /// Variant names, `default` first
+    pub variants: Vec<String>,
+}
+
+/// Sort variant names with `default` first; files starting with `_` are helpers
+fn variant_names(files: impl Iterator<Item = PathBuf>) -> Vec<String> {
+    let mut names: Vec<String> = files
+        .filter(|path| path.extension().is_some_and(|ext| ext == "typ"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .filter(|name| !name.starts_with('_'))
+        .collect();
+    names.sort_by_key(|name| (name != DEFAULT_VARIANT, name.clone()));
+    names
+}
+
+/// Variants of the template in `dir`
+///
+/// The entry file of an installed package counts as its default variant.
+pub fn variants(dir: &Path) -> Vec<String> {
+    let entry = packages::Manifest::load(dir)
+        .ok()
+        .flatten()
+        .map(|m| m.entry);
+    let files = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .map(|file| match &entry {
+            Some(entry) if file.file_name().to_string_lossy() == *entry => {
+                dir.join(format!("{}.typ", DEFAULT_VARIANT))
+            }
+            _ => file.path(),
+        });
+    let mut names = variant_names(files);
+    names.dedup();
+    names
+}
+
+/// File compiled for `variant` of the template in `dir`
+fn variant_file(dir: &Path, variant: &str) -> PathBuf {
+    if variant == DEFAULT_VARIANT {
+        if let Ok(Some(manifest)) = packages::Manifest::load(dir) {
+            return dir.join(manifest.entry);
+        }
+    }
+    dir.join(format!("{}.typ", variant))
+}
+
+/// Templates (directories with at least one variant) in `dir`, sorted by name
+///
+/// `common/` holds component overrides, not a template.
+pub fn templates_in(dir: &Path) -> Vec<Template> {
+    let mut templates: Vec<Template> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|entry| entry.file_name() != COMMON_DIR)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let variants = variants(&entry.path());
+            (!variants.is_empty()).then_some(Template { name, variants })
+        })
+        .collect();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+/// Standard templates embedded in docgen, without the shared `common` components
+pub fn standard_templates() -> Vec<Template> {
+    get_available_templates()
+        .into_iter()
+        .filter(|name| name != "common")
+        .filter_map(|name| {
+            let dir = TEMPLATES_DIR.get_dir(&name)?;
+            let variants = variant_names(dir.files().map(|f| f.path().to_path_buf()));
+            Some(Template { name, variants })
+        })
+        .collect()
+}
+
+/// Variant selected in company.json for `template`
+///
+/// `templates.clients.<customer number>.variant.<template>` wins over the
+/// project-wide `templates.variant.<template>`.
+pub fn configured_variant(
+    company: &serde_json::Value,
+    template: &str,
+    customer: Option<&str>,
+) -> Option<String> {
+    let settings = company.get("templates")?;
+    let for_client = customer
+        .and_then(|customer| settings.get("clients")?.get(customer)?.get("variant"))
+        .and_then(|variant| variant.get(template));
+    for_client
+        .or_else(|| settings.get("variant")?.get(template))?
+        .as_str()
+        .map(String::from)
+}
+
+/// Resolve a template variant to the entry point typst compiles
+///
+/// Looks in the project's templates/, then the user-global templates directory,
+/// then the standard templates in .docgen/templates/, taking the first that
+/// has the variant. A user-global `invoice/` can thus add variants to the
+/// standard invoice. User-global templates live outside the project root, so
+/// the path points into their copy in .docgen/user-templates/, which
+/// `ensure_local_templates_updated` keeps in sync.
+pub fn resolve_template(name: &str, variant: &str) -> Result<PathBuf> {
+    for (kind, value) in [("template", name), ("variant", variant)] {
+        if value.is_empty() || value.contains(['/', '\\']) || value.starts_with('.') {
+            anyhow::bail!("Invalid {} name '{}'", kind, value);
+        }
+    }
+    let custom = get_custom_templates_dir().join(name);
+    if variant_file(&custom, variant).exists() {
+        return Ok(variant_file(&custom, variant));
+    }
+
+    let user = get_user_templates_dir().map(|dir| dir.join(name));
+    if user
+        .as_ref()
+        .is_some_and(|dir| variant_file(dir, variant).exists())
+    {
+        return Ok(variant_file(
+            &get_user_templates_cache_dir().join(name),
+            variant,
+        ));
+    }
+
+    let standard = get_local_templates_dir().join(name);
+    if variant_file(&standard, variant).exists() {
+        return Ok(variant_file(&standard, variant));
+    }
+
+    let mut available: Vec<String> = [Some(custom), user, Some(standard)]
+        .iter()
+        .flatten()
+        .flat_map(|dir| variants(dir))
+        .collect();
+    if !available.is_empty() {
+        available.sort();
+        available.dedup();
+        anyhow::bail!(
+            "Template '{}' has no variant '{}' (available: {})",
+            name,
+            variant,
+            available.join(", ")
+        );
+    }
+
+    anyhow::bail!(
+        "Template '{}' not found in templates/, {} or the standard templates",
+        name,
+        get_user_templates_dir()
+            .map(|dir| format!("{}/", dir.display()))
+            .unwrap_or_else(|| "the user templates directory".to_string())
+    )
+}
+
+/// Directory of template `name` in resolution order, with where it was found
+///
+/// Unlike `resolve_template` this does not copy user-global templates.
+pub fn find_template(name: &str) -> Option<(&'static str, PathBuf)> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return None;
+    }
+    let candidates = [
+        ("project", Some(get_custom_templates_dir())),
+        ("user", get_user_templates_dir()),
+        ("standard", Some(get_local_templates_dir())),
+    ];
+    candidates.into_iter().find_map(|(source, dir)| {
+        let dir = dir?.join(name);
+        dir.is_dir().then_some((source, dir))
+    })
+}
+
+/// Point relative imports of the shared components at the standard templates
+///
+/// Standard templates import `../common/…`, which only exists next to them in
+/// .docgen/templates/. Copies elsewhere use the root-relative path instead.
+pub fn rewrite_common_imports(source: &str) -> String {
+    source.replace("\"../common/", "\"/.docgen/templates/common/")
+}
+
 /// Get the current docgen version
 pub fn get_docgen_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
@@ -41,6 +260,7 @@ pub fn get_available_templates() -> Vec<String> {
         "proposal".to_string(),
         "quotation-request".to_string(),
         "reminder".to_string(),
+        "report".to_string(),
         "sla".to_string(),
         "specification".to_string(),
         "task-list".to_string(),
@@ -48,35 +268,337 @@ pub fn get_available_templates() -> Vec<String> {
     ]
 }
 
+/// Hashes of the files last extracted to .docgen/templates/
+pub const STAMP_PATH: &str = ".docgen/template-stamp.json";
+
+/// Hashes of the files copied to .docgen/user-templates/
+const USER_STAMP_PATH: &str = ".docgen/user-template-stamp.json";
+
+static KEEP_LOCAL: AtomicBool = AtomicBool::new(false);
+
+/// Keep locally modified files in .docgen/templates/ instead of restoring them
+pub fn set_keep_local(keep: bool) {
+    KEEP_LOCAL.store(keep, Ordering::Relaxed);
+}
+
+/// docgen version and SHA-256 per file (relative to .docgen/templates/)
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Stamp {
+    version: String,
+    files: BTreeMap<String, String>,
+}
+
+/// What a template sync changed
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Files written because they were missing or outdated
+    pub written: usize,
+    /// Locally modified files that were overwritten
+    pub restored: Vec<String>,
+    /// Locally modified files that were deleted because they are no longer shipped
+    pub deleted: Vec<String>,
+    /// Locally modified files that were kept (`--keep-local`)
+    pub kept: Vec<String>,
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Embedded template files by path relative to the templates directory
+fn embedded_files() -> BTreeMap<String, Vec<u8>> {
+    fn collect(dir: &'static Dir, files: &mut BTreeMap<String, &'static [u8]>) {
+        for file in dir.files() {
+            files.insert(
+                file.path().to_string_lossy().replace('\\', "/"),
+                file.contents(),
+            );
+        }
+        for subdir in dir.dirs() {
+            collect(subdir, files);
+        }
+    }
+
+    let mut files = BTreeMap::new();
+    for name in get_available_templates() {
+        if let Some(dir) = TEMPLATES_DIR.get_dir(&name) {
+            collect(dir, &mut files);
+        }
+    }
+    files
+        .into_iter()
+        .map(|(path, contents)| (path, contents.to_vec()))
+        .collect()
+}
+
+/// A project or user-global replacement of a shared component
+#[derive(Debug, PartialEq)]
+pub struct Override {
+    /// Path relative to the templates directory, e.g. "common/footers.typ"
+    pub path: String,
+    /// File that replaces the embedded component
+    pub source: PathBuf,
+    /// Whether an embedded component of that name exists (else it is added)
+    pub replaces: bool,
+}
+
+/// Component overrides from `common/` of the user-global and project templates
+///
+/// Project files win over user-global ones of the same name.
+pub fn component_overrides() -> Vec<Override> {
+    let embedded = embedded_files();
+    let mut overrides: BTreeMap<String, PathBuf> = BTreeMap::new();
+    let dirs = [get_user_templates_dir(), Some(get_custom_templates_dir())];
+    for common in dirs.into_iter().flatten().map(|dir| dir.join(COMMON_DIR)) {
+        for entry in WalkDir::new(&common)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let Ok(relative) = entry.path().strip_prefix(&common) else {
+                continue;
+            };
+            let path = format!(
+                "{}/{}",
+                COMMON_DIR,
+                relative.to_string_lossy().replace('\\', "/")
+            );
+            overrides.insert(path, entry.into_path());
+        }
+    }
+    overrides
+        .into_iter()
+        .map(|(path, source)| Override {
+            replaces: embedded.contains_key(&path),
+            path,
+            source,
+        })
+        .collect()
+}
+
+/// The standard templates as compiled: embedded files with overrides applied
+fn effective_files() -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = embedded_files();
+    for component in component_overrides() {
+        let contents = fs::read(&component.source)
+            .with_context(|| format!("Failed to read override {}", component.source.display()))?;
+        files.insert(component.path, contents);
+    }
+    Ok(files)
+}
+
+/// User-global templates as copied into the project, by path relative to
+/// .docgen/user-templates/
+///
+/// `common/` holds component overrides and is applied to the standard
+/// templates instead. Imports of the shared components are rewritten like
+/// `copy_template` does.
+fn user_template_files() -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    let Some(dir) = get_user_templates_dir().filter(|dir| dir.is_dir()) else {
+        return Ok(files);
+    };
+    for entry in WalkDir::new(&dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() != 1 || e.file_name() != COMMON_DIR)
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let Ok(relative) = entry.path().strip_prefix(&dir) else {
+            continue;
+        };
+        let path = relative.to_string_lossy().replace('\\', "/");
+        let contents = fs::read(entry.path())
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        let contents = match std::str::from_utf8(&contents) {
+            Ok(text) if path.ends_with(".typ") => rewrite_common_imports(text).into_bytes(),
+            _ => contents,
+        };
+        files.insert(path, contents);
+    }
+    Ok(files)
+}
+
+/// Files of the embedded template `name` as a fork would contain them
+///
+/// Paths are relative to the template directory; `.typ` files have their
+/// imports of the shared components rewritten like `fork_template` does.
+pub fn fork_files(name: &str) -> Option<BTreeMap<String, Vec<u8>>> {
+    TEMPLATES_DIR.get_dir(name)?;
+    let prefix = format!("{}/", name);
+    let files = embedded_files()
+        .into_iter()
+        .filter_map(|(path, contents)| {
+            let relative = path.strip_prefix(&prefix)?.to_string();
+            let contents = match std::str::from_utf8(&contents) {
+                Ok(text) if relative.ends_with(".typ") => rewrite_common_imports(text).into_bytes(),
+                _ => contents,
+            };
+            Some((relative, contents))
+        })
+        .collect();
+    Some(files)
+}
+
 /// Ensure .docgen/templates/ is up-to-date with current docgen version
 /// This is called automatically on every compile/build
+///
+/// Only missing or outdated files are written. Component overrides from
+/// templates/common/ replace the embedded files of the same name. Files
+/// edited in place are restored with a warning, or kept if
+/// `set_keep_local(true)` was called.
+///
+/// User-global templates are copied to .docgen/user-templates/ the same
+/// way, so compiles only read them. Edits to that copy are always replaced.
 pub fn ensure_local_templates_updated() -> Result<()> {
-    let templates_dir = get_local_templates_dir();
+    let report = sync_templates(
+        &get_local_templates_dir(),
+        Path::new(STAMP_PATH),
+        &effective_files()?,
+        KEEP_LOCAL.load(Ordering::Relaxed),
+    )?;
+
+    let user_files = user_template_files()?;
+    if !user_files.is_empty() || Path::new(USER_STAMP_PATH).exists() {
+        sync_templates(
+            &get_user_templates_cache_dir(),
+            Path::new(USER_STAMP_PATH),
+            &user_files,
+            false,
+        )?;
+    }
 
-    // Create .docgen/templates if it doesn't exist
-    if !templates_dir.exists() {
-        fs::create_dir_all(&templates_dir)
-            .context("Failed to create .docgen/templates directory")?;
+    if !report.restored.is_empty() {
+        eprintln!(
+            "{} Local changes in .docgen/templates/ were overwritten: {}",
+            "⚠".yellow(),
+            report.restored.join(", ")
+        );
+        eprintln!("  Fork the template (docgen template fork) or pass --keep-local to keep them.");
     }
+    if !report.deleted.is_empty() {
+        eprintln!(
+            "{} Locally modified files that docgen no longer ships were deleted from .docgen/templates/: {}",
+            "⚠".yellow(),
+            report.deleted.join(", ")
+        );
+        eprintln!("  Restore them from version control, or pass --keep-local to keep them.");
+    }
+    if !report.kept.is_empty() {
+        eprintln!(
+            "{} Keeping locally modified templates: {}",
+            "⚠".yellow(),
+            report.kept.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Bring `dir` in line with `files`
+///
+/// A file counts as locally modified if it no longer matches the hash
+/// recorded in the stamp when it was extracted.
+fn sync_templates(
+    dir: &Path,
+    stamp_path: &Path,
+    files: &BTreeMap<String, Vec<u8>>,
+    keep_local: bool,
+) -> Result<SyncReport> {
+    let old: Stamp = fs::read_to_string(stamp_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let mut stamp = Stamp {
+        version: get_docgen_version(),
+        files: BTreeMap::new(),
+    };
+    let mut report = SyncReport::default();
+
+    for (path, contents) in files {
+        let target = dir.join(path);
+        let expected = sha256(contents);
+        let current = fs::read(&target).ok().map(|bytes| sha256(&bytes));
+        let recorded = old.files.get(path);
+        if current.as_ref() == Some(&expected) {
+            stamp.files.insert(path.clone(), expected);
+            continue;
+        }
+
+        // Without a recorded hash (first sync, or a stamp from before
+        // stamps existed) any difference may be a local edit
+        let modified = current.is_some() && current.as_ref() != recorded;
+        if modified && keep_local {
+            report.kept.push(path.clone());
+            // Keep the original hash so the file still counts as modified
+            if let Some(recorded) = recorded {
+                stamp.files.insert(path.clone(), recorded.clone());
+            }
+            continue;
+        }
+        if modified {
+            report.restored.push(path.clone());
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, contents)
+            .with_context(|| format!("Failed to extract template file: {}", path))?;
+        report.written += 1;
+        stamp.files.insert(path.clone(), expected);
+    }
+
+    // Files dropped from the embedded templates or removed overrides, unless edited locally
+    for (path, recorded) in &old.files {
+        if files.contains_key(path) {
+            continue;
+        }
+        let target = dir.join(path);
+        let current = fs::read(&target).ok().map(|bytes| sha256(&bytes));
+        let modified = current.is_some() && current.as_ref() != Some(recorded);
+        if modified && keep_local {
+            report.kept.push(path.clone());
+            stamp.files.insert(path.clone(), recorded.clone());
+            continue;
+        }
+        if modified {
+            report.deleted.push(path.clone());
+        }
+        if current.is_some() {
+            fs::remove_file(&target)?;
+        }
+    }
+
+    if stamp != old {
+        if let Some(parent) = stamp_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(stamp_path, serde_json::to_string_pretty(&stamp)?)?;
+    }
+    Ok(report)
+}
 
-    // Extract embedded templates to .docgen/templates/
+/// Delete and re-extract all standard templates (`template update --force`)
+pub fn reset_local_templates() -> Result<()> {
+    let templates_dir = get_local_templates_dir();
     for template_name in get_available_templates() {
         let dest = templates_dir.join(&template_name);
-
-        // Remove existing template if it exists
         if dest.exists() {
             fs::remove_dir_all(&dest)
                 .with_context(|| format!("Failed to remove old template: {}", template_name))?;
         }
-
-        // Get template from embedded resources
         if let Some(template_dir) = TEMPLATES_DIR.get_dir(&template_name) {
-            // Extract template directory
             extract_embedded_dir(template_dir, &dest)
                 .with_context(|| format!("Failed to extract template: {}", template_name))?;
         }
     }
-
+    let _ = fs::remove_file(STAMP_PATH);
+    sync_templates(
+        &templates_dir,
+        Path::new(STAMP_PATH),
+        &effective_files()?,
+        false,
+    )?;
     Ok(())
 }
 
@@ -121,6 +643,25 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Copy a template directory, rewriting its imports of the shared components
+pub fn copy_template(src: &Path, dst: &Path) -> Result<()> {
+    copy_dir_recursive(src, dst)?;
+
+    for entry in WalkDir::new(dst)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "typ"))
+    {
+        let content = fs::read_to_string(entry.path())?;
+        let rewritten = rewrite_common_imports(&content);
+        if rewritten != content {
+            fs::write(entry.path(), rewritten)?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Fork a standard template to custom templates directory
 pub fn fork_template(template_name: &str, custom_name: &str) -> Result<()> {
     let source = get_local_templates_dir().join(template_name);
@@ -147,13 +688,16 @@ pub fn fork_template(template_name: &str, custom_name: &str) -> Result<()> {
     }
 
     // Copy template
-    copy_dir_recursive(&source, &dest).with_context(|| {
+    copy_template(&source, &dest).with_context(|| {
         format!(
             "Failed to fork template '{}' to '{}'",
             template_name, custom_name
         )
     })?;
 
+    // Remember what the fork started from for `template diff` and `upgrade`
+    forks::record(&dest, template_name, &forks::read_files(&dest)?)?;
+
     Ok(())
 }
 
@@ -202,4 +746,268 @@ mod tests {
         let dir = get_custom_templates_dir();
         assert_eq!(dir, PathBuf::from("templates"));
     }
+
+    #[test]
+    fn test_rewrite_common_imports() {
+        let source = "#import \"../common/footers.typ\": accounting-footer\n\
+                      #import \"helpers.typ\": *\n";
+        assert_eq!(
+            rewrite_common_imports(source),
+            "#import \"/.docgen/templates/common/footers.typ\": accounting-footer\n\
+             #import \"helpers.typ\": *\n"
+        );
+    }
+
+    #[test]
+    fn test_resolve_template_rejects_paths() {
+        assert!(resolve_template("../invoice", DEFAULT_VARIANT).is_err());
+        assert!(resolve_template("a/b", DEFAULT_VARIANT).is_err());
+        assert!(resolve_template("", DEFAULT_VARIANT).is_err());
+        assert!(resolve_template("invoice", "../default").is_err());
+    }
+
+    #[test]
+    fn test_variant_names_default_first() {
+        let files = [
+            "minimal.typ",
+            "_helpers.typ",
+            "default.typ",
+            "a.typ",
+            "x.typ.backup",
+        ]
+        .iter()
+        .map(PathBuf::from);
+        assert_eq!(variant_names(files), vec!["default", "a", "minimal"]);
+    }
+
+    #[test]
+    fn test_standard_templates_have_default_variant() {
+        let templates = standard_templates();
+        assert!(!templates.iter().any(|t| t.name == "common"));
+        for template in &templates {
+            assert_eq!(template.variants[0], DEFAULT_VARIANT, "{}", template.name);
+        }
+        let invoice = templates.iter().find(|t| t.name == "invoice").unwrap();
+        assert!(invoice.variants.contains(&"minimal".to_string()));
+    }
+
+    /// Paths of required fields missing from `value`
+    fn missing_required(
+        schema: &serde_json::Value,
+        value: &serde_json::Value,
+        path: &str,
+    ) -> Vec<String> {
+        let mut missing = Vec::new();
+        if let Some(items) = schema.get("items") {
+            for (i, item) in value.as_array().into_iter().flatten().enumerate() {
+                missing.extend(missing_required(items, item, &format!("{}[{}]", path, i)));
+            }
+        }
+        let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) else {
+            return missing;
+        };
+        for key in schema["required"].as_array().into_iter().flatten() {
+            let key = key.as_str().unwrap();
+            if value.get(key).is_none() {
+                missing.push(format!("{}.{}", path, key));
+            }
+        }
+        for (key, property) in properties {
+            if let Some(child) = value.get(key) {
+                missing.extend(missing_required(
+                    property,
+                    child,
+                    &format!("{}.{}", path, key),
+                ));
+            }
+        }
+        missing
+    }
+
+    /// `data.a.b` paths a template reads, without method calls like `.len()`
+    fn data_paths(source: &str) -> Vec<String> {
+        let mut paths = Vec::new();
+        for (start, _) in source.match_indices("data.") {
+            let before = source[..start].chars().next_back();
+            if before.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+                continue;
+            }
+            let rest = &source[start + "data.".len()..];
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            let mut path = rest[..end].trim_end_matches('.');
+            if rest[end..].starts_with('(') {
+                path = path.rsplit_once('.').map_or("", |(object, _)| object);
+            }
+            if !path.is_empty() {
+                paths.push(path.to_string());
+            }
+        }
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Whether `schema` declares `path`; objects without `properties` accept anything
+    fn declares(schema: &serde_json::Value, path: &str) -> bool {
+        let mut node = schema;
+        for key in path.split('.') {
+            let Some(properties) = node.get("properties") else {
+                return true;
+            };
+            match properties.get(key) {
+                Some(child) => node = child,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn test_data_paths() {
+        let source = "#let x = data.metadata.show_footer\n\
+                      #if \"items\" in data and data.items.len() > 0 []\n\
+                      #title-data.name #meta-data.x #data.totals.total.";
+        assert_eq!(
+            data_paths(source),
+            vec!["items", "metadata.show_footer", "totals.total"]
+        );
+        let schema = serde_json::json!({"properties": {
+            "metadata": {"properties": {"title": {}}},
+            "recipient": {"type": "object"}
+        }});
+        assert!(declares(&schema, "metadata.title"));
+        assert!(declares(&schema, "recipient.name"));
+        assert!(!declares(&schema, "metadata.show_footer"));
+    }
+
+    #[test]
+    fn test_standard_templates_ship_metadata() {
+        let read = |path: String| -> serde_json::Value {
+            let file = TEMPLATES_DIR
+                .get_file(&path)
+                .unwrap_or_else(|| panic!("{}", path));
+            serde_json::from_slice(file.contents()).unwrap_or_else(|e| panic!("{}: {}", path, e))
+        };
+        for template in standard_templates() {
+            let name = &template.name;
+            let manifest: crate::packages::Manifest =
+                serde_json::from_value(read(format!("{}/template.json", name))).unwrap();
+            assert_eq!(&manifest.name, name);
+            assert!(manifest.description.is_some(), "{}", name);
+            assert!(!manifest.locales.is_empty(), "{}", name);
+
+            let schema = read(format!("{}/{}", name, manifest.schema.unwrap()));
+            let sample = read(format!("{}/{}", name, manifest.sample.unwrap()));
+            assert!(!crate::schema::fields(&schema).is_empty(), "{}", name);
+            let missing = missing_required(&schema, &sample, "");
+            assert!(missing.is_empty(), "{}: sample lacks {:?}", name, missing);
+
+            // Every field the variants read is documented in the schema
+            let dir = TEMPLATES_DIR.get_dir(name).unwrap();
+            let undeclared: Vec<String> = dir
+                .files()
+                .filter(|f| f.path().extension().is_some_and(|e| e == "typ"))
+                .flat_map(|f| data_paths(f.contents_utf8().unwrap()))
+                .filter(|path| !declares(&schema, path))
+                .collect();
+            assert!(
+                undeclared.is_empty(),
+                "{}: schema lacks {:?}",
+                name,
+                undeclared
+            );
+        }
+    }
+
+    #[test]
+    fn test_configured_variant_prefers_client_setting() {
+        let company = serde_json::json!({
+            "templates": {
+                "variant": {"invoice": "minimal", "offer": "detailed"},
+                "clients": {"K-003": {"variant": {"invoice": "english-letterhead"}}}
+            }
+        });
+        let variant = |template, customer| configured_variant(&company, template, customer);
+        assert_eq!(
+            variant("invoice", Some("K-003")).unwrap(),
+            "english-letterhead"
+        );
+        assert_eq!(variant("offer", Some("K-003")).unwrap(), "detailed");
+        assert_eq!(variant("invoice", Some("K-001")).unwrap(), "minimal");
+        assert_eq!(variant("invoice", None).unwrap(), "minimal");
+        assert_eq!(variant("letter", None), None);
+        assert_eq!(
+            configured_variant(&serde_json::json!({}), "invoice", None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sync_only_writes_changed_files() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path().join("templates");
+        let stamp = tmp.path().join("stamp.json");
+
+        let report = sync_templates(&dir, &stamp, &embedded_files(), false).unwrap();
+        assert_eq!(report.written, embedded_files().len());
+        assert!(dir.join("invoice/default.typ").exists());
+
+        let report = sync_templates(&dir, &stamp, &embedded_files(), false).unwrap();
+        assert_eq!(report.written, 0);
+
+        // Deleted files come back without a warning
+        fs::remove_file(dir.join("offer/default.typ")).unwrap();
+        let report = sync_templates(&dir, &stamp, &embedded_files(), false).unwrap();
+        assert_eq!(report.written, 1);
+        assert!(report.restored.is_empty());
+    }
+
+    #[test]
+    fn test_sync_detects_local_modifications() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path().join("templates");
+        let stamp = tmp.path().join("stamp.json");
+        sync_templates(&dir, &stamp, &embedded_files(), false).unwrap();
+
+        let invoice = dir.join("invoice/default.typ");
+        fs::write(&invoice, "// edited").unwrap();
+        let report = sync_templates(&dir, &stamp, &embedded_files(), true).unwrap();
+        assert_eq!(report.kept, vec!["invoice/default.typ"]);
+        assert_eq!(fs::read_to_string(&invoice).unwrap(), "// edited");
+
+        // Still reported as modified on the next run, and restored without --keep-local
+        let report = sync_templates(&dir, &stamp, &embedded_files(), false).unwrap();
+        assert_eq!(report.restored, vec!["invoice/default.typ"]);
+        assert_ne!(fs::read_to_string(&invoice).unwrap(), "// edited");
+    }
+
+    #[test]
+    fn test_sync_reports_edits_without_stamp_and_deleted_files() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path().join("templates");
+        let stamp = tmp.path().join("stamp.json");
+
+        // Extracted by an older docgen without a stamp, then edited
+        let invoice = dir.join("invoice/default.typ");
+        fs::create_dir_all(invoice.parent().unwrap()).unwrap();
+        fs::write(&invoice, "// edited").unwrap();
+        let report = sync_templates(&dir, &stamp, &embedded_files(), true).unwrap();
+        assert_eq!(report.kept, vec!["invoice/default.typ"]);
+        let report = sync_templates(&dir, &stamp, &embedded_files(), false).unwrap();
+        assert_eq!(report.restored, vec!["invoice/default.typ"]);
+
+        // A shipped file is dropped from the embedded set after it was edited
+        let mut files = embedded_files();
+        files.remove("offer/default.typ");
+        let offer = dir.join("offer/default.typ");
+        fs::write(&offer, "// edited").unwrap();
+        let report = sync_templates(&dir, &stamp, &files, true).unwrap();
+        assert_eq!(report.kept, vec!["offer/default.typ"]);
+        assert!(offer.exists());
+        let report = sync_templates(&dir, &stamp, &files, false).unwrap();
+        assert_eq!(report.deleted, vec!["offer/default.typ"]);
+        assert!(!offer.exists());
+    }
 }
@@ -1,16 +1,26 @@
+mod build_state;
 mod commands;
 mod data;
+mod discovery;
+mod document_types;
 mod embedded;
 mod encrypt;
+mod forks;
+mod layout;
+mod lint;
 mod local_templates;
 mod locale;
+mod packages;
+mod preview;
+mod render;
+mod schema;
+mod visual;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use walkdir::WalkDir;
 
 // Import new JSON-based stores
 use locale::{t, tf};
@@ -31,7 +41,7 @@ Features:
   • Multi-language support: de, en, es, fr, it, nl, pt
   • Concept and documentation templates with package system
   • Custom branding (colors, fonts, logo)
-  • SQLite database for client/project management
+  • Client/project management in JSON files or SQLite
   • Watch mode for auto-rebuild
   • PDF encryption for sensitive documents
 
@@ -46,6 +56,10 @@ Examples: https://github.com/casoon/typst-business-templates/tree/main/examples
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// Keep local edits in .docgen/templates/ instead of restoring the standard templates
+    #[arg(long, global = true)]
+    keep_local: bool,
 }
 
 #[derive(Subcommand)]
@@ -77,29 +91,49 @@ enum Commands {
     ///   docgen compile documents/invoices/2025/RE-2025-001.json
     ///   docgen compile invoice.json -o output/custom.pdf
     ///   docgen compile credentials.json --encrypt
+    ///   docgen compile offer.json --format png --ppi 300 --pages 1-2
     Compile {
         /// Path to JSON or .typ file
         input: PathBuf,
         /// Output PDF path (optional, auto-generated if not specified)
         #[arg(short, long)]
         output: Option<PathBuf>,
-        /// Template type (invoice, offer, credentials, concept, documentation)
-        /// Only needed if auto-detection fails
+        /// Template name: a type (invoice, offer, concept, ...) or a custom template
+        /// from templates/ or ~/.config/docgen/templates/.
+        /// Overrides the document's "template" field and type auto-detection
         #[arg(short, long)]
         template: Option<String>,
+        /// Template variant (default, minimal, ...; see `docgen template list`)
+        /// Overrides the document's "variant" field and company.json
+        #[arg(long)]
+        variant: Option<String>,
         /// Encrypt the PDF with password protection (requires qpdf)
         #[arg(short, long)]
         encrypt: bool,
+        #[command(flatten)]
+        render: render::RenderOptions,
     },
     /// Build all documents in a directory
     ///
-    /// Recursively finds all .json and .typ files and compiles them to PDF.
-    /// Maintains directory structure in output folder.
+    /// Recursively finds all documents and compiles them to PDF. Files listed
+    /// in .docgenignore (gitignore syntax) are skipped, as are JSON files
+    /// without "metadata" and .typ files without a "#show:" rule, unless
+    /// marked with "$docgen": {"type": ...} or "// docgen: type=...".
+    /// Mirrors the directory structure in the output folder unless an output
+    /// pattern is configured; two documents writing the same PDF fail the
+    /// build. Only documents whose
+    /// input, company.json, locale, templates or fonts changed since the last
+    /// build are recompiled (tracked in .docgen/build-state.json).
     ///
     /// Examples:
     ///   docgen build                    # Build all in documents/
     ///   docgen build documents/invoices # Build only invoices
     ///   docgen build -o pdfs            # Custom output directory
+    ///   docgen build -j 4               # Compile 4 documents at a time
+    ///   docgen build --force            # Rebuild everything
+    ///   docgen build --layout "{type}/{year}/{number}.pdf"
+    ///   docgen build --exclude "**/drafts/**"
+    ///   docgen build --format svg       # One SVG per page
     Build {
         /// Directory containing JSON and .typ files
         #[arg(default_value = "documents")]
@@ -107,21 +141,124 @@ enum Commands {
         /// Output directory for PDFs
         #[arg(short, long, default_value = "output")]
         output: PathBuf,
+        /// Number of documents compiled in parallel (default: number of CPUs)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Rebuild all documents, even those that are up to date
+        #[arg(short, long)]
+        force: bool,
+        /// Output path pattern, e.g. "{type}/{year}/{number}.pdf"
+        /// (default: structure.output_pattern in company.json, else "{path}.pdf")
+        #[arg(long)]
+        layout: Option<String>,
+        /// Only build documents matching this glob (repeatable)
+        #[arg(long)]
+        include: Vec<String>,
+        /// Skip documents matching this glob (repeatable)
+        #[arg(long)]
+        exclude: Vec<String>,
+        #[command(flatten)]
+        render: render::RenderOptions,
+    },
+    /// Write a first-page preview image of every document
+    ///
+    /// Renders page 1 of each document as PNG into the output directory,
+    /// using the same layout and discovery rules as build.
+    ///
+    /// Examples:
+    ///   docgen thumbnail
+    ///   docgen thumbnail documents/offers --ppi 48 -o public/previews
+    Thumbnail {
+        /// Directory containing the documents
+        #[arg(default_value = "documents")]
+        path: PathBuf,
+        /// Output directory for the images
+        #[arg(short, long, default_value = "output/thumbnails")]
+        output: PathBuf,
+        /// Resolution in pixels per inch
+        #[arg(long, default_value_t = 72)]
+        ppi: u32,
+        /// Number of documents rendered in parallel (default: number of CPUs)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Only render documents matching this glob (repeatable)
+        #[arg(long)]
+        include: Vec<String>,
+        /// Skip documents matching this glob (repeatable)
+        #[arg(long)]
+        exclude: Vec<String>,
+    },
+    /// Compare rendered documents with committed baseline images
+    ///
+    /// Renders every document in the fixture directory to one PNG per page
+    /// and compares each page with its baseline pixel by pixel. Pages that
+    /// change more than the tolerance fail and get a diff image in
+    /// .docgen/test/ with the changed pixels in red.
+    ///
+    /// Examples:
+    ///   docgen test                     # Fixtures in tests/visual/
+    ///   docgen test --update-baselines  # Accept the current rendering
+    ///   docgen test fixtures --baselines fixtures/expected --tolerance 0.5
+    Test {
+        /// Directory containing the fixture documents
+        #[arg(default_value = "tests/visual")]
+        path: PathBuf,
+        /// Directory of the baseline images (default: baselines/ in the fixture directory)
+        #[arg(long)]
+        baselines: Option<PathBuf>,
+        /// Write the rendered pages as new baselines instead of comparing
+        #[arg(long)]
+        update_baselines: bool,
+        /// Changed pixels allowed per page, in percent
+        #[arg(long, default_value_t = 0.1)]
+        tolerance: f64,
+        /// Resolution in pixels per inch
+        #[arg(long, default_value_t = 72)]
+        ppi: u32,
     },
     /// Watch for changes and rebuild automatically
     ///
-    /// Monitors directory for file changes and automatically recompiles
-    /// documents when .json or .typ files are modified.
+    /// Builds once, then monitors the documents and their dependencies
+    /// (data/company.json, locale/, template forks in templates/, fonts/)
+    /// and rebuilds every affected document into the same output layout as
+    /// build. Changes are collected until editing pauses for a moment.
+    ///
+    /// With --serve, a local preview server lists the documents and shows
+    /// each PDF (or its compile error), reloading the page after every
+    /// rebuild. It only listens on 127.0.0.1.
     ///
     /// Press Ctrl+C to stop watching.
     ///
-    /// Example: docgen watch documents/
+    /// Examples:
+    ///   docgen watch documents/
+    ///   docgen watch --serve --port 3030
     Watch {
         /// Directory to watch for changes
         #[arg(default_value = "documents")]
         path: PathBuf,
+        /// Output directory for PDFs
+        #[arg(short, long, default_value = "output")]
+        output: PathBuf,
+        /// Number of documents compiled in parallel (default: number of CPUs)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Output path pattern, as for build
+        #[arg(long)]
+        layout: Option<String>,
+        /// Only rebuild documents matching this glob (repeatable)
+        #[arg(long)]
+        include: Vec<String>,
+        /// Skip documents matching this glob (repeatable)
+        #[arg(long)]
+        exclude: Vec<String>,
+        /// Serve a live preview on http://127.0.0.1:<port>/
+        #[arg(long)]
+        serve: bool,
+        /// Port of the preview server
+        #[arg(long, default_value_t = 3030)]
+        port: u16,
     },
-    /// Client management (SQLite database)
+    /// Client management
     ///
     /// Manage clients in the local database:
     ///   - list: Show all clients
@@ -133,7 +270,7 @@ enum Commands {
         #[command(subcommand)]
         action: ClientAction,
     },
-    /// Project management (SQLite database)
+    /// Project management
     ///
     /// Manage projects linked to clients:
     ///   - list: Show projects for a client
@@ -164,6 +301,104 @@ enum Commands {
         #[command(subcommand)]
         action: TemplateAction,
     },
+    /// Revenue and business reports
+    ///
+    /// Computed from the invoices and credit notes in documents/:
+    ///   - revenue: Net, VAT and gross revenue of a year
+    ///   - client: All documents of one client with payment status
+    ///   - outstanding: Unpaid invoices (no payment.paid_date)
+    ///   - vat: Figures for the advance VAT return and OSS
+    ///
+    /// Output as terminal table (default), CSV, JSON or PDF.
+    ///
+    /// Examples:
+    ///   docgen report revenue --year 2025 --by client
+    ///   docgen report client K-001 --format pdf
+    ///   docgen report outstanding --format csv -o outstanding.csv
+    Report {
+        #[command(subcommand)]
+        action: ReportAction,
+    },
+    /// Export bookings for accounting
+    ///
+    /// Builds booking lines from the issued invoices and credit notes:
+    ///   - datev: DATEV Buchungsstapel (EXTF CSV) for the tax advisor
+    ///   - journal: Generic CSV journal
+    ///
+    /// Revenue accounts (SKR03/SKR04) and DATEV numbers are configured in
+    /// the "accounting" block of data/company.json.
+    ///
+    /// Examples:
+    ///   docgen export datev --year 2025 --month 03
+    ///   docgen export journal --year 2025
+    Export {
+        #[command(subcommand)]
+        action: ExportAction,
+    },
+    /// Storage backend for clients, projects and counters
+    ///
+    /// Data is kept either in JSON files (default) or in an SQLite database
+    /// (data/docgen.db). The backend is selected in the "storage" block of
+    /// data/company.json.
+    ///
+    /// Examples:
+    ///   docgen db info
+    ///   docgen db migrate --to sqlite
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+    /// Upgrade project files from older docgen versions
+    ///
+    /// Brings data files (clients, projects, counters, company.json) to the
+    /// current format version step by step and moves pre-0.5 template
+    /// copies out of templates/. Every changed file is backed up to
+    /// .docgen/backups/ first.
+    ///
+    /// Examples:
+    ///   docgen migrate --dry-run
+    ///   docgen migrate
+    Migrate {
+        /// Show what would change without touching any file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Issue a document and record it in the GoBD journal
+    ///
+    /// Compiles the document (unless --pdf is given) and appends the SHA-256
+    /// of its JSON and PDF to the hash-chained journal data/journal.jsonl.
+    /// Issued documents must not be edited afterwards; correct them with a
+    /// credit note instead.
+    ///
+    /// Examples:
+    ///   docgen issue documents/invoices/2025/RE-2025-001.json
+    ///   docgen issue documents/invoices/RE-2025-002.json --pdf sent/RE-2025-002.pdf
+    Issue {
+        /// Path to the JSON document
+        input: PathBuf,
+        /// Record an existing PDF instead of compiling one
+        #[arg(long)]
+        pdf: Option<PathBuf>,
+        /// Template type, only needed if auto-detection fails
+        #[arg(short, long)]
+        template: Option<String>,
+    },
+    /// Check issued documents against the GoBD journal
+    ///
+    /// Verifies the hash chain of data/journal.jsonl and reports:
+    ///   - issued documents that were edited or deleted
+    ///   - PDFs that no longer match their recorded hash
+    ///   - gaps and duplicates in the number sequences
+    ///   - counters that disagree with the issued numbers
+    ///
+    /// Exits with an error if anything was found.
+    ///
+    /// Example: docgen audit
+    Audit {
+        /// Directory scanned for documents sharing a number
+        #[arg(default_value = "documents")]
+        path: PathBuf,
+    },
     /// Show AI assistant guide (detailed documentation for LLMs)
     ///
     /// Displays comprehensive documentation designed for AI assistants like
@@ -220,6 +455,51 @@ enum ClientAction {
         /// Client number (e.g., 1) or K-number (e.g., K-001)
         id: String,
     },
+    /// Import clients from CSV, vCard or JSON
+    ///
+    /// Every imported client gets the next K-number. Clients whose email or
+    /// company name already exists are skipped with a warning.
+    /// CSV headers are matched by name (English or German); map other
+    /// columns with --map.
+    ///
+    /// Examples:
+    ///   docgen client import kunden.csv --dry-run
+    ///   docgen client import export.csv --map "Firma=company,Ort=city"
+    ///   docgen client import contacts.vcf
+    Import {
+        /// File to import
+        file: PathBuf,
+
+        /// Input format (default: from file extension)
+        #[arg(short, long, value_enum)]
+        format: Option<commands::client::ContactFormat>,
+
+        /// Column mapping for CSV: "Column=field,..."
+        #[arg(short, long)]
+        map: Option<String>,
+
+        /// Show what would be imported without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Import clients even if they look like duplicates
+        #[arg(long)]
+        allow_duplicates: bool,
+    },
+    /// Export all clients as CSV, vCard or JSON
+    ///
+    /// Examples:
+    ///   docgen client export --format csv -o clients.csv
+    ///   docgen client export --format vcf > contacts.vcf
+    Export {
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "csv")]
+        format: commands::client::ContactFormat,
+
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -259,6 +539,157 @@ enum ProjectAction {
     },
 }
 
+#[derive(Subcommand)]
+enum ReportAction {
+    /// Revenue of a year, credit notes deducted
+    ///
+    /// Examples:
+    ///   docgen report revenue --year 2025
+    ///   docgen report revenue --year 2025 --by project --format json
+    Revenue {
+        /// Fiscal year
+        #[arg(short, long)]
+        year: i32,
+        /// Group rows by month, client or project
+        #[arg(long, value_enum, default_value = "month")]
+        by: commands::report::GroupBy,
+        /// Directory containing the issued documents
+        #[arg(long, default_value = "documents")]
+        path: PathBuf,
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: commands::report::ReportFormat,
+        /// Output file (stdout for CSV/JSON, output/reports/ for PDF if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// All invoices and credit notes of one client
+    ///
+    /// Example: docgen report client K-001
+    Client {
+        /// Client number (e.g., 1) or K-number (e.g., K-001)
+        id: String,
+        /// Directory containing the issued documents
+        #[arg(long, default_value = "documents")]
+        path: PathBuf,
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: commands::report::ReportFormat,
+        /// Output file (stdout for CSV/JSON, output/reports/ for PDF if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Figures for the advance VAT return (UStVA) and OSS
+    ///
+    /// Net turnover and VAT per rate, reverse-charge and intra-community
+    /// supplies separated, plus B2C sales to other EU countries per
+    /// destination country. Rates are read from vat_breakdown; use the
+    /// rate code "ReverseCharge" or "IntraCommunity" for exempt supplies.
+    ///
+    /// Examples:
+    ///   docgen report vat --period 2025-Q1
+    ///   docgen report vat --period 2025-03 --basis cash
+    Vat {
+        /// Period: YYYY, YYYY-Q1..Q4 or YYYY-MM
+        #[arg(short, long)]
+        period: commands::report::Period,
+        /// Accrual (invoice date) or cash (payment date) basis
+        #[arg(short, long, value_enum, default_value = "accrual")]
+        basis: commands::report::Basis,
+        /// Directory containing the issued documents
+        #[arg(long, default_value = "documents")]
+        path: PathBuf,
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: commands::report::ReportFormat,
+        /// Output file (stdout for CSV/JSON, output/reports/ for PDF if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Unpaid invoices with days overdue
+    ///
+    /// Invoices count as paid once they carry payment.paid_date.
+    ///
+    /// Example: docgen report outstanding
+    Outstanding {
+        /// Directory containing the issued documents
+        #[arg(long, default_value = "documents")]
+        path: PathBuf,
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: commands::report::ReportFormat,
+        /// Output file (stdout for CSV/JSON, output/reports/ for PDF if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Show the active storage backend and record counts
+    Info,
+    /// Convert data to another storage backend
+    ///
+    /// Copies all clients, projects and counters (keeping IDs and numbers)
+    /// and switches "storage.backend" in data/company.json. The previous
+    /// data is left in place.
+    ///
+    /// Examples:
+    ///   docgen db migrate --to sqlite
+    ///   docgen db migrate --to json
+    Migrate {
+        /// Target backend
+        #[arg(long, value_enum)]
+        to: data::Backend,
+
+        /// Overwrite data already present in the target backend
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportAction {
+    /// DATEV booking batch (Buchungsstapel CSV)
+    ///
+    /// One booking per VAT line: debtor account (derived from the client
+    /// number) against the revenue account for the rate. The PDF file name
+    /// is attached as document reference.
+    ///
+    /// Example: docgen export datev --year 2025 --month 03
+    Datev {
+        /// Fiscal year
+        #[arg(short, long)]
+        year: i32,
+        /// Month (1-12), whole year if omitted
+        #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..=12))]
+        month: Option<u32>,
+        /// Directory containing the issued documents
+        #[arg(long, default_value = "documents")]
+        path: PathBuf,
+        /// Output file (default: output/exports/EXTF_Buchungsstapel_<period>.csv)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Generic CSV journal of all bookings
+    ///
+    /// Example: docgen export journal --year 2025 --month 03
+    Journal {
+        /// Fiscal year
+        #[arg(short, long)]
+        year: i32,
+        /// Month (1-12), whole year if omitted
+        #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..=12))]
+        month: Option<u32>,
+        /// Directory containing the issued documents
+        #[arg(long, default_value = "documents")]
+        path: PathBuf,
+        /// Output file (default: output/exports/journal-<period>.csv)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
 #[derive(Subcommand)]
 enum TemplateAction {
     /// Initialize project templates
@@ -286,19 +717,152 @@ enum TemplateAction {
         name: String,
     },
 
+    /// Scaffold a template for a new document type
+    ///
+    /// Generates default.typ (using the shared header and footer), a schema,
+    /// a sample document and labels for all languages, and registers the
+    /// type for type detection, numbering and documents/ directories.
+    ///
+    /// Examples:
+    ///   docgen template new maintenance-report --layout accounting --title Wartungsbericht --prefix WB
+    ///   docgen template new acceptance-certificate --layout document --global
+    New {
+        /// Type name (lowercase letters, digits and '-')
+        name: String,
+
+        /// Page layout
+        #[arg(short, long, value_enum, default_value = "accounting")]
+        layout: document_types::Layout,
+
+        /// Document title (default: from the name)
+        #[arg(short, long)]
+        title: Option<String>,
+
+        /// Number prefix, e.g. WB for WB-2025-001 (default: initials of the name)
+        #[arg(short, long)]
+        prefix: Option<String>,
+
+        /// Create in the user-global templates directory, for all projects
+        #[arg(short, long)]
+        global: bool,
+    },
+
+    /// Show upstream changes since a custom template was forked
+    ///
+    /// Compares the fork's recorded base with the standard template of this
+    /// docgen version.
+    ///
+    /// Example: docgen template diff custom-invoice
+    Diff {
+        /// Custom template in templates/
+        name: String,
+    },
+
+    /// Merge upstream changes into a custom template
+    ///
+    /// Three-way merge of the fork's base, your changes and the current
+    /// standard template. Collisions are left as conflict markers.
+    ///
+    /// Example: docgen template upgrade custom-invoice
+    Upgrade {
+        /// Custom template in templates/
+        name: String,
+    },
+
     /// List available templates
     ///
     /// Shows standard templates (.docgen/templates/) and custom templates (templates/)
     ///
-    /// Example: docgen template list
-    List,
+    /// Examples:
+    ///   docgen template list
+    ///   docgen template list --installed
+    List {
+        /// Only show installed template packages with their versions
+        #[arg(long)]
+        installed: bool,
+    },
+
+    /// Show a template's data fields, variants and locales
+    ///
+    /// Reads the template.json and schema.json shipped with the template.
+    ///
+    /// Examples:
+    ///   docgen template info invoice
+    ///   docgen template info invoice --sample > documents/invoices/RE-2025-002.json
+    Info {
+        /// Template name (standard, custom or installed)
+        name: String,
+
+        /// Print the template's sample document as JSON
+        #[arg(long)]
+        sample: bool,
+    },
+
+    /// Compile templates against their sample and edge cases
+    ///
+    /// Renders each variant with the template's sample, with optional
+    /// fields removed, with empty item lists, with very long descriptions
+    /// and in all seven languages. Reports failures, unexpected extra pages
+    /// (long descriptions may add one per text) and typst warnings. Without a name, all templates are checked.
+    ///
+    /// Examples:
+    ///   docgen template lint
+    ///   docgen template lint custom-invoice
+    Lint {
+        /// Template name (standard, custom or installed)
+        name: Option<String>,
+    },
+
+    /// Install a template package (a directory with a template.json manifest)
+    ///
+    /// Installs into templates/, or the user-global templates directory with
+    /// --global. The source can be a directory, a .tar.gz archive or a git
+    /// URL with an optional #subdirectory.
+    ///
+    /// Examples:
+    ///   docgen template install ../company-templates/acme-invoice
+    ///   docgen template install acme-invoice-1.2.0.tar.gz --global
+    ///   docgen template install https://github.com/acme/templates.git#invoice
+    Install {
+        /// Directory, .tar.gz archive or git URL
+        source: String,
+
+        /// Install for all projects of this user (~/.config/docgen/templates/)
+        #[arg(short, long)]
+        global: bool,
+
+        /// Replace an installed package of the same name
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Remove an installed template package
+    ///
+    /// Example: docgen template uninstall acme-invoice
+    Uninstall {
+        /// Package name from its template.json
+        name: String,
+
+        /// Remove from the user-global templates directory
+        #[arg(short, long)]
+        global: bool,
+    },
 
     /// Update standard templates to current version
     ///
     /// Manually trigger update of .docgen/templates/ (normally auto-updated)
     ///
-    /// Example: docgen template update
-    Update,
+    /// Only changed files are rewritten. Locally edited files are restored
+    /// with a warning, or kept with --keep-local.
+    ///
+    /// Examples:
+    ///   docgen template update
+    ///   docgen template update --force
+    Update {
+        /// Delete and re-extract all standard templates, including local edits
+        #[arg(short, long)]
+        force: bool,
+    },
 }
 
 fn main() -> Result<()> {
@@ -306,6 +870,7 @@ fn main() -> Result<()> {
     locale::init();
 
     let cli = Cli::parse();
+    local_templates::set_keep_local(cli.keep_local);
 
     match cli.command {
         // No command = show help (interactive mode removed for simplification)
@@ -319,6 +884,7 @@ fn main() -> Result<()> {
             println!("  client list        List all clients");
             println!("  client add         Add new client");
             println!("  project list       List projects for client");
+            println!("  report revenue     Revenue report for a year");
             println!("  template init      Initialize project templates");
             println!("\nFor more help: docgen --help");
             Ok(())
@@ -328,13 +894,104 @@ fn main() -> Result<()> {
             input,
             output,
             template,
+            variant,
             encrypt,
-        }) => compile_document(&input, output, template, encrypt),
-        Some(Commands::Build { path, output }) => build_all(&path, &output),
-        Some(Commands::Watch { path }) => watch_directory(&path),
+            render,
+        }) => compile_document(&input, output, template, variant, encrypt, &render),
+        Some(Commands::Build {
+            path,
+            output,
+            jobs,
+            force,
+            layout,
+            include,
+            exclude,
+            render,
+        }) => commands::build::run(
+            &path,
+            &output,
+            commands::build::BuildOptions {
+                jobs,
+                force,
+                layout,
+                include,
+                exclude,
+                render,
+            },
+        ),
+        Some(Commands::Thumbnail {
+            path,
+            output,
+            ppi,
+            jobs,
+            include,
+            exclude,
+        }) => commands::build::run(
+            &path,
+            &output,
+            commands::build::BuildOptions {
+                jobs,
+                force: false,
+                layout: None,
+                include,
+                exclude,
+                render: render::RenderOptions {
+                    format: render::OutputFormat::Png,
+                    ppi: Some(ppi),
+                    pages: Some("1".to_string()),
+                },
+            },
+        ),
+        Some(Commands::Test {
+            path,
+            baselines,
+            update_baselines,
+            tolerance,
+            ppi,
+        }) => commands::test::run(
+            &path,
+            commands::test::TestOptions {
+                baselines,
+                update_baselines,
+                tolerance,
+                ppi,
+            },
+        ),
+        Some(Commands::Watch {
+            path,
+            output,
+            jobs,
+            layout,
+            include,
+            exclude,
+            serve,
+            port,
+        }) => commands::watch::run(
+            &path,
+            &output,
+            commands::build::BuildOptions {
+                jobs,
+                force: false,
+                layout,
+                include,
+                exclude,
+                render: Default::default(),
+            },
+            serve.then_some(port),
+        ),
         Some(Commands::Client { action }) => commands::client::handle(action),
         Some(Commands::Project { action }) => commands::project::handle(action),
         Some(Commands::Template { action }) => commands::template::handle(action),
+        Some(Commands::Report { action }) => commands::report::handle(action),
+        Some(Commands::Export { action }) => commands::export::handle(action),
+        Some(Commands::Db { action }) => commands::db::handle(action),
+        Some(Commands::Migrate { dry_run }) => commands::migrate::run(dry_run),
+        Some(Commands::Issue {
+            input,
+            pdf,
+            template,
+        }) => commands::audit::issue(&input, pdf, template),
+        Some(Commands::Audit { path }) => commands::audit::audit(&path),
         Some(Commands::AiGuide) => show_ai_guide(),
     }
 }
@@ -380,67 +1037,21 @@ fn init_project(name: &str) -> Result<()> {
     for dir in dirs {
         std::fs::create_dir_all(base.join(dir))?;
     }
+    // Document types from user-global templates
+    for doc_type in document_types::user_types() {
+        std::fs::create_dir_all(
+            base.join("documents")
+                .join(document_types::directory(&doc_type))
+                .join(&current_year),
+        )?;
+    }
 
     // Create company.json with language field
-    let company = r##"{
-  "name": "Your Company",
-  "language": "en",
-  "address": {
-    "street": "Street",
-    "house_number": "1",
-    "postal_code": "12345",
-    "city": "City",
-    "country": "Country"
-  },
-  "contact": {
-    "phone": "+1 234 567890",
-    "email": "info@example.com",
-    "website": "www.example.com"
-  },
-  "tax_id": "123/456/78901",
-  "vat_id": "XX123456789",
-  "business_owner": "Your Name",
-  "bank_account": {
-    "bank_name": "Bank",
-    "account_holder": "Your Company",
-    "iban": "XX00 0000 0000 0000 0000 00",
-    "bic": "BANKXXXX"
-  },
-  "branding": {
-    "accent_color": "#E94B3C",
-    "primary_color": "#2c3e50",
-    "font_preset": "inter"
-  },
-  "numbering": {
-    "year_format": "short",
-    "prefixes": {
-      "invoice": "RE",
-      "offer": "AN",
-      "credentials": "ZD",
-      "concept": "KO",
-      "documentation": "DOC"
-    }
-  },
-  "structure": {
-    "organize_by_year": true
-  },
-  "default_terms": {
-    "hourly_rate": "95.00",
-    "currency": "EUR",
-    "payment_days": 14,
-    "warranty_months": 12,
-    "vat_rate": 19,
-    "standard_terms": [
-      "All prices are net plus statutory VAT.",
-      "Payment due within 14 days without deduction.",
-      "Copyright remains with the contractor until full payment."
-    ]
-  }
-}"##;
+    let company = data::company::TEMPLATE;
     std::fs::write(base.join("data/company.json"), company)?;
 
     // Initialize templates using the new v0.5.0 system
-    std::env::set_current_dir(&base)?;
+    std::env::set_current_dir(base)?;
     local_templates::init_project()?;
 
     // Write embedded locales
@@ -451,7 +1062,10 @@ fn init_project(name: &str) -> Result<()> {
     }
 
     // Create .gitignore
-    std::fs::write(base.join(".gitignore"), "output/*.pdf\ndata/docgen.db\n")?;
+    std::fs::write(
+        base.join(".gitignore"),
+        "output/*.pdf\ndata/docgen.db\ndata/*.lock\n",
+    )?;
 
     println!("{} {}", "✓".green(), t("init", "created"));
 
@@ -470,16 +1084,22 @@ fn compile_document(
     input: &Path,
     output: Option<PathBuf>,
     template: Option<String>,
+    variant: Option<String>,
     encrypt: bool,
+    render: &render::RenderOptions,
 ) -> Result<()> {
     if !input.exists() {
         anyhow::bail!("{}: {}", t("compile", "file_not_found"), input.display());
     }
+    render.validate()?;
+    if encrypt && render.format != render::OutputFormat::Pdf {
+        anyhow::bail!("--encrypt only applies to PDF output");
+    }
 
     // Auto-update local templates to current version
     local_templates::ensure_local_templates_updated()?;
 
-    let output_path = output.unwrap_or_else(|| input.with_extension("pdf"));
+    let output_path = output.unwrap_or_else(|| render.output_path(&input.with_extension("pdf")));
 
     println!(
         "{} {}",
@@ -494,24 +1114,50 @@ fn compile_document(
         )
     );
 
+    render_document(input, &output_path, template, variant, render)?;
+    println!("{} {}", "✓".green(), t("compile", "success"));
+
+    // Handle encryption if requested
+    if encrypt {
+        // Check if qpdf is available first
+        encrypt::check_qpdf_available()?;
+
+        println!("{} Encrypting PDF...", "→".blue());
+        let encryption_opts = encrypt::prompt_encryption_options()?;
+        let temp_path = output_path.with_extension("pdf.tmp");
+
+        // Rename original to temp
+        std::fs::rename(&output_path, &temp_path)?;
+
+        // Encrypt temp to final output
+        encrypt::encrypt_pdf(&temp_path, &output_path, encryption_opts)?;
+
+        // Remove temp file
+        std::fs::remove_file(&temp_path)?;
+
+        println!("{} PDF encrypted successfully", "✓".green());
+    }
+
+    Ok(())
+}
+
+/// Run typst for one document without printing anything
+///
+/// Templates must already be synced. Typst's diagnostics are captured and
+/// returned as the error, so parallel builds don't interleave their output.
+fn render_document(
+    input: &Path,
+    output_path: &Path,
+    template: Option<String>,
+    variant: Option<String>,
+    render: &render::RenderOptions,
+) -> Result<()> {
     // Determine company.json and locale paths
     let company_path = "/data/company.json";
-    let company_json_path = Path::new("data/company.json");
-
-    // Read language from company.json to determine locale
-    let locale_path = if company_json_path.exists() {
-        let company_content = std::fs::read_to_string(company_json_path).unwrap_or_default();
-        let lang = serde_json::from_str::<serde_json::Value>(&company_content)
-            .ok()
-            .and_then(|v| v.get("language").and_then(|l| l.as_str().map(String::from)))
-            .unwrap_or_else(|| "de".to_string());
-        format!("/locale/{}.json", lang)
-    } else {
-        "/locale/de.json".to_string()
-    };
+    let locale_path = locale_path();
 
     // Check if input is a .typ file (direct compilation mode)
-    let status = if input.extension().map_or(false, |ext| ext == "typ") {
+    let result = if input.extension().is_some_and(|ext| ext == "typ") {
         // Direct .typ file compilation
         Command::new("typst")
             .args([
@@ -525,15 +1171,14 @@ fn compile_document(
                 &format!("company={}", company_path),
                 "--input",
                 &format!("locale={}", locale_path),
-                &output_path.to_string_lossy(),
             ])
-            .status()
+            .args(render.typst_args())
+            .arg(output_path)
+            .output()
             .context(t("compile", "typst_not_found"))?
     } else {
         // JSON-based compilation with template
-        let doc_type = template
-            .unwrap_or_else(|| detect_document_type(input).unwrap_or("invoice".to_string()));
-        let template_path = format!(".docgen/templates/{}/default.typ", doc_type);
+        let template_path = template_path(input, template, variant)?;
         let data_path = format!("/{}", input.display());
 
         Command::new("typst")
@@ -543,214 +1188,93 @@ fn compile_document(
                 ".",
                 "--font-path",
                 "fonts",
-                &template_path,
+                &template_path.to_string_lossy(),
                 "--input",
                 &format!("data={}", data_path),
                 "--input",
                 &format!("company={}", company_path),
                 "--input",
                 &format!("locale={}", locale_path),
-                &output_path.to_string_lossy(),
             ])
-            .status()
+            .args(render.typst_args())
+            .arg(output_path)
+            .output()
             .context(t("compile", "typst_not_found"))?
     };
 
-    if status.success() {
-        println!("{} {}", "✓".green(), t("compile", "success"));
-
-        // Handle encryption if requested
-        if encrypt {
-            // Check if qpdf is available first
-            encrypt::check_qpdf_available()?;
-
-            println!("{} {}", "→".blue(), "Encrypting PDF...");
-            let encryption_opts = encrypt::prompt_encryption_options()?;
-            let temp_path = output_path.with_extension("pdf.tmp");
-
-            // Rename original to temp
-            std::fs::rename(&output_path, &temp_path)?;
-
-            // Encrypt temp to final output
-            encrypt::encrypt_pdf(&temp_path, &output_path, encryption_opts)?;
-
-            // Remove temp file
-            std::fs::remove_file(&temp_path)?;
-
-            println!("{} {}", "✓".green(), "PDF encrypted successfully");
-        }
-
+    if result.status.success() {
         Ok(())
     } else {
-        anyhow::bail!("{}", t("compile", "failed"))
+        let diagnostics = String::from_utf8_lossy(&result.stderr);
+        anyhow::bail!("{}\n{}", t("compile", "failed"), diagnostics.trim_end())
     }
 }
+/// Root-relative locale file (e.g. "/locale/de.json") for the company's language
+fn locale_path() -> String {
+    let company_json_path = Path::new("data/company.json");
 
-fn build_all(path: &Path, output: &Path) -> Result<()> {
-    println!(
-        "{} {}",
-        "→".blue(),
-        tf("build", "building_all", &[&path.display().to_string()])
-    );
-
-    std::fs::create_dir_all(output)?;
-
-    let mut count = 0;
-    let mut errors = 0;
-
-    // Find both .json and .typ files
-    for entry in WalkDir::new(path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| {
-            e.path()
-                .extension()
-                .map_or(false, |ext| ext == "json" || ext == "typ")
-        })
-    {
-        let input = entry.path();
-        let output_file = output
-            .join(input.file_stem().unwrap())
-            .with_extension("pdf");
-
-        match compile_document(input, Some(output_file), None, false) {
-            Ok(_) => count += 1,
-            Err(e) => {
-                println!(
-                    "{} {}: {}",
-                    "✗".red(),
-                    tf("compile", "error_at", &[&input.display().to_string()]),
-                    e
-                );
-                errors += 1;
-            }
-        }
+    // Read language from company.json to determine locale
+    if company_json_path.exists() {
+        let company_content = std::fs::read_to_string(company_json_path).unwrap_or_default();
+        let lang = serde_json::from_str::<serde_json::Value>(&company_content)
+            .ok()
+            .and_then(|v| v.get("language").and_then(|l| l.as_str().map(String::from)))
+            .unwrap_or_else(|| "de".to_string());
+        format!("/locale/{}.json", lang)
+    } else {
+        "/locale/de.json".to_string()
     }
-
-    println!();
-    println!(
-        "{} {}",
-        if errors == 0 {
-            "✓".green()
-        } else {
-            "!".yellow()
-        },
-        tf(
-            "build",
-            "documents_created",
-            &[&count.to_string(), &errors.to_string()]
-        )
-    );
-
-    Ok(())
 }
 
-fn watch_directory(path: &Path) -> Result<()> {
-    use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-    use std::sync::mpsc::channel;
-    use std::time::Duration;
-
-    println!(
-        "{} {}",
-        "→".blue(),
-        tf("watch", "watching", &[&path.display().to_string()])
-    );
+/// Template entry point used to compile a JSON document
+///
+/// `--template` wins over the document's own `"template"` field, which wins
+/// over its type. The variant comes from `--variant`, the document's
+/// `"variant"` field, the client's or project's setting in company.json, or
+/// is `default`. Both are resolved by `local_templates::resolve_template`.
+fn template_path(
+    input: &Path,
+    template: Option<String>,
+    variant: Option<String>,
+) -> Result<PathBuf> {
+    let document = std::fs::read_to_string(input)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .unwrap_or_default();
+    let field = |key: &str| document.get(key)?.as_str().map(String::from);
+
+    let name = template
+        .or_else(|| field("template"))
+        .or_else(|| document_type(input))
+        .unwrap_or_else(|| "invoice".to_string());
+    let variant = variant.or_else(|| field("variant")).or_else(|| {
+        let company = std::fs::read_to_string("data/company.json").ok()?;
+        let company = serde_json::from_str(&company).ok()?;
+        let customer = document
+            .get("metadata")
+            .and_then(|metadata| metadata.get("customer_number"))
+            .and_then(|number| number.as_str());
+        local_templates::configured_variant(&company, &name, customer)
+    });
+    local_templates::resolve_template(
+        &name,
+        variant
+            .as_deref()
+            .unwrap_or(local_templates::DEFAULT_VARIANT),
+    )
+}
 
-    let (tx, rx) = channel();
-    let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
-    watcher.watch(path, RecursiveMode::Recursive)?;
-
-    loop {
-        match rx.recv_timeout(Duration::from_secs(1)) {
-            Ok(Ok(event)) => {
-                if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
-                    for path in event.paths {
-                        if path
-                            .extension()
-                            .map_or(false, |ext| ext == "json" || ext == "typ")
-                        {
-                            println!();
-                            println!(
-                                "{} {}: {}",
-                                "⟳".yellow(),
-                                t("watch", "change_detected"),
-                                path.display()
-                            );
-                            let _ = compile_document(&path, None, None, false);
-                        }
-                    }
-                }
-            }
-            Ok(Err(e)) => println!("{}: {}", t("common", "error"), e),
-            Err(_) => {}
-        }
-    }
+/// Type from the document's marker, else guessed from its path
+fn document_type(path: &Path) -> Option<String> {
+    discovery::declared_type(path).or_else(|| detect_document_type(path))
 }
 
 fn detect_document_type(path: &Path) -> Option<String> {
     let s = path.to_string_lossy().to_lowercase();
 
-    // Accounting-Layout Templates
-    if s.contains("invoice") || s.contains("rechnung") || s.contains("/re-") {
-        Some("invoice".to_string())
-    } else if s.contains("offer") || s.contains("angebot") || s.contains("/an-") {
-        Some("offer".to_string())
-    } else if s.contains("letter") || s.contains("brief") || s.contains("/br-") {
-        Some("letter".to_string())
-    } else if s.contains("credit-note")
-        || s.contains("credit_note")
-        || s.contains("gutschrift")
-        || s.contains("/gs-")
-    {
-        Some("credit-note".to_string())
-    } else if s.contains("reminder") || s.contains("mahnung") || s.contains("/m") {
-        Some("reminder".to_string())
-    } else if s.contains("delivery-note")
-        || s.contains("delivery_note")
-        || s.contains("lieferschein")
-        || s.contains("/ls-")
-    {
-        Some("delivery-note".to_string())
-    } else if s.contains("order-confirmation")
-        || s.contains("order_confirmation")
-        || s.contains("auftragsbestätigung")
-        || s.contains("/ab-")
-    {
-        Some("order-confirmation".to_string())
-    } else if s.contains("time-sheet")
-        || s.contains("time_sheet")
-        || s.contains("timesheet")
-        || s.contains("stundenzettel")
-        || s.contains("/ts-")
-    {
-        Some("time-sheet".to_string())
-    } else if s.contains("quotation-request")
-        || s.contains("quotation_request")
-        || s.contains("angebotsanfrage")
-        || s.contains("/anf-")
-    {
-        Some("quotation-request".to_string())
-    }
-    // Document-Layout Templates
-    else if s.contains("credential") || s.contains("zugang") || s.contains("/zd-") {
-        Some("credentials".to_string())
-    } else if s.contains("concept") || s.contains("konzept") || s.contains("/ko-") {
-        Some("concept".to_string())
-    } else if s.contains("documentation") || s.contains("dokumentation") || s.contains("/dok-") {
-        Some("documentation".to_string())
-    } else if s.contains("contract") || s.contains("vertrag") || s.contains("/vtr-") {
-        Some("contract".to_string())
-    } else if s.contains("protocol") || s.contains("protokoll") || s.contains("/prot-") {
-        Some("protocol".to_string())
-    } else if s.contains("specification") || s.contains("spezifikation") || s.contains("/spec-") {
-        Some("specification".to_string())
-    } else if s.contains("proposal") || s.contains("vorschlag") || s.contains("/pro-") {
-        Some("proposal".to_string())
-    } else if s.contains("sla") || s.contains("/sla-") {
-        Some("sla".to_string())
-    } else {
-        None
-    }
+    // Types added with `docgen template new` or by installed packages win;
+    // `template new` refuses names and prefixes the built-in types use
+    document_types::detect(&s).or_else(|| document_types::detect_builtin(&s).map(String::from))
 }
 
 fn show_ai_guide() -> Result<()> {
@@ -774,7 +1298,9 @@ When you run `docgen init my-business`, it creates:
 ```
 my-business/
 ├── data/
-│   ├── docgen.db          # SQLite database (auto-created)
+│   ├── clients.json       # Clients (or docgen.db with SQLite storage)
+│   ├── projects.json      # Projects
+│   ├── counters.json      # Number counters
 │   └── company.json       # Company data & branding
 ├── documents/
 │   ├── invoices/2025/     # Invoice JSON files
@@ -799,6 +1325,14 @@ my-business/
 
 ## DOCUMENT TYPES & WORKFLOWS
 
+The examples below are abridged. Every template ships its own field list and a
+complete sample document:
+
+```bash
+docgen template info invoice            # Fields, types, variants, locales
+docgen template info invoice --sample   # Sample JSON to start from
+```
+
 ### 1. INVOICE (Rechnung) - JSON Workflow
 File: `documents/invoices/2025/RE-2025-001.json`
 
@@ -0,0 +1,409 @@
+// Installable template packages described by a template.json manifest
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use crate::forks;
+use crate::local_templates;
+
+/// Manifest file at the root of a template package
+pub const MANIFEST_FILE: &str = "template.json";
+
+/// Package description in template.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Directory name the template is installed under
+    pub name: String,
+    /// Package version; standard templates have none
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String,
+    /// Document type the template renders (invoice, offer, ...)
+    #[serde(rename = "type")]
+    pub doc_type: String,
+    /// Required docgen version, e.g. ">=0.6, <0.8"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docgen: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON schema of the document data, relative to the package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// Sample document, relative to the package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<String>,
+    /// Entry file compiled for the default variant
+    #[serde(default = "default_entry")]
+    pub entry: String,
+    /// company.json fields the template reads, e.g. "bank_account.iban"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub company_fields: Vec<String>,
+    /// Languages the template's texts support
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locales: Vec<String>,
+    /// Page layout of a new document type: "accounting" or "document"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// Number prefix of a new document type, e.g. "WB" for WB-2025-001
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// Directory below documents/ holding documents of a new type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// Further words in file paths that identify a new document type
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+}
+
+fn default_entry() -> String {
+    format!("{}.typ", local_templates::DEFAULT_VARIANT)
+}
+
+impl Manifest {
+    /// Read the manifest of the template in `dir`, if it has one
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        let manifest = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid manifest: {}", path.display()))?;
+        Ok(Some(manifest))
+    }
+
+    /// Check the manifest against the package contents and this docgen version
+    pub fn validate(&self, dir: &Path) -> Result<()> {
+        if self.name.is_empty()
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            anyhow::bail!(
+                "Invalid template name '{}' in {}: use letters, digits, '-' and '_'",
+                self.name,
+                MANIFEST_FILE
+            );
+        }
+        semver::Version::parse(&self.version).with_context(|| {
+            format!(
+                "Invalid version '{}' in {} (expected e.g. 1.0.0)",
+                self.version, MANIFEST_FILE
+            )
+        })?;
+
+        if let Some(required) = &self.docgen {
+            let requirement = semver::VersionReq::parse(required).with_context(|| {
+                format!(
+                    "Invalid docgen requirement '{}' in {}",
+                    required, MANIFEST_FILE
+                )
+            })?;
+            let current = semver::Version::parse(&local_templates::get_docgen_version())?;
+            if !requirement.matches(&current) {
+                anyhow::bail!(
+                    "Template '{}' {} requires docgen {}, this is {}",
+                    self.name,
+                    self.version,
+                    required,
+                    current
+                );
+            }
+        }
+
+        let files = [
+            ("entry", Some(&self.entry)),
+            ("schema", self.schema.as_ref()),
+            ("sample", self.sample.as_ref()),
+        ];
+        for (field, file) in files {
+            if let Some(file) = file.filter(|file| !is_inside(file)) {
+                anyhow::bail!(
+                    "Invalid {} '{}' in {}: paths must stay inside the package",
+                    field,
+                    file,
+                    MANIFEST_FILE
+                );
+            }
+        }
+        if !self.entry.ends_with(".typ") || !dir.join(&self.entry).is_file() {
+            anyhow::bail!("Entry file '{}' not found in the package", self.entry);
+        }
+        for (field, file) in [("schema", &self.schema), ("sample", &self.sample)] {
+            if let Some(file) = file {
+                let content = fs::read_to_string(dir.join(file)).with_context(|| {
+                    format!("{} file '{}' not found in the package", field, file)
+                })?;
+                serde_json::from_str::<serde_json::Value>(&content)
+                    .with_context(|| format!("{} file '{}' is not valid JSON", field, file))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where to install a package
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    /// templates/ of the current project
+    Project,
+    /// The user-global templates directory, shared by all projects
+    User,
+}
+
+impl Scope {
+    pub fn dir(self) -> Result<PathBuf> {
+        match self {
+            Scope::Project => Ok(local_templates::get_custom_templates_dir()),
+            Scope::User => local_templates::get_user_templates_dir()
+                .context("No user configuration directory on this system"),
+        }
+    }
+}
+
+/// Whether `dir` holds an installed package rather than a fork
+///
+/// Forks keep the manifest of the standard template they were made from.
+fn is_package(dir: &Path) -> Result<bool> {
+    Ok(Manifest::load(dir)?.is_some() && !dir.join(forks::FORK_DIR).exists())
+}
+
+/// An installed package
+pub struct Installed {
+    pub manifest: Manifest,
+    pub dir: PathBuf,
+}
+
+/// Install a package from a directory, a .tar.gz archive or a git repository
+///
+/// Git sources are URLs ending in `.git` (or starting with `git@`/`git+`),
+/// optionally followed by `#subdir`. An existing package of the same name
+/// is only replaced with `force`.
+pub fn install(source: &str, scope: Scope, force: bool) -> Result<Installed> {
+    let staging = PathBuf::from(".docgen/tmp").join(format!("install-{}", std::process::id()));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+
+    let result = fetch(source, &staging).and_then(|dir| install_dir(&dir, scope, force));
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// Get the package directory for `source`, downloading or unpacking into `staging`
+fn fetch(source: &str, staging: &Path) -> Result<PathBuf> {
+    let path = Path::new(source);
+    if path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+
+    if source.ends_with(".tar.gz") || source.ends_with(".tgz") {
+        let file =
+            fs::File::open(path).with_context(|| format!("Failed to open archive {}", source))?;
+        tar::Archive::new(flate2::read::GzDecoder::new(file))
+            .unpack(staging)
+            .with_context(|| format!("Failed to unpack {}", source))?;
+        return package_root(staging);
+    }
+
+    let (url, subdir) = source.split_once('#').unwrap_or((source, ""));
+    if url.ends_with(".git") || url.starts_with("git@") || url.starts_with("git+") {
+        if !is_inside(subdir) {
+            anyhow::bail!("Invalid path '{}' in git source", subdir);
+        }
+        let url = url.trim_start_matches("git+");
+        let checkout = staging.join("checkout");
+        // "--" keeps URLs like "--upload-pack=..." from being read as options
+        let output = Command::new("git")
+            .args(["clone", "--depth", "1", "--quiet", "--", url])
+            .arg(&checkout)
+            .output()
+            .context("Failed to run git. Is it installed?")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "git clone {} failed:\n{}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            );
+        }
+        return package_root(&checkout.join(subdir));
+    }
+
+    anyhow::bail!(
+        "Cannot install '{}': expected a directory, a .tar.gz archive or a git URL",
+        source
+    )
+}
+
+/// Whether a relative path from a package stays inside it
+fn is_inside(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// The directory holding the manifest: `dir` itself or its only subdirectory
+fn package_root(dir: &Path) -> Result<PathBuf> {
+    if dir.join(MANIFEST_FILE).exists() {
+        return Ok(dir.to_path_buf());
+    }
+    let subdirs: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("{} not found", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.path())
+        .collect();
+    match subdirs.as_slice() {
+        [only] if only.join(MANIFEST_FILE).exists() => Ok(only.clone()),
+        _ => anyhow::bail!("No {} found in the package", MANIFEST_FILE),
+    }
+}
+
+fn install_dir(source: &Path, scope: Scope, force: bool) -> Result<Installed> {
+    let manifest = Manifest::load(source)?
+        .with_context(|| format!("No {} in {}", MANIFEST_FILE, source.display()))?;
+    manifest.validate(source)?;
+
+    let dest = scope.dir()?.join(&manifest.name);
+    if dest.exists() {
+        if !is_package(&dest)? {
+            anyhow::bail!(
+                "{} exists and is not an installed package (a fork?). Remove it first.",
+                dest.display()
+            );
+        }
+        if !force {
+            anyhow::bail!(
+                "Template '{}' is already installed in {}. Use --force to replace it.",
+                manifest.name,
+                dest.display()
+            );
+        }
+        fs::remove_dir_all(&dest)?;
+    }
+
+    local_templates::copy_template(source, &dest)
+        .with_context(|| format!("Failed to install template '{}'", manifest.name))?;
+    // Never carry a source checkout's git metadata along
+    let _ = fs::remove_dir_all(dest.join(".git"));
+    Ok(Installed {
+        manifest,
+        dir: dest,
+    })
+}
+
+/// Remove an installed package
+pub fn uninstall(name: &str, scope: Scope) -> Result<PathBuf> {
+    let dir = scope.dir()?.join(name);
+    if !dir.exists() || name.contains(['/', '\\']) || name.starts_with('.') {
+        anyhow::bail!(
+            "Template '{}' is not installed in {}",
+            name,
+            scope.dir()?.display()
+        );
+    }
+    if !is_package(&dir)? {
+        anyhow::bail!(
+            "{} was not installed by docgen (a fork?). Remove it by hand if you really want to.",
+            dir.display()
+        );
+    }
+    fs::remove_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Installed packages in a scope, sorted by name
+pub fn installed(scope: Scope) -> Vec<Installed> {
+    let Ok(dir) = scope.dir() else {
+        return Vec::new();
+    };
+    let mut packages: Vec<Installed> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| is_package(&entry.path()).unwrap_or(false))
+        .filter_map(|entry| {
+            let manifest = Manifest::load(&entry.path()).ok()??;
+            Some(Installed {
+                manifest,
+                dir: entry.path(),
+            })
+        })
+        .collect();
+    packages.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+    packages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn package(manifest: &str) -> TempDir {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join(MANIFEST_FILE), manifest).unwrap();
+        fs::write(tmp.path().join("invoice.typ"), "").unwrap();
+        fs::write(tmp.path().join("sample.json"), "{}").unwrap();
+        tmp
+    }
+
+    #[test]
+    fn test_manifest_validation() {
+        let valid = r#"{"name": "acme-invoice", "version": "1.0.0", "type": "invoice",
+                        "docgen": ">=0.6", "entry": "invoice.typ", "sample": "sample.json"}"#;
+        let tmp = package(valid);
+        let manifest = Manifest::load(tmp.path()).unwrap().unwrap();
+        manifest.validate(tmp.path()).unwrap();
+
+        for (broken, error) in [
+            (
+                valid.replace("acme-invoice", "../x"),
+                "Invalid template name",
+            ),
+            (valid.replace("1.0.0", "one"), "Invalid version"),
+            (valid.replace(">=0.6", ">=99"), "requires docgen >=99"),
+            (valid.replace("invoice.typ", "missing.typ"), "Entry file"),
+            (valid.replace("sample.json", "nope.json"), "sample file"),
+            (
+                valid.replace("invoice.typ", "../../x.typ"),
+                "Invalid entry '../../x.typ'",
+            ),
+            (
+                valid.replace("invoice.typ", "/etc/x.typ"),
+                "paths must stay inside the package",
+            ),
+            (
+                valid.replace("sample.json", "a/../../sample.json"),
+                "Invalid sample",
+            ),
+        ] {
+            let tmp = package(&broken);
+            let manifest = Manifest::load(tmp.path()).unwrap().unwrap();
+            let err = manifest.validate(tmp.path()).unwrap_err().to_string();
+            assert!(err.contains(error), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_git_subdir_is_checked_before_cloning() {
+        let tmp = TempDir::new().unwrap();
+        for source in [
+            "https://example.invalid/t.git#../..",
+            "https://example.invalid/t.git#/etc",
+        ] {
+            let err = fetch(source, tmp.path()).unwrap_err().to_string();
+            assert!(err.contains("Invalid path"), "{}", err);
+        }
+        assert!(fs::read_dir(tmp.path()).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_entry_defaults_to_default_typ() {
+        let manifest: Manifest =
+            serde_json::from_str(r#"{"name": "a", "version": "1.0.0", "type": "offer"}"#).unwrap();
+        assert_eq!(manifest.entry, "default.typ");
+    }
+}
@@ -0,0 +1,330 @@
+// Local preview server for `docgen watch --serve`
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::commands::build::Outcome;
+
+/// How long a reload event may block on a stalled page
+const EVENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Latest build result of one document
+struct Entry {
+    pdf: PathBuf,
+    error: Option<String>,
+    /// Bumped on every rebuild so the browser doesn't show a cached PDF
+    version: u64,
+}
+
+#[derive(Default)]
+struct State {
+    documents: BTreeMap<String, Entry>,
+    /// Error of the whole build (e.g. an output collision)
+    build_error: Option<String>,
+    /// Open server-sent event streams
+    listeners: Vec<TcpStream>,
+}
+
+/// Serves a document list and PDF pages on 127.0.0.1 and tells open pages
+/// to reload after every build
+#[derive(Clone)]
+pub struct Preview {
+    state: Arc<Mutex<State>>,
+    port: u16,
+}
+
+impl Preview {
+    /// Bind to localhost and serve in a background thread
+    pub fn start(port: u16) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .with_context(|| format!("Cannot listen on 127.0.0.1:{}", port))?;
+        let preview = Self {
+            state: Arc::default(),
+            port: listener.local_addr()?.port(),
+        };
+        let server = preview.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let server = server.clone();
+                thread::spawn(move || {
+                    let _ = server.handle(stream);
+                });
+            }
+        });
+        Ok(preview)
+    }
+
+    /// Record a build pass and push a reload event to all open pages
+    pub fn update(&self, outcome: &Outcome) {
+        let mut state = self.state.lock().unwrap();
+        let mut documents = BTreeMap::new();
+        for (input, pdf) in &outcome.documents {
+            let key = key(input);
+            let previous = state.documents.remove(&key);
+            let (error, version) = previous.map_or((None, 0), |e| (e.error, e.version));
+            documents.insert(
+                key,
+                Entry {
+                    pdf: pdf.clone(),
+                    error,
+                    version,
+                },
+            );
+        }
+        for (input, _) in &outcome.built {
+            if let Some(entry) = documents.get_mut(&key(input)) {
+                entry.error = None;
+                entry.version += 1;
+            }
+        }
+        state.build_error = None;
+        for (input, error) in &outcome.failed {
+            match documents.get_mut(&key(input)) {
+                Some(entry) => entry.error = Some(error.clone()),
+                None => state.build_error = Some(error.clone()),
+            }
+        }
+        state.documents = documents;
+        let mut listeners = std::mem::take(&mut state.listeners);
+        drop(state);
+
+        // Write without the lock so a stalled page cannot block requests
+        listeners.retain_mut(|stream| stream.write_all(b"data: reload\n\n").is_ok());
+        self.state.lock().unwrap().listeners.extend(listeners);
+    }
+
+    fn handle(&self, mut stream: TcpStream) -> Result<()> {
+        let mut request_line = String::new();
+        let mut reader = BufReader::new(stream.try_clone()?);
+        reader.read_line(&mut request_line)?;
+        let mut host = None;
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 2 {
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("host") {
+                    host = Some(value.trim().to_string());
+                }
+            }
+            header.clear();
+        }
+        // Pages of other sites may resolve their own host name to 127.0.0.1
+        if !host.is_some_and(|host| allowed_host(&host, self.port)) {
+            return respond(&mut stream, "403 Forbidden", "text/plain", b"forbidden");
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+        if method != "GET" {
+            return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"");
+        }
+        let path = percent_decode(target.split('?').next().unwrap_or("/"));
+
+        if path == "/events" {
+            stream.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+                  Cache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+            )?;
+            stream.set_write_timeout(Some(EVENT_TIMEOUT))?;
+            self.state.lock().unwrap().listeners.push(stream);
+            return Ok(());
+        }
+
+        let state = self.state.lock().unwrap();
+        if path == "/" {
+            let page = index_page(&state);
+            drop(state);
+            return respond(
+                &mut stream,
+                "200 OK",
+                "text/html; charset=utf-8",
+                page.as_bytes(),
+            );
+        }
+        if let Some(document) = path.strip_prefix("/view/") {
+            if let Some(entry) = state.documents.get(document) {
+                let page = document_page(document, entry);
+                drop(state);
+                return respond(
+                    &mut stream,
+                    "200 OK",
+                    "text/html; charset=utf-8",
+                    page.as_bytes(),
+                );
+            }
+        }
+        if let Some(document) = path.strip_prefix("/pdf/") {
+            // Only PDFs of known documents are served
+            if let Some(pdf) = state.documents.get(document).map(|e| e.pdf.clone()) {
+                drop(state);
+                if let Ok(bytes) = fs::read(&pdf) {
+                    return respond(&mut stream, "200 OK", "application/pdf", &bytes);
+                }
+                return respond(&mut stream, "404 Not Found", "text/plain", b"not built yet");
+            }
+        }
+        respond(&mut stream, "404 Not Found", "text/plain", b"not found")
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    Ok(())
+}
+
+/// Reloads the page on every build event
+const RELOAD_SCRIPT: &str =
+    "<script>new EventSource('/events').onmessage = () => location.reload();</script>";
+
+const STYLE: &str = "<style>body{font-family:sans-serif;margin:0 1.5rem}\
+    a{text-decoration:none}li{margin:.3rem 0}.failed{color:#b00020}\
+    pre{background:#fff0f0;border-left:4px solid #b00020;padding:1rem;white-space:pre-wrap}\
+    iframe{width:100%;height:calc(100vh - 5rem);border:1px solid #ccc}</style>";
+
+fn index_page(state: &State) -> String {
+    let mut html = format!(
+        "<!doctype html><title>docgen preview</title>{}<h1>Documents</h1>",
+        STYLE
+    );
+    if let Some(error) = &state.build_error {
+        html.push_str(&format!("<pre>{}</pre>", escape(error)));
+    }
+    html.push_str("<ul>");
+    for (document, entry) in &state.documents {
+        let (class, mark) = match entry.error {
+            Some(_) => ("failed", "✗"),
+            None => ("", "✓"),
+        };
+        html.push_str(&format!(
+            "<li class=\"{}\">{} <a href=\"/view/{}\">{}</a></li>",
+            class,
+            mark,
+            percent_encode(document),
+            escape(document)
+        ));
+    }
+    html.push_str("</ul>");
+    html.push_str(RELOAD_SCRIPT);
+    html
+}
+
+fn document_page(document: &str, entry: &Entry) -> String {
+    let body = match &entry.error {
+        Some(error) => format!("<pre>{}</pre>", escape(error)),
+        None => format!(
+            "<iframe src=\"/pdf/{}?v={}\"></iframe>",
+            percent_encode(document),
+            entry.version
+        ),
+    };
+    format!(
+        "<!doctype html><title>{0}</title>{1}<p><a href=\"/\">← Documents</a> · {0}</p>{2}{3}",
+        escape(document),
+        STYLE,
+        body,
+        RELOAD_SCRIPT
+    )
+}
+
+/// Only requests addressed to this server on localhost are answered
+fn allowed_host(host: &str, port: u16) -> bool {
+    let (name, host_port) = host.rsplit_once(':').unwrap_or((host, "80"));
+    matches!(name, "localhost" | "127.0.0.1") && host_port.parse() == Ok(port)
+}
+
+fn key(input: &std::path::Path) -> String {
+    input.to_string_lossy().replace('\\', "/")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Encode everything but unreserved characters and '/'
+fn percent_encode(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_encoding_round_trip() {
+        let path = "documents/angebote/Angebot Müller & Co.json";
+        let encoded = percent_encode(path);
+        assert_eq!(
+            encoded,
+            "documents/angebote/Angebot%20M%C3%BCller%20%26%20Co.json"
+        );
+        assert_eq!(percent_decode(&encoded), path);
+    }
+
+    #[test]
+    fn test_allowed_host() {
+        assert!(allowed_host("localhost:8000", 8000));
+        assert!(allowed_host("127.0.0.1:8000", 8000));
+        assert!(!allowed_host("localhost:8001", 8000));
+        assert!(!allowed_host("evil.example:8000", 8000));
+        assert!(!allowed_host("localhost", 8000));
+        assert!(allowed_host("localhost", 80));
+    }
+
+    #[test]
+    fn test_errors_are_escaped_in_page() {
+        let entry = Entry {
+            pdf: PathBuf::from("output/a.pdf"),
+            error: Some("error: unknown variable <x>".to_string()),
+            version: 0,
+        };
+        let page = document_page("documents/a.json", &entry);
+        assert!(page.contains("unknown variable &lt;x&gt;"));
+        assert!(!page.contains("<iframe"));
+    }
+}
@@ -0,0 +1,235 @@
+// Output formats of compiled documents
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::locale::t;
+
+/// Placeholder typst replaces with the zero-padded page number
+pub const PAGE_PLACEHOLDER: &str = "{0p}";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Pdf,
+    Png,
+    Svg,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::Png => "png",
+            OutputFormat::Svg => "svg",
+        }
+    }
+}
+
+/// Format options shared by compile, build and thumbnail
+#[derive(Debug, Clone, Default, Args)]
+pub struct RenderOptions {
+    /// Output format; png and svg write one file per page (name-01.png, ...)
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+    /// Resolution of PNG output in pixels per inch (typst default: 144)
+    #[arg(long)]
+    pub ppi: Option<u32>,
+    /// Pages to export, e.g. "1" or "1,3-5"
+    #[arg(long)]
+    pub pages: Option<String>,
+}
+
+impl RenderOptions {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(pages) = &self.pages {
+            let valid = !pages.is_empty()
+                && pages.split(',').all(|range| {
+                    let mut bounds = range.splitn(2, '-');
+                    let is_page = |s: Option<&str>| {
+                        s.is_some_and(|s| s.is_empty() || s.chars().all(|c| c.is_ascii_digit()))
+                    };
+                    !range.is_empty()
+                        && is_page(bounds.next())
+                        && is_page(bounds.next().or(Some("")))
+                });
+            if !valid {
+                anyhow::bail!(
+                    "Invalid page selection '{}'. Use page numbers and ranges like 1,3-5",
+                    pages
+                );
+            }
+        }
+        if self.ppi.is_some() && self.format != OutputFormat::Png {
+            anyhow::bail!("--ppi only applies to --format png");
+        }
+        Ok(())
+    }
+
+    /// Extra arguments for `typst compile`
+    pub fn typst_args(&self) -> Vec<String> {
+        let mut args = vec!["--format".to_string(), self.format.extension().to_string()];
+        if let Some(ppi) = self.ppi {
+            args.extend(["--ppi".to_string(), ppi.to_string()]);
+        }
+        if let Some(pages) = &self.pages {
+            args.extend(["--pages".to_string(), pages.clone()]);
+        }
+        args
+    }
+
+    /// Output path for a document whose PDF would be `pdf`
+    ///
+    /// Image formats get a page number placeholder unless a single page is
+    /// selected.
+    pub fn output_path(&self, pdf: &Path) -> PathBuf {
+        let path = pdf.with_extension(self.format.extension());
+        if self.format == OutputFormat::Pdf || self.is_single_page() {
+            return path;
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!(
+            "{}-{}.{}",
+            stem,
+            PAGE_PLACEHOLDER,
+            self.format.extension()
+        ))
+    }
+
+    /// Recorded with the build state, so changing options rebuilds
+    pub fn fingerprint(&self) -> String {
+        self.typst_args().join(" ")
+    }
+
+    fn is_single_page(&self) -> bool {
+        self.pages
+            .as_deref()
+            .is_some_and(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+    }
+}
+
+/// Files written for an output path, one per page for image formats
+pub fn page_files(output: &Path) -> Vec<PathBuf> {
+    let name = output
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let Some((prefix, suffix)) = name.split_once(PAGE_PLACEHOLDER) else {
+        return if output.exists() {
+            vec![output.to_path_buf()]
+        } else {
+            Vec::new()
+        };
+    };
+    let dir = output.parent().unwrap_or(Path::new(""));
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let mut pages: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            name.len() > prefix.len() + suffix.len()
+                && name.starts_with(prefix)
+                && name.ends_with(suffix)
+                && name[prefix.len()..name.len() - suffix.len()]
+                    .chars()
+                    .all(|c| c.is_ascii_digit())
+        })
+        .map(|e| output.with_file_name(e.file_name()))
+        .collect();
+    pages.sort();
+    pages
+}
+
+/// First diagnostic of a render error, skipping the "Compilation failed" header
+///
+/// Errors without diagnostics (a missing file, invalid JSON) are kept whole.
+pub fn first_diagnostic(error: &str) -> &str {
+    let header = t("compile", "failed");
+    error
+        .strip_prefix(header.as_str())
+        .unwrap_or(error)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_else(|| error.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(format: OutputFormat, pages: Option<&str>) -> RenderOptions {
+        RenderOptions {
+            format,
+            ppi: None,
+            pages: pages.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_output_path() {
+        let pdf = Path::new("output/invoices/RE-001.pdf");
+        assert_eq!(
+            options(OutputFormat::Pdf, None).output_path(pdf),
+            PathBuf::from("output/invoices/RE-001.pdf")
+        );
+        assert_eq!(
+            options(OutputFormat::Png, None).output_path(pdf),
+            PathBuf::from("output/invoices/RE-001-{0p}.png")
+        );
+        assert_eq!(
+            options(OutputFormat::Svg, Some("1")).output_path(pdf),
+            PathBuf::from("output/invoices/RE-001.svg")
+        );
+    }
+
+    #[test]
+    fn test_validate_pages() {
+        assert!(options(OutputFormat::Pdf, Some("1,3-5,7-"))
+            .validate()
+            .is_ok());
+        assert!(options(OutputFormat::Pdf, Some("1;3")).validate().is_err());
+        assert!(options(OutputFormat::Pdf, Some("")).validate().is_err());
+        let ppi_for_pdf = RenderOptions {
+            ppi: Some(300),
+            ..RenderOptions::default()
+        };
+        assert!(ppi_for_pdf.validate().is_err());
+    }
+
+    #[test]
+    fn test_page_files() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        for name in ["a-02.png", "a-01.png", "a-notes.png", "b-01.png"] {
+            fs::write(tmp.path().join(name), "").unwrap();
+        }
+        let pages = page_files(&tmp.path().join("a-{0p}.png"));
+        assert_eq!(
+            pages,
+            vec![tmp.path().join("a-01.png"), tmp.path().join("a-02.png")]
+        );
+    }
+
+    #[test]
+    fn test_first_diagnostic() {
+        let error = format!(
+            "{}\nerror: unknown variable: foo\n  ┌─ default.typ:3:1",
+            t("compile", "failed")
+        );
+        assert_eq!(first_diagnostic(&error), "error: unknown variable: foo");
+        assert_eq!(
+            first_diagnostic("Invalid JSON in invoice.json"),
+            "Invalid JSON in invoice.json"
+        );
+        let header = t("compile", "failed");
+        assert_eq!(first_diagnostic(&header), header);
+    }
+}
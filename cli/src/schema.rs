@@ -0,0 +1,214 @@
+// Data fields described by a template's JSON schema (schema.json)
+//
+// Only the subset docgen writes itself is understood: `type`, `properties`,
+// `required`, `items`, `description` and `examples`.
+use serde_json::Value;
+
+/// A leaf field of a document
+#[derive(Debug, PartialEq)]
+pub struct Field {
+    /// Dotted path; array elements appear as `items[]`
+    pub path: String,
+    /// JSON type, e.g. "string" or "array"
+    pub kind: String,
+    /// Required at every level from the document root
+    pub required: bool,
+    pub description: Option<String>,
+    pub example: Option<Value>,
+}
+
+/// All leaf fields of `schema` in schema order
+pub fn fields(schema: &Value) -> Vec<Field> {
+    let mut fields = Vec::new();
+    collect(schema, "", true, &mut fields);
+    fields
+}
+
+fn collect(schema: &Value, path: &str, required: bool, fields: &mut Vec<Field>) {
+    let kind = schema
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("any")
+        .to_string();
+
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        let required_keys: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|keys| keys.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        for (key, property) in properties {
+            let child = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            collect(
+                property,
+                &child,
+                required && required_keys.contains(&key.as_str()),
+                fields,
+            );
+        }
+        return;
+    }
+
+    // Arrays of objects list their elements' fields
+    if let Some(items) = schema
+        .get("items")
+        .filter(|i| i.get("properties").is_some())
+    {
+        collect(items, &format!("{}[]", path), required, fields);
+        return;
+    }
+
+    let example = schema
+        .get("examples")
+        .and_then(Value::as_array)
+        .and_then(|examples| examples.first())
+        .cloned()
+        .or_else(|| {
+            schema
+                .get("items")
+                .and_then(|items| items.get("examples"))
+                .and_then(Value::as_array)
+                .and_then(|examples| examples.first())
+                .map(|example| Value::Array(vec![example.clone()]))
+        });
+    fields.push(Field {
+        path: path.to_string(),
+        kind,
+        required,
+        description: schema
+            .get("description")
+            .and_then(Value::as_str)
+            .map(String::from),
+        example,
+    });
+}
+
+/// `value` reduced to the fields `schema` requires
+pub fn required_only(schema: &Value, value: &Value) -> Value {
+    if let (Some(properties), Some(object)) = (
+        schema.get("properties").and_then(Value::as_object),
+        value.as_object(),
+    ) {
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|keys| keys.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        return object
+            .iter()
+            .filter(|(key, _)| required.contains(&key.as_str()))
+            .map(|(key, child)| {
+                let child = match properties.get(key) {
+                    Some(property) => required_only(property, child),
+                    None => child.clone(),
+                };
+                (key.clone(), child)
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        return array
+            .iter()
+            .map(|item| required_only(items, item))
+            .collect();
+    }
+    value.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fields_flatten_objects_and_arrays() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "metadata": {
+                    "type": "object",
+                    "properties": {
+                        "number": {"type": "string", "examples": ["RE-1"]},
+                        "note": {"type": "string", "description": "Free text"}
+                    },
+                    "required": ["number"]
+                },
+                "salutation": {
+                    "type": "object",
+                    "properties": {"greeting": {"type": "string"}},
+                    "required": ["greeting"]
+                },
+                "items": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {"total": {"type": "number", "examples": [10.5]}},
+                        "required": ["total"]
+                    }
+                },
+                "tags": {"type": "array", "items": {"type": "string", "examples": ["a"]}}
+            },
+            "required": ["metadata", "items"]
+        });
+
+        let summary: Vec<(String, bool)> = fields(&schema)
+            .into_iter()
+            .map(|f| (f.path, f.required))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("metadata.number".to_string(), true),
+                ("metadata.note".to_string(), false),
+                // Required inside an optional object is still optional
+                ("salutation.greeting".to_string(), false),
+                ("items[].total".to_string(), true),
+                ("tags".to_string(), false),
+            ]
+        );
+
+        let fields = fields(&schema);
+        assert_eq!(fields[0].example, Some(json!("RE-1")));
+        assert_eq!(fields[1].description.as_deref(), Some("Free text"));
+        assert_eq!(fields[4].kind, "array");
+        assert_eq!(fields[4].example, Some(json!(["a"])));
+    }
+
+    #[test]
+    fn test_required_only_drops_optional_fields() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "metadata": {
+                    "type": "object",
+                    "properties": {"number": {"type": "string"}, "note": {"type": "string"}},
+                    "required": ["number"]
+                },
+                "items": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {"total": {"type": "number"}, "unit": {"type": "string"}},
+                        "required": ["total"]
+                    }
+                },
+                "closing": {"type": "string"}
+            },
+            "required": ["metadata", "items"]
+        });
+        let sample = json!({
+            "metadata": {"number": "RE-1", "note": "x"},
+            "items": [{"total": 1, "unit": "h"}, {"total": 2}],
+            "closing": "Bye"
+        });
+        assert_eq!(
+            required_only(&schema, &sample),
+            json!({"metadata": {"number": "RE-1"}, "items": [{"total": 1}, {"total": 2}]})
+        );
+    }
+}
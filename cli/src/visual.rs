@@ -0,0 +1,186 @@
+// Pixel comparison of rendered pages for visual regression tests
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Channel difference below which a pixel counts as unchanged (anti-aliasing noise)
+const CHANNEL_THRESHOLD: u8 = 32;
+
+/// Colour of changed pixels in diff images
+const CHANGED: [u8; 4] = [255, 0, 0, 255];
+
+/// An RGBA image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    /// Decode a PNG of any colour type to 8-bit RGBA
+    pub fn load(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .with_context(|| format!("Invalid PNG: {}", path.display()))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .with_context(|| format!("Invalid PNG: {}", path.display()))?;
+        let bytes = &buffer[..info.buffer_size()];
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => bytes
+                .chunks_exact(4)
+                .map(|p| [p[0], p[1], p[2], p[3]])
+                .collect(),
+            png::ColorType::Rgb => bytes
+                .chunks_exact(3)
+                .map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => bytes
+                .chunks_exact(2)
+                .map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => bytes.iter().map(|&g| [g, g, g, 255]).collect(),
+            png::ColorType::Indexed => {
+                anyhow::bail!("Unsupported PNG colour type: {}", path.display())
+            }
+        };
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels.concat())?;
+        Ok(())
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        (x < self.width && y < self.height).then(|| self.pixels[(y * self.width + x) as usize])
+    }
+}
+
+/// Result of comparing a page with its baseline
+pub struct Comparison {
+    /// Pixels that changed, including those outside the smaller image
+    pub changed: usize,
+    pub total: usize,
+    /// The baseline faded, with changed pixels in red
+    pub diff: Image,
+}
+
+impl Comparison {
+    /// Share of changed pixels in percent
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.changed as f64 * 100.0 / self.total as f64
+    }
+}
+
+/// Compare `actual` with `baseline` pixel by pixel
+///
+/// Images of different sizes are compared on the larger canvas; pixels
+/// only one of them covers count as changed.
+pub fn compare(baseline: &Image, actual: &Image) -> Comparison {
+    let width = baseline.width.max(actual.width);
+    let height = baseline.height.max(actual.height);
+    let mut changed = 0;
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let before = baseline.pixel(x, y);
+            let same = match (before, actual.pixel(x, y)) {
+                (Some(a), Some(b)) => a
+                    .iter()
+                    .zip(b.iter())
+                    .all(|(a, b)| a.abs_diff(*b) < CHANNEL_THRESHOLD),
+                _ => false,
+            };
+            if same {
+                pixels.push(faded(before.unwrap_or([255; 4])));
+            } else {
+                changed += 1;
+                pixels.push(CHANGED);
+            }
+        }
+    }
+    Comparison {
+        changed,
+        total: pixels.len(),
+        diff: Image {
+            width,
+            height,
+            pixels,
+        },
+    }
+}
+
+/// Light grey version of a pixel, so changes stand out
+fn faded([r, g, b, _]: [u8; 4]) -> [u8; 4] {
+    let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+    let light = (255 - (255 - luma) / 4) as u8;
+    [light, light, light, 255]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32, color: [u8; 4]) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![color; (width * height) as usize],
+        }
+    }
+
+    #[test]
+    fn test_compare_counts_changed_pixels() {
+        let white = image(4, 4, [255; 4]);
+        assert_eq!(compare(&white, &white).changed, 0);
+
+        // Anti-aliasing noise is ignored
+        let noisy = image(4, 4, [250, 250, 250, 255]);
+        assert_eq!(compare(&white, &noisy).changed, 0);
+
+        let mut marked = white.clone();
+        marked.pixels[5] = [0, 0, 0, 255];
+        let comparison = compare(&white, &marked);
+        assert_eq!(comparison.changed, 1);
+        assert_eq!(comparison.percent(), 100.0 / 16.0);
+        assert_eq!(comparison.diff.pixels[5], CHANGED);
+        assert_eq!(comparison.diff.pixels[0], [255; 4]);
+
+        // A taller page changes the rows only it covers
+        let taller = image(4, 6, [255; 4]);
+        let comparison = compare(&white, &taller);
+        assert_eq!((comparison.changed, comparison.total), (8, 24));
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("page.png");
+        let mut page = image(3, 2, [255; 4]);
+        page.pixels[4] = [10, 20, 30, 255];
+        page.save(&path).unwrap();
+        assert_eq!(Image::load(&path).unwrap(), page);
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+use crate::common::{docgen_binary, setup_data_project};
+
+fn setup_test_project() -> TempDir {
+    let tmp = setup_data_project();
+    let data_dir = tmp.path().join("data");
+    fs::write(data_dir.join("company.json"), r#"{"name": "Test GmbH"}"#).unwrap();
+
+    let invoices = tmp.path().join("documents/invoices");
+    fs::create_dir_all(&invoices).unwrap();
+    for number in ["RE-2025-001", "RE-2025-002", "RE-2025-041", "RE-2025-042"] {
+        fs::write(
+            invoices.join(format!("{}.json", number)),
+            format!(r#"{{"metadata": {{"invoice_number": "{}"}}}}"#, number),
+        )
+        .unwrap();
+        fs::write(invoices.join(format!("{}.pdf", number)), number).unwrap();
+    }
+
+    tmp
+}
+
+fn docgen(tmp: &TempDir, args: &[&str]) -> std::process::Output {
+    Command::new(docgen_binary())
+        .args(args)
+        .current_dir(tmp.path())
+        .output()
+        .expect("Failed to execute docgen")
+}
+
+fn issue(tmp: &TempDir, number: &str) -> std::process::Output {
+    let json = format!("documents/invoices/{}.json", number);
+    let pdf = format!("documents/invoices/{}.pdf", number);
+    docgen(tmp, &["issue", &json, "--pdf", &pdf])
+}
+
+#[test]
+fn test_audit_detects_edited_issued_document() {
+    let tmp = setup_test_project();
+
+    for number in ["RE-2025-001", "RE-2025-002"] {
+        let output = issue(&tmp, number);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let output = docgen(&tmp, &["audit"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("2 issued document(s) verified"));
+
+    // Issuing the same number twice is refused
+    let output = issue(&tmp, "RE-2025-001");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already issued"));
+
+    // Editing an issued document and its PDF is reported
+    let invoices = tmp.path().join("documents/invoices");
+    fs::write(
+        invoices.join("RE-2025-002.json"),
+        r#"{"metadata": {"invoice_number": "RE-2025-002"}, "note": "changed"}"#,
+    )
+    .unwrap();
+    fs::remove_file(invoices.join("RE-2025-001.pdf")).unwrap();
+
+    let output = docgen(&tmp, &["audit"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("RE-2025-002.json was edited after it was issued"));
+    assert!(stdout.contains("RE-2025-001: PDF documents/invoices/RE-2025-001.pdf is missing"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Audit found 2 problem(s)"));
+}
+
+#[test]
+fn test_audit_with_untouched_counters() {
+    let tmp = setup_test_project();
+
+    // The journal starts mid-year; the counters were never bumped
+    for number in ["RE-2025-041", "RE-2025-042"] {
+        let output = issue(&tmp, number);
+        assert!(output.status.success());
+    }
+    let output = docgen(&tmp, &["audit"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(!stdout.contains("counter"));
+    assert!(!stdout.contains("missing"));
+}
@@ -0,0 +1,382 @@
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+use crate::common::{docgen_binary, setup_company_project};
+
+fn setup_test_project() -> TempDir {
+    let tmp = setup_company_project();
+    fs::create_dir_all(tmp.path().join("documents/invoices")).unwrap();
+    tmp
+}
+
+fn docgen(tmp: &TempDir, args: &[&str]) -> std::process::Output {
+    Command::new(docgen_binary())
+        .args(args)
+        .current_dir(tmp.path())
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .expect("Failed to execute docgen")
+}
+
+#[test]
+fn test_build_reports_failures_in_order() {
+    let tmp = setup_test_project();
+    // Not valid JSON, so these fail whether or not typst is installed
+    for name in ["c", "a", "b"] {
+        fs::write(
+            tmp.path().join(format!("documents/invoices/{}.json", name)),
+            "{",
+        )
+        .unwrap();
+    }
+
+    let output = docgen(&tmp, &["build", "--jobs", "3"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let a = stdout.find("Compiling documents/invoices/a.json").unwrap();
+    let b = stdout.find("Compiling documents/invoices/b.json").unwrap();
+    let c = stdout.find("Compiling documents/invoices/c.json").unwrap();
+    assert!(a < b && b < c, "{}", stdout);
+    assert!(stdout.contains("Failed documents:"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("3 documents failed"));
+
+    // Templates were synced once for the whole build
+    assert!(tmp.path().join(".docgen/templates/invoice").exists());
+}
+
+/// A stand-in for typst that writes its arguments to the output file
+/// (page 01 for image formats)
+/// and fails for documents named "broken"
+#[cfg(unix)]
+fn fake_typst(tmp: &TempDir) -> String {
+    crate::common::fake_typst(
+        tmp,
+        "case \"$*\" in *broken*) echo \"error: broken document\" >&2; exit 1;; esac\n\
+         for last; do :; done\n\
+         echo \"$@\" > \"$(echo \"$last\" | sed 's/{0p}/01/')\"\n",
+    )
+}
+
+#[cfg(unix)]
+#[test]
+fn test_build_dot_skips_templates_and_output() {
+    let tmp = TempDir::new().unwrap();
+    let path = fake_typst(&tmp);
+    let project = tmp.path().join("project");
+    let run = |args: &[&str]| {
+        let output = Command::new(docgen_binary())
+            .args(args)
+            .current_dir(if args[0] == "init" {
+                tmp.path()
+            } else {
+                &project
+            })
+            .env("PATH", &path)
+            .env("XDG_CONFIG_HOME", tmp.path().join("config"))
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    run(&["init", "project"]);
+    run(&["template", "fork", "invoice", "--name", "custom-invoice"]);
+    fs::write(
+        project.join("documents/RE-2025-001.json"),
+        r#"{"metadata": {}}"#,
+    )
+    .unwrap();
+    // A stray document in the output directory
+    fs::create_dir_all(project.join("output")).unwrap();
+    fs::write(project.join("output/copy.json"), r#"{"metadata": {}}"#).unwrap();
+
+    // The first build syncs .docgen/templates/ with its samples
+    for _ in 0..2 {
+        let stdout = run(&["build", "."]);
+        assert!(stdout.contains("1 documents created"), "{}", stdout);
+        assert!(!stdout.contains("sample.json"), "{}", stdout);
+        assert!(!stdout.contains("output/copy.json"), "{}", stdout);
+        fs::remove_file(project.join(".docgen/build-state.json")).unwrap();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_build_skips_up_to_date_documents() {
+    let tmp = setup_test_project();
+    let path = fake_typst(&tmp);
+    for name in ["RE-2025-001", "RE-2025-002"] {
+        fs::write(
+            tmp.path().join(format!("documents/invoices/{}.json", name)),
+            r#"{"metadata": {}}"#,
+        )
+        .unwrap();
+    }
+    let build = |args: &[&str]| {
+        let output = Command::new(docgen_binary())
+            .arg("build")
+            .args(args)
+            .current_dir(tmp.path())
+            .env("PATH", &path)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    assert!(build(&[]).contains("2 documents created"));
+    assert!(tmp.path().join(".docgen/build-state.json").exists());
+    assert!(build(&[]).contains("2 documents up to date"));
+
+    // One changed document rebuilds alone
+    fs::write(
+        tmp.path().join("documents/invoices/RE-2025-002.json"),
+        r#"{"metadata": {"changed": true}}"#,
+    )
+    .unwrap();
+    let stdout = build(&[]);
+    assert!(stdout.contains("1 documents created"));
+    assert!(stdout.contains("Compiling documents/invoices/RE-2025-002.json"));
+
+    // company.json is a dependency of every document
+    fs::write(
+        tmp.path().join("data/company.json"),
+        r#"{"name": "Test GmbH", "language": "en", "bank": "new"}"#,
+    )
+    .unwrap();
+    assert!(build(&[]).contains("2 documents created"));
+
+    assert!(build(&["--force"]).contains("2 documents created"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_build_mirrors_source_tree_and_detects_collisions() {
+    let tmp = setup_test_project();
+    let path = fake_typst(&tmp);
+    for year in ["2024", "2025"] {
+        let dir = tmp.path().join("documents/invoices").join(year);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("RE-001.json"), r#"{"metadata": {}}"#).unwrap();
+    }
+    let build = |args: &[&str]| {
+        Command::new(docgen_binary())
+            .arg("build")
+            .args(args)
+            .current_dir(tmp.path())
+            .env("PATH", &path)
+            .env("RUST_BACKTRACE", "0")
+            .output()
+            .unwrap()
+    };
+
+    let output = build(&[]);
+    assert!(output.status.success());
+    assert!(tmp.path().join("output/invoices/2024/RE-001.pdf").exists());
+    assert!(tmp.path().join("output/invoices/2025/RE-001.pdf").exists());
+
+    let output = build(&["--force", "--layout", "{type}/{stem}.pdf"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("output/invoice/RE-001.pdf"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("more than one document"));
+    assert!(!tmp.path().join("output/invoice").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_build_skips_ignored_files_and_partials() {
+    let tmp = setup_test_project();
+    let path = fake_typst(&tmp);
+    let docs = tmp.path().join("documents");
+    fs::create_dir_all(docs.join("drafts")).unwrap();
+    fs::create_dir_all(docs.join("concepts")).unwrap();
+    fs::write(docs.join("invoices/RE-001.json"), r#"{"metadata": {}}"#).unwrap();
+    fs::write(docs.join("invoices/RE-002.json"), r#"{"metadata": {}}"#).unwrap();
+    fs::write(docs.join("drafts/RE-003.json"), r#"{"metadata": {}}"#).unwrap();
+    fs::write(docs.join("invoices/items.json"), r#"[{"pos": 1}]"#).unwrap();
+    fs::write(docs.join("concepts/parts.typ"), "#let note(body) = body\n").unwrap();
+    fs::write(
+        docs.join("concepts/brief.typ"),
+        "// docgen: type=concept\n#import \"parts.typ\": note\n",
+    )
+    .unwrap();
+    fs::write(tmp.path().join(".docgenignore"), "documents/drafts/\n").unwrap();
+
+    let output = Command::new(docgen_binary())
+        .args(["build", "--exclude", "**/RE-002.json"])
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("2 documents created"), "{}", stdout);
+    assert!(tmp.path().join("output/invoices/RE-001.pdf").exists());
+    assert!(tmp.path().join("output/concepts/brief.pdf").exists());
+    assert!(!tmp.path().join("output/concepts/parts.pdf").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watch_rebuilds_dependents_of_company_json() {
+    use std::io::Read;
+    use std::process::Stdio;
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+
+    let tmp = setup_test_project();
+    let path = fake_typst(&tmp);
+    fs::write(
+        tmp.path().join("documents/invoices/RE-001.json"),
+        r#"{"metadata": {}}"#,
+    )
+    .unwrap();
+    let pdf = tmp.path().join("output/invoices/RE-001.pdf");
+
+    let mut watch = Command::new(docgen_binary())
+        .arg("watch")
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let wait_for = |done: &dyn Fn() -> bool| {
+        let start = Instant::now();
+        while !done() && start.elapsed() < Duration::from_secs(10) {
+            sleep(Duration::from_millis(100));
+        }
+    };
+
+    wait_for(&|| pdf.exists());
+    assert!(pdf.exists());
+    fs::remove_file(&pdf).unwrap();
+    sleep(Duration::from_millis(500));
+
+    fs::write(
+        tmp.path().join("data/company.json"),
+        r#"{"name": "Renamed GmbH", "language": "en"}"#,
+    )
+    .unwrap();
+    wait_for(&|| pdf.exists());
+    sleep(Duration::from_millis(800));
+    watch.kill().unwrap();
+    watch.wait().unwrap();
+
+    let mut stdout = String::new();
+    watch
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut stdout)
+        .unwrap();
+    assert!(pdf.exists(), "{}", stdout);
+    // Rebuilt once for the change, without reacting to its own output
+    assert_eq!(stdout.matches("Change:").count(), 1, "{}", stdout);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watch_serve_lists_documents_and_errors() {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+
+    let tmp = setup_test_project();
+    let path = fake_typst(&tmp);
+    let docs = tmp.path().join("documents/invoices");
+    fs::write(docs.join("RE-001.json"), r#"{"metadata": {}}"#).unwrap();
+    fs::write(docs.join("broken.json"), r#"{"metadata": {}}"#).unwrap();
+
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut watch = Command::new(docgen_binary())
+        .args(["watch", "--serve", "--port", &port.to_string()])
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let request = |target: &str, host: &str| -> Option<String> {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).ok()?;
+        write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, host).ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        Some(response)
+    };
+    let get = |target: &str| request(target, &format!("localhost:{}", port));
+    let start = Instant::now();
+    let mut index = None;
+    while start.elapsed() < Duration::from_secs(10) {
+        index = get("/").filter(|page| page.contains("RE-001.json"));
+        if index.is_some() {
+            break;
+        }
+        sleep(Duration::from_millis(100));
+    }
+    let error_page = get("/view/documents/invoices/broken.json");
+    let pdf = get("/pdf/documents/invoices/RE-001.json");
+    let outside = get("/pdf/data/company.json");
+    // DNS rebinding: another site's name resolving to 127.0.0.1
+    let rebound = request("/", &format!("evil.example:{}", port));
+    watch.kill().unwrap();
+    watch.wait().unwrap();
+
+    let index = index.expect("preview server did not list the documents");
+    assert!(index.contains("href=\"/view/documents/invoices/RE-001.json\""));
+    assert!(error_page.unwrap().contains("error: broken document"));
+    assert!(pdf.unwrap().contains("application/pdf"));
+    assert!(outside.unwrap().starts_with("HTTP/1.1 404"));
+    assert!(rebound.unwrap().starts_with("HTTP/1.1 403"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_build_png_pages_and_thumbnails() {
+    let tmp = setup_test_project();
+    let path = fake_typst(&tmp);
+    fs::write(
+        tmp.path().join("documents/invoices/RE-001.json"),
+        r#"{"metadata": {}}"#,
+    )
+    .unwrap();
+    let docgen_ok = |args: &[&str]| {
+        let output = Command::new(docgen_binary())
+            .args(args)
+            .current_dir(tmp.path())
+            .env("PATH", &path)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        assert!(output.status.success(), "{}", stdout);
+        stdout
+    };
+
+    docgen_ok(&["build", "--format", "png", "--ppi", "200"]);
+    let page = tmp.path().join("output/invoices/RE-001-01.png");
+    let args = fs::read_to_string(&page).unwrap();
+    assert!(args.contains("--format png --ppi 200"), "{}", args);
+    assert!(
+        docgen_ok(&["build", "--format", "png", "--ppi", "200"]).contains("1 documents up to date")
+    );
+    // Other options are a different build
+    assert!(docgen_ok(&["build", "--format", "png"]).contains("1 documents created"));
+
+    docgen_ok(&["thumbnail"]);
+    let thumbnail =
+        fs::read_to_string(tmp.path().join("output/thumbnails/invoices/RE-001.png")).unwrap();
+    assert!(thumbnail.contains("--ppi 72 --pages 1"), "{}", thumbnail);
+}
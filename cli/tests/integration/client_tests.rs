@@ -1,36 +1,11 @@
 use std::fs;
-use std::path::PathBuf;
 use std::process::Command;
-use tempfile::TempDir;
-
-fn docgen_binary() -> PathBuf {
-    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    path.push("target");
-    path.push("debug");
-    path.push("docgen");
-    path
-}
-
-fn setup_test_project() -> TempDir {
-    let tmp = TempDir::new().unwrap();
-    let data_dir = tmp.path().join("data");
-    fs::create_dir_all(&data_dir).unwrap();
-
-    // Initialize empty JSON files
-    fs::write(data_dir.join("clients.json"), "[]").unwrap();
-    fs::write(data_dir.join("projects.json"), "[]").unwrap();
-    fs::write(
-        data_dir.join("counters.json"),
-        r#"{"client":0,"invoice":0,"offer":0,"credentials":0,"concept":0,"documentation":0}"#,
-    )
-    .unwrap();
 
-    tmp
-}
+use crate::common::{docgen_binary, setup_data_project};
 
 #[test]
 fn test_client_list_empty() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     let output = Command::new(docgen_binary())
         .arg("client")
@@ -46,7 +21,7 @@ fn test_client_list_empty() {
 
 #[test]
 fn test_client_add() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     let output = Command::new(docgen_binary())
         .arg("client")
@@ -73,7 +48,7 @@ fn test_client_add() {
 
 #[test]
 fn test_client_list_with_clients() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     // Add a client
     Command::new(docgen_binary())
@@ -101,7 +76,7 @@ fn test_client_list_with_clients() {
 
 #[test]
 fn test_client_add_multiple() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     // Add first client
     Command::new(docgen_binary())
@@ -136,7 +111,7 @@ fn test_client_add_multiple() {
 
 #[test]
 fn test_client_add_without_name_fails() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     let output = Command::new(docgen_binary())
         .arg("client")
@@ -152,7 +127,7 @@ fn test_client_add_without_name_fails() {
 
 #[test]
 fn test_client_show() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     // Add a client
     Command::new(docgen_binary())
@@ -181,7 +156,7 @@ fn test_client_show() {
 
 #[test]
 fn test_json_file_format() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     // Add a client
     Command::new(docgen_binary())
@@ -210,7 +185,7 @@ fn test_json_file_format() {
 
 #[test]
 fn test_client_delete() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     // Add two clients
     Command::new(docgen_binary())
@@ -253,7 +228,7 @@ fn test_client_delete() {
 
 #[test]
 fn test_client_delete_with_projects_fails() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     // Add a client
     Command::new(docgen_binary())
@@ -293,3 +268,182 @@ fn test_client_delete_with_projects_fails() {
     let clients: Vec<serde_json::Value> = serde_json::from_str(&clients_json).unwrap();
     assert_eq!(clients.len(), 1);
 }
+
+#[test]
+fn test_client_import_csv_skips_duplicates() {
+    let tmp = setup_data_project();
+
+    Command::new(docgen_binary())
+        .args(["client", "add", "--name", "Existing Client"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+
+    fs::write(
+        tmp.path().join("kunden.csv"),
+        "Kunde;Firma;Straße;PLZ;Ort;E-Mail\n\
+         Max Muster;Muster GmbH;Hauptstraße 12;12345;Berlin;max@muster.de\n\
+         Erika Beispiel;Beispiel AG;Ringweg 3;80331;München;erika@beispiel.de\n\
+         Max M.;MUSTER GMBH;;;;\n",
+    )
+    .unwrap();
+
+    // Dry run writes nothing
+    let output = Command::new(docgen_binary())
+        .args(["client", "import", "kunden.csv", "--dry-run"])
+        .current_dir(tmp.path())
+        .output()
+        .expect("Failed to execute import");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2 client(s) would be imported, 1 skipped"));
+    let clients_json = fs::read_to_string(tmp.path().join("data/clients.json")).unwrap();
+    let clients: Vec<serde_json::Value> = serde_json::from_str(&clients_json).unwrap();
+    assert_eq!(clients.len(), 1);
+
+    let output = Command::new(docgen_binary())
+        .args(["client", "import", "kunden.csv"])
+        .current_dir(tmp.path())
+        .output()
+        .expect("Failed to execute import");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("duplicate of K-002"));
+
+    let clients_json = fs::read_to_string(tmp.path().join("data/clients.json")).unwrap();
+    let clients: Vec<serde_json::Value> = serde_json::from_str(&clients_json).unwrap();
+    assert_eq!(clients.len(), 3);
+    assert_eq!(clients[1]["number"], 2);
+    assert_eq!(clients[1]["company"], "Muster GmbH");
+    assert_eq!(clients[1]["house_number"], "12");
+    assert_eq!(clients[2]["number"], 3);
+    assert_eq!(clients[2]["city"], "München");
+}
+
+#[test]
+fn test_client_export_vcf_roundtrip() {
+    let tmp = setup_data_project();
+
+    fs::write(
+        tmp.path().join("contacts.json"),
+        r#"[{"name": "Sarah Schmidt", "company": "DataFlow AG", "email": "sarah@dataflow.example", "city": "Berlin"}]"#,
+    )
+    .unwrap();
+    let output = Command::new(docgen_binary())
+        .args(["client", "import", "contacts.json"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = Command::new(docgen_binary())
+        .args([
+            "client",
+            "export",
+            "--format",
+            "vcf",
+            "-o",
+            "out/contacts.vcf",
+        ])
+        .current_dir(tmp.path())
+        .output()
+        .expect("Failed to execute export");
+    assert!(output.status.success());
+    let vcf = fs::read_to_string(tmp.path().join("out/contacts.vcf")).unwrap();
+    assert!(vcf.contains("FN:Sarah Schmidt"));
+    assert!(vcf.contains("X-DOCGEN-NUMBER:K-001"));
+
+    // Importing the export again is detected as duplicate
+    let output = Command::new(docgen_binary())
+        .args(["client", "import", "out/contacts.vcf"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("duplicate of K-001"));
+}
+
+#[test]
+fn test_concurrent_client_add_assigns_unique_numbers() {
+    let tmp = setup_data_project();
+
+    let children: Vec<_> = (0..8)
+        .map(|i| {
+            Command::new(docgen_binary())
+                .arg("client")
+                .arg("add")
+                .arg("--name")
+                .arg(format!("Parallel {}", i))
+                .current_dir(tmp.path())
+                .stdout(std::process::Stdio::null())
+                .spawn()
+                .expect("Failed to spawn docgen")
+        })
+        .collect();
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+
+    let clients_json = fs::read_to_string(tmp.path().join("data/clients.json")).unwrap();
+    let clients: Vec<serde_json::Value> = serde_json::from_str(&clients_json).unwrap();
+    assert_eq!(clients.len(), 8);
+    let mut numbers: Vec<i64> = clients
+        .iter()
+        .map(|c| c["number"].as_i64().unwrap())
+        .collect();
+    numbers.sort();
+    assert_eq!(numbers, (1..=8).collect::<Vec<_>>());
+
+    let counters_json = fs::read_to_string(tmp.path().join("data/counters.json")).unwrap();
+    let counters: serde_json::Value = serde_json::from_str(&counters_json).unwrap();
+    assert_eq!(counters["client"], 8);
+}
+
+#[test]
+fn test_client_export_csv_roundtrip() {
+    let source = setup_data_project();
+    fs::write(
+        source.path().join("contacts.json"),
+        r#"[{"name": "Sarah Schmidt", "company": "DataFlow AG", "email": "sarah@dataflow.example", "city": "Berlin"}]"#,
+    )
+    .unwrap();
+    let output = Command::new(docgen_binary())
+        .args(["client", "import", "contacts.json"])
+        .current_dir(source.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = Command::new(docgen_binary())
+        .args(["client", "export", "--format", "csv", "-o", "clients.csv"])
+        .current_dir(source.path())
+        .output()
+        .expect("Failed to execute export");
+    assert!(output.status.success());
+
+    // The export imports into a fresh project with the standard headers
+    let target = setup_data_project();
+    fs::copy(
+        source.path().join("clients.csv"),
+        target.path().join("clients.csv"),
+    )
+    .unwrap();
+    let output = Command::new(docgen_binary())
+        .args(["client", "import", "clients.csv"])
+        .current_dir(target.path())
+        .output()
+        .expect("Failed to execute import");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let clients_json = fs::read_to_string(target.path().join("data/clients.json")).unwrap();
+    let clients: Vec<serde_json::Value> = serde_json::from_str(&clients_json).unwrap();
+    assert_eq!(clients.len(), 1);
+    assert_eq!(clients[0]["name"], "Sarah Schmidt");
+    assert_eq!(clients[0]["company"], "DataFlow AG");
+    assert_eq!(clients[0]["email"], "sarah@dataflow.example");
+    assert_eq!(clients[0]["city"], "Berlin");
+}
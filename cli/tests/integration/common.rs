@@ -0,0 +1,70 @@
+// Helpers shared by the integration tests
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+pub fn docgen_binary() -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("target");
+    path.push("debug");
+    path.push("docgen");
+    path
+}
+
+/// Project with empty client, project and counter stores
+pub fn setup_data_project() -> TempDir {
+    let tmp = TempDir::new().unwrap();
+    let data_dir = tmp.path().join("data");
+    fs::create_dir_all(&data_dir).unwrap();
+
+    fs::write(data_dir.join("clients.json"), "[]").unwrap();
+    fs::write(data_dir.join("projects.json"), "[]").unwrap();
+    fs::write(
+        data_dir.join("counters.json"),
+        r#"{"client":0,"invoice":0,"offer":0,"credentials":0,"concept":0,"documentation":0}"#,
+    )
+    .unwrap();
+
+    tmp
+}
+
+/// Project with an English company.json and a private user config directory
+pub fn setup_company_project() -> TempDir {
+    let tmp = TempDir::new().unwrap();
+    fs::create_dir_all(tmp.path().join("data")).unwrap();
+    fs::write(
+        tmp.path().join("data/company.json"),
+        r#"{"name": "Test GmbH", "language": "en"}"#,
+    )
+    .unwrap();
+    fs::create_dir_all(tmp.path().join("config")).unwrap();
+    tmp
+}
+
+/// Install a shell `script` as `typst` in `<tmp>/bin`
+///
+/// Returns a PATH with the fake first.
+#[cfg(unix)]
+pub fn fake_typst(tmp: &TempDir, script: &str) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin = tmp.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let typst = bin.join("typst");
+    fs::write(&typst, format!("#!/bin/sh\n{}", script)).unwrap();
+    fs::set_permissions(&typst, fs::Permissions::from_mode(0o755)).unwrap();
+    format!("{}:{}", bin.display(), std::env::var("PATH").unwrap())
+}
+
+/// Run docgen in `tmp` with `path` as PATH and `<tmp>/config` as user config directory
+pub fn docgen_with_path(tmp: &TempDir, path: &str, args: &[&str]) -> Output {
+    Command::new(docgen_binary())
+        .args(args)
+        .current_dir(tmp.path())
+        .env("PATH", path)
+        .env("XDG_CONFIG_HOME", tmp.path().join("config"))
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .expect("Failed to execute docgen")
+}
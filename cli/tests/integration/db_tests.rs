@@ -0,0 +1,100 @@
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+use crate::common::{docgen_binary, setup_data_project};
+
+fn setup_test_project() -> TempDir {
+    let tmp = setup_data_project();
+    let data_dir = tmp.path().join("data");
+    fs::write(
+        data_dir.join("company.json"),
+        r#"{"name": "Test GmbH", "storage": {"backend": "json"}}"#,
+    )
+    .unwrap();
+
+    tmp
+}
+
+fn docgen(tmp: &TempDir, args: &[&str]) -> std::process::Output {
+    Command::new(docgen_binary())
+        .args(args)
+        .current_dir(tmp.path())
+        .output()
+        .expect("Failed to execute docgen")
+}
+
+#[test]
+fn test_db_migrate_to_sqlite_and_back() {
+    let tmp = setup_test_project();
+
+    docgen(&tmp, &["client", "add", "--name", "First Client"]);
+    docgen(&tmp, &["project", "add", "K-001", "Website"]);
+
+    let output = docgen(&tmp, &["db", "migrate", "--to", "sqlite"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(tmp.path().join("data/docgen.db").exists());
+    let company = fs::read_to_string(tmp.path().join("data/company.json")).unwrap();
+    assert!(company.contains(r#""backend": "sqlite""#));
+
+    // New clients continue the numbering inside the database
+    let output = docgen(&tmp, &["client", "add", "--name", "Second Client"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("K-002"));
+    let clients_json = fs::read_to_string(tmp.path().join("data/clients.json")).unwrap();
+    let clients: Vec<serde_json::Value> = serde_json::from_str(&clients_json).unwrap();
+    assert_eq!(clients.len(), 1);
+
+    let output = docgen(&tmp, &["project", "list", "K-001"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Website"));
+
+    // Going back refuses to overwrite the old JSON data without --force
+    let output = docgen(&tmp, &["db", "migrate", "--to", "json"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+
+    let output = docgen(&tmp, &["db", "migrate", "--to", "json", "--force"]);
+    assert!(output.status.success());
+    let clients_json = fs::read_to_string(tmp.path().join("data/clients.json")).unwrap();
+    let clients: Vec<serde_json::Value> = serde_json::from_str(&clients_json).unwrap();
+    assert_eq!(clients.len(), 2);
+    assert_eq!(clients[1]["name"], "Second Client");
+}
+
+#[test]
+fn test_migrate_upgrades_legacy_data_files() {
+    let tmp = setup_test_project();
+
+    let output = docgen(&tmp, &["migrate", "--dry-run"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("data/clients.json: version 1 → 2"));
+    assert!(stdout.contains("4 change(s) would be made"));
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("data/clients.json")).unwrap(),
+        "[]"
+    );
+
+    let output = docgen(&tmp, &["migrate"]);
+    assert!(output.status.success());
+    let clients_json = fs::read_to_string(tmp.path().join("data/clients.json")).unwrap();
+    let clients: serde_json::Value = serde_json::from_str(&clients_json).unwrap();
+    assert_eq!(clients["version"], 2);
+    assert!(fs::read_dir(tmp.path().join(".docgen/backups"))
+        .unwrap()
+        .next()
+        .is_some());
+
+    // Upgraded files keep working and stay at version 2
+    docgen(&tmp, &["client", "add", "--name", "After Migration"]);
+    let clients_json = fs::read_to_string(tmp.path().join("data/clients.json")).unwrap();
+    let clients: serde_json::Value = serde_json::from_str(&clients_json).unwrap();
+    assert_eq!(clients["version"], 2);
+    assert_eq!(clients["clients"][0]["name"], "After Migration");
+
+    let output = docgen(&tmp, &["migrate"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("up to date"));
+}
@@ -0,0 +1,93 @@
+// Integration tests for docgen template lint
+use std::fs;
+use tempfile::TempDir;
+
+use crate::common::{docgen_with_path, fake_typst, setup_company_project};
+
+/// Run docgen with a fake typst that renders one SVG page per document
+///
+/// It fails on templates using `invoice_nr`, warns about a missing font,
+/// renders a second page for documents without items and 40 pages for the
+/// long descriptions.
+#[cfg(unix)]
+fn docgen(tmp: &TempDir, args: &[&str]) -> std::process::Output {
+    let path = fake_typst(
+        tmp,
+        r#"entry="$6"
+data=""
+for arg; do
+  case "$arg" in data=/*) data="${arg#data=/}" ;; esac
+  last="$arg"
+done
+if grep -q invoice_nr "$entry"; then
+  echo "error: dictionary does not contain key \"invoice_nr\"" >&2
+  exit 1
+fi
+echo "warning: unknown font family: acme sans" >&2
+echo "<svg/>" > "$(echo "$last" | sed 's/{0p}/1/')"
+if grep -q '"items": \[\]' "$data"; then
+  echo "<svg/>" > "$(echo "$last" | sed 's/{0p}/2/')"
+fi
+if grep -q 'Lorem ipsum' "$data"; then
+  for page in $(seq 2 40); do
+    echo "<svg/>" > "$(echo "$last" | sed "s/{0p}/$page/")"
+  done
+fi
+"#,
+    );
+    docgen_with_path(tmp, &path, args)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_template_lint_reports_failures_overflow_and_warnings() {
+    let tmp = setup_company_project();
+
+    let output = docgen(&tmp, &["template", "lint", "invoice"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("✓ invoice (default)"));
+    assert!(stdout.contains("sample: 1 page"));
+    assert!(stdout.contains("missing optional fields"));
+    assert!(stdout.contains("empty lists: 2 pages (expected at most 1)"));
+    // Long texts may add a page per description, but not dozens
+    assert!(stdout.contains("long descriptions: 40 pages (expected at most"));
+    assert!(stdout.contains("locale de"));
+    assert!(!stdout.contains("locale en"));
+    assert!(stdout.contains("unknown font family: acme sans"));
+
+    // Case data, per-language company files and page renders stay in .docgen/lint/
+    let company = fs::read_to_string(tmp.path().join(".docgen/lint/company-pt.json")).unwrap();
+    assert!(company.contains("\"language\": \"pt\""));
+    assert!(company.contains("Test GmbH"));
+    assert!(tmp
+        .path()
+        .join(".docgen/lint/invoice/default/case-01-1.svg")
+        .exists());
+
+    // A broken fork fails the lint
+    let output = docgen(
+        &tmp,
+        &["template", "fork", "invoice", "--name", "custom-invoice"],
+    );
+    assert!(output.status.success());
+    let entry = tmp.path().join("templates/custom-invoice/default.typ");
+    let source = fs::read_to_string(&entry).unwrap();
+    fs::write(&entry, source.replace("invoice_number", "invoice_nr")).unwrap();
+
+    let output = docgen(&tmp, &["template", "lint", "custom-invoice"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("✗ custom-invoice (default)"));
+    assert!(stdout.contains("sample: dictionary does not contain key \"invoice_nr\""));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("template variant(s) failed"));
+
+    let output = docgen(&tmp, &["template", "lint", "no-such-template"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not found"));
+}
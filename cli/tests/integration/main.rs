@@ -1,5 +1,12 @@
+mod audit_tests;
+mod build_tests;
 mod client_tests;
+mod common;
+mod db_tests;
+mod lint_tests;
 mod project_tests;
+mod template_tests;
+mod visual_tests;
 
 // Integration tests for docgen CLI
 // These tests compile and run the actual binary
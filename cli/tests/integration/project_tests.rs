@@ -1,31 +1,8 @@
 use std::fs;
-use std::path::PathBuf;
 use std::process::Command;
 use tempfile::TempDir;
 
-fn docgen_binary() -> PathBuf {
-    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    path.push("target");
-    path.push("debug");
-    path.push("docgen");
-    path
-}
-
-fn setup_test_project() -> TempDir {
-    let tmp = TempDir::new().unwrap();
-    let data_dir = tmp.path().join("data");
-    fs::create_dir_all(&data_dir).unwrap();
-
-    fs::write(data_dir.join("clients.json"), "[]").unwrap();
-    fs::write(data_dir.join("projects.json"), "[]").unwrap();
-    fs::write(
-        data_dir.join("counters.json"),
-        r#"{"client":0,"invoice":0,"offer":0,"credentials":0,"concept":0,"documentation":0}"#,
-    )
-    .unwrap();
-
-    tmp
-}
+use crate::common::{docgen_binary, setup_data_project};
 
 fn add_test_client(tmp: &TempDir, name: &str) -> String {
     let output = Command::new(docgen_binary())
@@ -52,7 +29,7 @@ fn add_test_client(tmp: &TempDir, name: &str) -> String {
 
 #[test]
 fn test_project_list_empty() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
     let client_id = add_test_client(&tmp, "Test Client");
 
     let output = Command::new(docgen_binary())
@@ -70,7 +47,7 @@ fn test_project_list_empty() {
 
 #[test]
 fn test_project_add() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
     let client_id = add_test_client(&tmp, "Project Test Client");
 
     let output = Command::new(docgen_binary())
@@ -93,7 +70,7 @@ fn test_project_add() {
 
 #[test]
 fn test_project_per_client_numbering() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     // Add two clients
     let client1 = add_test_client(&tmp, "Client One");
@@ -150,7 +127,7 @@ fn test_project_per_client_numbering() {
 
 #[test]
 fn test_project_delete() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
 
     // Add client
     Command::new(docgen_binary())
@@ -203,7 +180,7 @@ fn test_project_delete() {
 
 #[test]
 fn test_project_list_with_projects() {
-    let tmp = setup_test_project();
+    let tmp = setup_data_project();
     let client_id = add_test_client(&tmp, "List Test Client");
 
     // Add some projects
@@ -0,0 +1,568 @@
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+use crate::common::{docgen_with_path, fake_typst, setup_company_project};
+
+fn setup_test_project() -> TempDir {
+    let tmp = setup_company_project();
+    fs::create_dir_all(tmp.path().join("documents/invoices")).unwrap();
+    tmp
+}
+
+/// Run docgen with a fake typst on PATH and a private user config directory
+#[cfg(unix)]
+fn docgen(tmp: &TempDir, args: &[&str]) -> std::process::Output {
+    let path = fake_typst(tmp, "for last; do :; done\necho \"$@\" > \"$last\"\n");
+    docgen_with_path(tmp, &path, args)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_compile_resolves_project_user_and_standard_templates() {
+    let tmp = setup_test_project();
+    let invoice = "documents/invoices/RE-2025-001.json";
+    let pdf = tmp.path().join("documents/invoices/RE-2025-001.pdf");
+    fs::write(tmp.path().join(invoice), r#"{"metadata": {}}"#).unwrap();
+
+    // Standard template by document type
+    let output = docgen(&tmp, &["compile", invoice]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(fs::read_to_string(&pdf)
+        .unwrap()
+        .contains(".docgen/templates/invoice/default.typ"));
+
+    // User-global templates are copied into the project for typst
+    let user = tmp.path().join("config/docgen/templates/letterhead");
+    fs::create_dir_all(&user).unwrap();
+    fs::write(
+        user.join("default.typ"),
+        "#import \"../common/footers.typ\": accounting-footer\n",
+    )
+    .unwrap();
+    fs::write(
+        tmp.path().join(invoice),
+        r#"{"template": "letterhead", "metadata": {}}"#,
+    )
+    .unwrap();
+    assert!(docgen(&tmp, &["compile", invoice]).status.success());
+    assert!(fs::read_to_string(&pdf)
+        .unwrap()
+        .contains(".docgen/user-templates/letterhead/default.typ"));
+    let copy = fs::read_to_string(
+        tmp.path()
+            .join(".docgen/user-templates/letterhead/default.typ"),
+    )
+    .unwrap();
+    assert!(copy.contains("\"/.docgen/templates/common/footers.typ\""));
+
+    // The copy is synced, not recreated on every compile
+    let marker = tmp
+        .path()
+        .join(".docgen/user-templates/letterhead/notes.txt");
+    fs::write(&marker, "kept").unwrap();
+    fs::write(user.join("default.typ"), "// changed\n").unwrap();
+    assert!(docgen(&tmp, &["compile", invoice]).status.success());
+    assert!(marker.exists());
+    assert_eq!(
+        fs::read_to_string(
+            tmp.path()
+                .join(".docgen/user-templates/letterhead/default.typ")
+        )
+        .unwrap(),
+        "// changed\n"
+    );
+
+    // Forks in templates/ take precedence over both
+    let output = docgen(
+        &tmp,
+        &["template", "fork", "invoice", "--name", "letterhead"],
+    );
+    assert!(output.status.success());
+    let fork = fs::read_to_string(tmp.path().join("templates/letterhead/default.typ")).unwrap();
+    assert!(fork.contains("\"/.docgen/templates/common/"));
+    assert!(!fork.contains("\"../common/"));
+    assert!(docgen(&tmp, &["compile", invoice]).status.success());
+    assert!(fs::read_to_string(&pdf)
+        .unwrap()
+        .contains(" templates/letterhead/default.typ"));
+
+    // --template overrides the document's own field
+    assert!(docgen(&tmp, &["compile", invoice, "--template", "offer"])
+        .status
+        .success());
+    assert!(fs::read_to_string(&pdf)
+        .unwrap()
+        .contains(".docgen/templates/offer/default.typ"));
+
+    let output = docgen(&tmp, &["compile", invoice, "--template", "missing"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Template 'missing' not found"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_compile_selects_variants_per_document_and_client() {
+    let tmp = setup_test_project();
+    let invoice = "documents/invoices/RE-2025-001.json";
+    let pdf = tmp.path().join("documents/invoices/RE-2025-001.pdf");
+    let compiled_with = |args: &[&str]| {
+        let output = docgen(&tmp, &[&["compile", invoice], args].concat());
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        fs::read_to_string(&pdf).unwrap()
+    };
+
+    fs::write(
+        tmp.path().join(invoice),
+        r#"{"metadata": {"customer_number": "K-003"}}"#,
+    )
+    .unwrap();
+    assert!(compiled_with(&[]).contains(".docgen/templates/invoice/default.typ"));
+
+    // Embedded variants need no fork
+    assert!(
+        compiled_with(&["--variant", "minimal"]).contains(".docgen/templates/invoice/minimal.typ")
+    );
+
+    // A user-global variant extends the standard invoice
+    let user = tmp.path().join("config/docgen/templates/invoice");
+    fs::create_dir_all(&user).unwrap();
+    fs::write(user.join("english-letterhead.typ"), "").unwrap();
+    fs::write(
+        tmp.path().join("data/company.json"),
+        r#"{"name": "Test GmbH", "language": "en", "templates": {
+            "variant": {"invoice": "minimal"},
+            "clients": {"K-003": {"variant": {"invoice": "english-letterhead"}}}
+        }}"#,
+    )
+    .unwrap();
+    assert!(compiled_with(&[]).contains(".docgen/user-templates/invoice/english-letterhead.typ"));
+
+    // The document's own setting wins over company.json
+    fs::write(
+        tmp.path().join(invoice),
+        r#"{"variant": "minimal", "metadata": {"customer_number": "K-003"}}"#,
+    )
+    .unwrap();
+    assert!(compiled_with(&[]).contains(".docgen/templates/invoice/minimal.typ"));
+
+    let output = docgen(&tmp, &["compile", invoice, "--variant", "fancy"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("no variant 'fancy' (available: default, english-letterhead, minimal)"));
+
+    let output = docgen(&tmp, &["template", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("default, minimal"), "{}", stdout);
+    assert!(stdout.contains("english-letterhead"), "{}", stdout);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_template_sync_keeps_unchanged_files_and_warns_about_edits() {
+    let tmp = setup_test_project();
+    let invoice = "documents/invoices/RE-2025-001.json";
+    fs::write(tmp.path().join(invoice), r#"{"metadata": {}}"#).unwrap();
+    let template = tmp.path().join(".docgen/templates/invoice/default.typ");
+
+    assert!(docgen(&tmp, &["compile", invoice]).status.success());
+    assert!(tmp.path().join(".docgen/template-stamp.json").exists());
+    let modified = fs::metadata(&template).unwrap().modified().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    assert!(docgen(&tmp, &["compile", invoice]).status.success());
+    assert_eq!(
+        fs::metadata(&template).unwrap().modified().unwrap(),
+        modified
+    );
+
+    fs::write(&template, "// local edit").unwrap();
+    let output = docgen(&tmp, &["compile", invoice, "--keep-local"]);
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Keeping locally modified templates: invoice/default.typ"));
+    assert_eq!(fs::read_to_string(&template).unwrap(), "// local edit");
+
+    let output = docgen(&tmp, &["compile", invoice]);
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("were overwritten: invoice/default.typ")
+    );
+    assert_ne!(fs::read_to_string(&template).unwrap(), "// local edit");
+
+    // --force re-extracts everything, including stray files
+    let stray = tmp.path().join(".docgen/templates/invoice/stray.typ");
+    fs::write(&stray, "").unwrap();
+    assert!(docgen(&tmp, &["template", "update", "--force"])
+        .status
+        .success());
+    assert!(!stray.exists());
+    assert!(template.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_template_diff_and_upgrade_merge_upstream_changes() {
+    let tmp = setup_test_project();
+    assert!(docgen(&tmp, &["template", "init"]).status.success());
+    assert!(
+        docgen(&tmp, &["template", "fork", "invoice", "--name", "branded"])
+            .status
+            .success()
+    );
+    let fork = tmp.path().join("templates/branded");
+    assert!(fork.join(".fork/fork.json").exists());
+
+    let output = docgen(&tmp, &["template", "diff", "branded"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is up to date"));
+
+    // Pretend the fork was made from an older invoice without the second header line
+    let base_path = fork.join(".fork/base/default.typ");
+    let current = fs::read_to_string(&base_path).unwrap();
+    let older = current.replacen("// Based on casoon-documents simple.typ template\n", "", 1);
+    fs::write(&base_path, &older).unwrap();
+    fs::write(
+        fork.join("default.typ"),
+        format!("{}// our footer tweak\n", older),
+    )
+    .unwrap();
+
+    let output = docgen(&tmp, &["template", "diff", "branded"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("+// Based on casoon-documents simple.typ template"),
+        "{}",
+        stdout
+    );
+
+    let output = docgen(&tmp, &["template", "upgrade", "branded"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let merged = fs::read_to_string(fork.join("default.typ")).unwrap();
+    assert!(merged.contains("// Based on casoon-documents simple.typ template"));
+    assert!(merged.ends_with("// our footer tweak\n"));
+    let output = docgen(&tmp, &["template", "diff", "branded"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is up to date"));
+
+    // Both sides changed the same line
+    let current = fs::read_to_string(&base_path).unwrap();
+    fs::write(
+        &base_path,
+        current.replacen("CASOON Layout", "Old Layout", 1),
+    )
+    .unwrap();
+    let ours = fs::read_to_string(fork.join("default.typ")).unwrap();
+    fs::write(
+        fork.join("default.typ"),
+        ours.replacen("CASOON Layout", "Branded Layout", 1),
+    )
+    .unwrap();
+    let output = docgen(&tmp, &["template", "upgrade", "branded"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("have conflicts"));
+    let conflicted = fs::read_to_string(fork.join("default.typ")).unwrap();
+    assert!(conflicted.contains("<<<<<<<"));
+    assert!(conflicted.contains("Branded Layout"));
+    assert!(conflicted.contains(">>>>>>>"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_template_install_list_and_uninstall_packages() {
+    let tmp = setup_test_project();
+    let package = tmp.path().join("acme/acme-invoice");
+    fs::create_dir_all(&package).unwrap();
+    fs::write(
+        package.join("template.json"),
+        r#"{"name": "acme-invoice", "version": "1.2.0", "type": "invoice",
+            "docgen": ">=0.6", "entry": "acme.typ", "sample": "sample.json"}"#,
+    )
+    .unwrap();
+    fs::write(
+        package.join("acme.typ"),
+        "#import \"../common/footers.typ\": accounting-footer\n",
+    )
+    .unwrap();
+    fs::write(package.join("sample.json"), r#"{"metadata": {}}"#).unwrap();
+
+    let output = docgen(&tmp, &["template", "install", "acme/acme-invoice"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let installed = fs::read_to_string(tmp.path().join("templates/acme-invoice/acme.typ")).unwrap();
+    assert!(installed.contains("/.docgen/templates/common/footers.typ"));
+
+    // The manifest's entry file is the default variant
+    let invoice = "documents/invoices/RE-2025-001.json";
+    fs::write(
+        tmp.path().join(invoice),
+        r#"{"template": "acme-invoice", "metadata": {}}"#,
+    )
+    .unwrap();
+    assert!(docgen(&tmp, &["compile", invoice]).status.success());
+    assert!(
+        fs::read_to_string(tmp.path().join("documents/invoices/RE-2025-001.pdf"))
+            .unwrap()
+            .contains("templates/acme-invoice/acme.typ")
+    );
+
+    let output = docgen(&tmp, &["template", "install", "acme/acme-invoice"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+
+    // Archives install into the user-global directory with --global
+    let status = Command::new("tar")
+        .args(["czf", "acme.tar.gz", "-C", "acme", "acme-invoice"])
+        .current_dir(tmp.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let output = docgen(&tmp, &["template", "install", "acme.tar.gz", "--global"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(tmp
+        .path()
+        .join("config/docgen/templates/acme-invoice/template.json")
+        .exists());
+
+    let output = docgen(&tmp, &["template", "list", "--installed"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("1.2.0").count(), 2, "{}", stdout);
+    assert!(stdout.contains("config/docgen/templates/acme-invoice/"));
+
+    assert!(docgen(&tmp, &["template", "uninstall", "acme-invoice"])
+        .status
+        .success());
+    assert!(!tmp.path().join("templates/acme-invoice").exists());
+
+    // Forks are not packages
+    docgen(&tmp, &["template", "fork", "offer", "--name", "my-offer"]);
+    let output = docgen(&tmp, &["template", "uninstall", "my-offer"]);
+    assert!(!output.status.success());
+    assert!(tmp.path().join("templates/my-offer").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_template_info_lists_fields_and_prints_sample() {
+    let tmp = setup_test_project();
+
+    let output = docgen(&tmp, &["template", "info", "invoice"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("metadata.invoice_number"), "{}", stdout);
+    assert!(stdout.contains("items[].description"));
+    assert!(stdout.contains("default, minimal"));
+    assert!(stdout.contains("bank_account.iban"));
+
+    let output = docgen(&tmp, &["template", "info", "invoice", "--sample"]);
+    assert!(output.status.success());
+    let sample: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(sample["metadata"]["invoice_number"].is_string());
+
+    // Templates without metadata still show their variants
+    fs::create_dir_all(tmp.path().join("templates/plain")).unwrap();
+    fs::write(tmp.path().join("templates/plain/default.typ"), "").unwrap();
+    let output = docgen(&tmp, &["template", "info", "plain"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Variants:  default"), "{}", stdout);
+    assert!(!docgen(&tmp, &["template", "info", "plain", "--sample"])
+        .status
+        .success());
+
+    assert!(!docgen(&tmp, &["template", "info", "nope"]).status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_template_new_registers_document_type() {
+    let tmp = setup_test_project();
+
+    let output = docgen(
+        &tmp,
+        &[
+            "template",
+            "new",
+            "maintenance-report",
+            "--layout",
+            "accounting",
+            "--title",
+            "Wartungsbericht",
+            "--prefix",
+            "WB",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let dir = tmp.path().join("templates/maintenance-report");
+    for file in [
+        "default.typ",
+        "template.json",
+        "schema.json",
+        "sample.json",
+        "locale.json",
+    ] {
+        assert!(dir.join(file).exists(), "{}", file);
+    }
+    let source = fs::read_to_string(dir.join("default.typ")).unwrap();
+    assert!(source.contains("\"/.docgen/templates/common/accounting-header.typ\""));
+    let labels: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("locale.json")).unwrap()).unwrap();
+    assert_eq!(labels.as_object().unwrap().len(), 7);
+    assert_eq!(labels["fr"]["title"], "Wartungsbericht");
+
+    let company: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(tmp.path().join("data/company.json")).unwrap())
+            .unwrap();
+    assert_eq!(company["numbering"]["prefixes"]["maintenance-report"], "WB");
+
+    // Documents are recognised by directory, prefix and title without a "template" field
+    let sample = docgen(
+        &tmp,
+        &["template", "info", "maintenance-report", "--sample"],
+    )
+    .stdout;
+    fs::create_dir_all(tmp.path().join("documents/maintenance-reports")).unwrap();
+    fs::create_dir_all(tmp.path().join("documents/misc")).unwrap();
+    for document in [
+        "documents/maintenance-reports/first.json",
+        "documents/misc/WB-2025-002.json",
+        "documents/misc/wartungsbericht-halle-3.json",
+    ] {
+        fs::write(tmp.path().join(document), &sample).unwrap();
+    }
+    let output = docgen(&tmp, &["build"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for pdf in [
+        "output/maintenance-reports/first.pdf",
+        "output/misc/WB-2025-002.pdf",
+        "output/misc/wartungsbericht-halle-3.pdf",
+    ] {
+        let args = fs::read_to_string(tmp.path().join(pdf)).unwrap();
+        assert!(
+            args.contains("templates/maintenance-report/default.typ"),
+            "{}: {}",
+            pdf,
+            args
+        );
+    }
+
+    // Standard names and existing types are refused
+    assert!(!docgen(&tmp, &["template", "new", "invoice"])
+        .status
+        .success());
+    assert!(!docgen(&tmp, &["template", "new", "maintenance-report"])
+        .status
+        .success());
+
+    // Names and prefixes the built-in detection uses are refused
+    let output = docgen(&tmp, &["template", "new", "order"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("built-in type 'order-confirmation'"));
+    let output = docgen(&tmp, &["template", "new", "rental-extension"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Prefix 'RE'"));
+    assert!(!tmp.path().join("templates/rental-extension").exists());
+
+    // User-global types get their documents/ directory in new projects
+    let output = docgen(
+        &tmp,
+        &[
+            "template",
+            "new",
+            "acceptance-certificate",
+            "--layout",
+            "document",
+            "--global",
+        ],
+    );
+    assert!(output.status.success());
+    let source = fs::read_to_string(
+        tmp.path()
+            .join("config/docgen/templates/acceptance-certificate/default.typ"),
+    )
+    .unwrap();
+    assert!(source.contains("#let acceptance_certificate("));
+    assert!(docgen(&tmp, &["init", "other"]).status.success());
+    assert!(tmp
+        .path()
+        .join("other/documents/acceptance-certificates")
+        .is_dir());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_common_component_overrides_apply_to_standard_templates() {
+    let tmp = setup_test_project();
+    let invoice = "documents/invoices/RE-2025-001.json";
+    fs::write(tmp.path().join(invoice), r#"{"metadata": {}}"#).unwrap();
+    assert!(docgen(&tmp, &["compile", invoice]).status.success());
+    let footers = tmp.path().join(".docgen/templates/common/footers.typ");
+    let embedded = fs::read_to_string(&footers).unwrap();
+
+    fs::create_dir_all(tmp.path().join("templates/common")).unwrap();
+    fs::write(
+        tmp.path().join("templates/common/footers.typ"),
+        "// our footer\n",
+    )
+    .unwrap();
+    fs::write(tmp.path().join("templates/common/seal.typ"), "// new\n").unwrap();
+
+    let output = docgen(&tmp, &["compile", invoice]);
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&footers).unwrap(), "// our footer\n");
+    assert!(tmp
+        .path()
+        .join(".docgen/templates/common/seal.typ")
+        .exists());
+    // The rest of the tree stays embedded, and overrides are not local edits
+    assert!(tmp
+        .path()
+        .join(".docgen/templates/common/styles.typ")
+        .exists());
+    let output = docgen(&tmp, &["compile", invoice]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Local changes"));
+
+    let output = docgen(&tmp, &["template", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Component Overrides"), "{}", stdout);
+    assert!(stdout.contains("common/footers.typ"));
+    assert!(stdout.contains("common/seal.typ"));
+    assert!(stdout.contains("(new)"));
+    // common/ is not listed as a custom template
+    let custom = stdout.split("Custom Templates").nth(1).unwrap();
+    assert!(!custom.contains("common"), "{}", stdout);
+
+    // Removing the overrides brings the embedded components back
+    fs::remove_dir_all(tmp.path().join("templates/common")).unwrap();
+    let output = docgen(&tmp, &["compile", invoice]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Local changes"));
+    assert_eq!(fs::read_to_string(&footers).unwrap(), embedded);
+    assert!(!tmp
+        .path()
+        .join(".docgen/templates/common/seal.typ")
+        .exists());
+}
@@ -0,0 +1,101 @@
+// Integration tests for docgen test (visual regression)
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+use crate::common::{docgen_with_path, fake_typst, setup_company_project};
+
+fn setup_test_project() -> TempDir {
+    let tmp = setup_company_project();
+    fs::create_dir_all(tmp.path().join("tests/visual/invoices")).unwrap();
+    fs::write(
+        tmp.path().join("tests/visual/invoices/RE-2025-001.json"),
+        r#"{"metadata": {"invoice_number": "RE-2025-001"}}"#,
+    )
+    .unwrap();
+    tmp
+}
+
+/// White 100x100 page with a black box of `size` pixels in the corner
+fn write_page(path: &Path, size: usize) {
+    let mut pixels = vec![255u8; 100 * 100 * 3];
+    for y in 0..size {
+        for x in 0..size {
+            pixels[(y * 100 + x) * 3..(y * 100 + x) * 3 + 3].fill(0);
+        }
+    }
+    let file = fs::File::create(path).unwrap();
+    let mut encoder = png::Encoder::new(file, 100, 100);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&pixels).unwrap();
+}
+
+/// Run docgen with a fake typst that renders `page.png` as the only page
+#[cfg(unix)]
+fn docgen(tmp: &TempDir, args: &[&str]) -> std::process::Output {
+    let path = fake_typst(
+        tmp,
+        &format!(
+            "for last; do :; done\ncp \"{}\" \"$(echo \"$last\" | sed 's/{{0p}}/1/')\"\n",
+            tmp.path().join("page.png").display()
+        ),
+    );
+    docgen_with_path(tmp, &path, args)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_visual_regression_against_baselines() {
+    let tmp = setup_test_project();
+    let page = tmp.path().join("page.png");
+    let baseline = tmp
+        .path()
+        .join("tests/visual/baselines/invoices/RE-2025-001-1.png");
+    let diff = tmp
+        .path()
+        .join(".docgen/test/invoices/RE-2025-001-1.diff.png");
+    write_page(&page, 10);
+
+    // Without baselines the test fails and says how to create them
+    let output = docgen(&tmp, &["test"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--update-baselines"));
+
+    let output = docgen(&tmp, &["test", "--update-baselines"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(baseline.exists());
+
+    let output = docgen(&tmp, &["test"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("invoices/RE-2025-001: 1 page(s)"));
+
+    // 1% of the page changes: fails by default, passes with a higher tolerance
+    write_page(&page, 14);
+    let output = docgen(&tmp, &["test"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(
+        stdout.contains("page 1: 0.96% of pixels changed"),
+        "{}",
+        stdout
+    );
+    assert!(diff.exists());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 of 1 document(s) failed"));
+
+    let output = docgen(&tmp, &["test", "--tolerance", "2"]);
+    assert!(output.status.success());
+    assert!(!diff.exists());
+
+    // Accepting the change replaces the baseline
+    let output = docgen(&tmp, &["test", "--update-baselines"]);
+    assert!(output.status.success());
+    assert_eq!(fs::read(&baseline).unwrap(), fs::read(&page).unwrap());
+    assert!(docgen(&tmp, &["test"]).status.success());
+}
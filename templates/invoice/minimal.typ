@@ -0,0 +1,110 @@
+// Invoice Template (Rechnung) - Minimal variant
+// Same header and address block as default.typ, without salutation,
+// VAT column and sub-items; one-line payment note instead of the closing
+
+#import "../common/footers.typ": accounting-footer
+#import "../common/formatting.typ": format_german_date, format_money
+#import "../common/din5008-address.typ": din5008-address-block
+#import "../common/accounting-header.typ": accounting-header, invoice-metadata
+#import "../common/totals-summary.typ": invoice-totals
+
+// Load data from JSON input
+#let data = json(sys.inputs.data)
+
+// Load company data
+#let company = json("/data/company.json")
+
+#let show-footer = if "metadata" in data and "show_footer" in data.metadata { data.metadata.show_footer } else { true }
+
+#set page(
+  paper: "a4",
+  margin: (left: 50pt, right: 45pt, top: 50pt, bottom: 80pt),
+
+  footer: if show-footer { accounting-footer(company: company) }
+)
+
+#set text(
+  font: "Helvetica",
+  size: 10pt,
+  lang: "de"
+)
+
+// ============================================================================
+// HEADER SECTION
+// ============================================================================
+
+#accounting-header(
+  company: company,
+  metadata_content: invoice-metadata(
+    invoice_number: data.metadata.invoice_number,
+    invoice_date: data.metadata.invoice_date,
+    customer_number: if "customer_number" in data.metadata { data.metadata.customer_number } else { none },
+    performance_period: if "performance_period" in data.metadata { data.metadata.performance_period } else { none },
+    due_date: if "due_date" in data.metadata { data.metadata.due_date } else { none },
+  )
+)
+
+// ============================================================================
+// RECIPIENT ADDRESS (DIN 5008 position at y=160)
+// ============================================================================
+
+#din5008-address-block(
+  company: company,
+  recipient: data.recipient,
+)
+
+#text(weight: "bold")[Rechnung]
+
+#v(10pt)
+
+// ============================================================================
+// ITEMS TABLE SECTION (4-column layout)
+// ============================================================================
+
+#block[
+  #set text(size: 9pt, font: "Helvetica")
+
+  #grid(
+    columns: (35pt, 1fr, 60pt, 80pt),
+    align: (center, left, center, right),
+    row-gutter: 8pt,
+
+    text(weight: "bold")[Pos.],
+    text(weight: "bold")[Bezeichnung],
+    text(weight: "bold")[Menge],
+    text(weight: "bold")[Summe],
+  )
+
+  #v(5pt)
+  #line(length: 100%, stroke: 0.5pt)
+  #v(5pt)
+
+  #grid(
+    columns: (35pt, 1fr, 60pt, 80pt),
+    align: (center, left, center, right),
+    row-gutter: 8pt,
+
+    ..data.items.map(item => (
+      [#item.position],
+      [#item.description],
+      [#item.quantity],
+      [#format_money(item.total.amount)],
+    )).flatten()
+  )
+
+  #v(5pt)
+  #line(length: 100%, stroke: 0.5pt)
+]
+
+#v(5pt)
+
+// Totals section (right-aligned)
+#invoice-totals(
+  subtotal: data.totals.subtotal,
+  vat_breakdown: data.totals.vat_breakdown,
+  total: data.totals.total,
+)
+
+#v(12pt)
+
+Zahlbar ohne Abzug bis zum #format_german_date(data.payment.due_date).
@@ -0,0 +1,76 @@
+// Report Template (Auswertung) - Accounting Layout
+// Generic table report generated by `docgen report ... --format pdf`
+// Expects pre-formatted cells: title, subtitle, columns, rows, totals
+
+// Load data from JSON input
+#import "../common/footers.typ": accounting-footer
+#import "../common/formatting.typ": format_german_date
+#let data = json(sys.inputs.data)
+
+// Load company data
+#let company = json("/data/company.json")
+
+#set page(
+  paper: "a4",
+  flipped: data.columns.len() > 6,
+  margin: (left: 50pt, right: 45pt, top: 50pt, bottom: 80pt),
+
+  footer: accounting-footer(company: company)
+)
+
+#set text(font: "Helvetica", size: 10pt, lang: "de")
+
+// ============================================================================
+// HEADER
+// ============================================================================
+
+#grid(
+  columns: (1fr, auto),
+  align: (left + bottom, right + bottom),
+  [
+    #text(weight: "bold", size: 16pt)[#data.title]
+    #if "subtitle" in data and data.subtitle != none [
+      \ #text(size: 10pt, fill: luma(100))[#data.subtitle]
+    ]
+  ],
+  [
+    #set text(size: 8pt)
+    #company.name\
+    Erstellt am #format_german_date(data.generated_at)
+  ],
+)
+
+#v(5pt)
+#line(length: 100%, stroke: 0.5pt)
+#v(10pt)
+
+// ============================================================================
+// TABLE
+// ============================================================================
+
+#let aligns = data.columns.map(c => if c.align == "right" { right } else { left })
+
+#block[
+  #set text(size: 8pt)
+
+  #table(
+    columns: data.columns.len(),
+    align: (col, row) => aligns.at(col),
+    stroke: (x, y) => if y == 0 { (bottom: 0.5pt) } else { none },
+    inset: 5pt,
+    fill: (x, y) => if y > 0 and calc.even(y) { luma(245) },
+
+    ..data.columns.map(c => [*#c.label*]),
+    ..data.rows.flatten().map(cell => [#cell]),
+
+    ..if "totals" in data and data.totals != none {
+      (table.hline(stroke: 0.5pt),)
+      data.totals.map(cell => [*#cell*])
+    } else { () },
+  )
+]
+
+#if data.rows.len() == 0 [
+  #v(10pt)
+  #text(fill: luma(120))[Keine Dokumente im Auswertungszeitraum.]
+]